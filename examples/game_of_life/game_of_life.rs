@@ -10,12 +10,16 @@
 use std::sync::Arc;
 
 use bevy::{math::IVec2, prelude::Resource};
+use bevy_vulkano::{ComputePass, Dispatch, GpuTimer, SharedMemoryAllocator};
+/// Specialization constants of the compute shader. Local workgroup size can be tuned per device
+/// without recompiling the shader. Defaults to 8x8.
+pub use compute_life_cs::SpecializationConstants as GameOfLifeSpecConstants;
 use rand::Rng;
 use vulkano::{
-    buffer::{BufferUsage, CpuAccessibleBuffer},
+    buffer::{Buffer, BufferAccess, BufferCreateInfo, BufferUsage, CpuAccessibleBuffer, Subbuffer},
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        PrimaryAutoCommandBuffer,
+        DispatchIndirectCommand, PrimaryAutoCommandBuffer,
     },
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
@@ -23,12 +27,16 @@ use vulkano::{
     device::{DeviceOwned, Queue},
     format::Format,
     image::{ImageAccess, ImageUsage, StorageImage},
-    memory::allocator::StandardMemoryAllocator,
-    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    memory::allocator::{AllocationCreateInfo, MemoryUsage},
+    pipeline::{ComputePipeline, Pipeline},
+    shader::ShaderModule,
     sync::GpuFuture,
 };
 use vulkano_util::renderer::DeviceImageView;
 
+/// Workgroup counts read by [`GameOfLifeComputePipeline::compute_indirect`]
+pub type IndirectArgsBuffer = Subbuffer<[DispatchIndirectCommand]>;
+
 /// Pipeline holding double buffered grid & color image.
 /// Grids are used to calculate the state, and color image is used to show the output.
 /// Because each step we determine state in parallel, we need to write the output to
@@ -40,17 +48,23 @@ pub struct GameOfLifeComputePipeline {
     command_buffer_allocator: StandardCommandBufferAllocator,
     descriptor_set_allocator: StandardDescriptorSetAllocator,
     compute_life_pipeline: Arc<ComputePipeline>,
+    /// Writes the workgroup counts of indirect dispatches on the GPU
+    indirect_args_pipeline: Arc<ComputePipeline>,
+    /// Specialization the pipeline was created with, reused when the shader is reloaded
+    spec_constants: GameOfLifeSpecConstants,
+    /// Local workgroup size the pipeline was specialized with
+    local_size: [u32; 2],
     life_in: Arc<CpuAccessibleBuffer<[u32]>>,
     life_out: Arc<CpuAccessibleBuffer<[u32]>>,
     image: DeviceImageView,
 }
 
 fn rand_grid(
-    allocator: &Arc<StandardMemoryAllocator>,
+    allocator: &Arc<SharedMemoryAllocator>,
     size: [u32; 2],
 ) -> Arc<CpuAccessibleBuffer<[u32]>> {
     CpuAccessibleBuffer::from_iter(
-        allocator,
+        &**allocator,
         BufferUsage {
             storage_buffer: true,
             ..BufferUsage::empty()
@@ -65,15 +79,27 @@ fn rand_grid(
 
 impl GameOfLifeComputePipeline {
     pub fn new(
-        allocator: &Arc<StandardMemoryAllocator>,
+        allocator: &Arc<SharedMemoryAllocator>,
         compute_queue: Arc<Queue>,
         size: [u32; 2],
+        spec_constants: GameOfLifeSpecConstants,
     ) -> GameOfLifeComputePipeline {
         let life_in = rand_grid(allocator, size);
         let life_out = rand_grid(allocator, size);
 
         let compute_life_pipeline = {
             let shader = compute_life_cs::load(compute_queue.device().clone()).unwrap();
+            ComputePipeline::new(
+                allocator.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &spec_constants,
+                None,
+                |_| {},
+            )
+            .unwrap()
+        };
+        let indirect_args_pipeline = {
+            let shader = indirect_args_cs::load(compute_queue.device().clone()).unwrap();
             ComputePipeline::new(
                 allocator.device().clone(),
                 shader.entry_point("main").unwrap(),
@@ -85,7 +111,7 @@ impl GameOfLifeComputePipeline {
         };
 
         let image = StorageImage::general_purpose_image_view(
-            allocator,
+            &**allocator,
             compute_queue.clone(),
             size,
             Format::R8G8B8A8_UNORM,
@@ -107,16 +133,56 @@ impl GameOfLifeComputePipeline {
                 allocator.device().clone(),
             ),
             compute_life_pipeline,
+            indirect_args_pipeline,
+            spec_constants,
+            local_size: [spec_constants.local_size_x, spec_constants.local_size_y],
             life_in,
             life_out,
             image,
         }
     }
 
+    /// Rebuilds the simulation pipeline with a reloaded `compute_life.comp` module. The current
+    /// pipeline is kept if the new one can't be created, e.g. because the module is missing `main`.
+    pub fn set_compute_shader(&mut self, shader: &Arc<ShaderModule>) -> anyhow::Result<()> {
+        let entry_point = shader
+            .entry_point("main")
+            .ok_or_else(|| anyhow::anyhow!("shader has no main entry point"))?;
+        self.compute_life_pipeline = ComputePipeline::new(
+            self.compute_queue.device().clone(),
+            entry_point,
+            &self.spec_constants,
+            None,
+            |_| {},
+        )?;
+        Ok(())
+    }
+
     pub fn color_image(&self) -> DeviceImageView {
         self.image.clone()
     }
 
+    /// Creates a buffer for [`GameOfLifeComputePipeline::compute_indirect`]. It needs
+    /// `STORAGE_BUFFER` usage to be written by the GPU and `INDIRECT_BUFFER` usage to be read by
+    /// the dispatch.
+    pub fn create_indirect_args_buffer(
+        allocator: &Arc<SharedMemoryAllocator>,
+    ) -> IndirectArgsBuffer {
+        Buffer::new_slice(
+            &**allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::INDIRECT_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::DeviceOnly,
+                ..Default::default()
+            },
+            1,
+        )
+        .unwrap()
+    }
+
     pub fn draw_life(&mut self, pos: IVec2) {
         let mut life_in = self.life_in.write().unwrap();
         let size = self.image.image().dimensions().width_height();
@@ -132,6 +198,7 @@ impl GameOfLifeComputePipeline {
         before_future: Box<dyn GpuFuture>,
         life_color: [f32; 4],
         dead_color: [f32; 4],
+        timer: Option<&GpuTimer>,
     ) -> Box<dyn GpuFuture> {
         let mut builder = AutoCommandBufferBuilder::primary(
             &self.command_buffer_allocator,
@@ -139,15 +206,21 @@ impl GameOfLifeComputePipeline {
             CommandBufferUsage::OneTimeSubmit,
         )
         .unwrap();
+        if let Some(timer) = timer {
+            timer.begin(&mut builder);
+        }
 
         // Dispatch will mutate the builder adding commands which won't be sent before we build the command buffer
         // after dispatches. This will minimize the commands we send to the GPU. For example, we could be doing
         // tens of dispatches here depending on our needs. Maybe we wanted to simulate 10 steps at a time...
 
         // First compute the next state
-        self.dispatch(&mut builder, life_color, dead_color, 0);
+        self.dispatch(&mut builder, life_color, dead_color, 0, None);
         // Then color based on the next state
-        self.dispatch(&mut builder, life_color, dead_color, 1);
+        self.dispatch(&mut builder, life_color, dead_color, 1, None);
+        if let Some(timer) = timer {
+            timer.end(&mut builder);
+        }
 
         let command_buffer = builder.build().unwrap();
         let finished = before_future
@@ -161,7 +234,94 @@ impl GameOfLifeComputePipeline {
         after_pipeline
     }
 
-    /// Build the command for a dispatch.
+    /// Same as [`GameOfLifeComputePipeline::compute`], but the workgroup counts are read from
+    /// `indirect_buffer`, which a prior pass fills on the GPU from the grid size. Create the buffer
+    /// with [`GameOfLifeComputePipeline::create_indirect_args_buffer`].
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `indirect_buffer` lacks `STORAGE_BUFFER` or `INDIRECT_BUFFER` usage
+    pub fn compute_indirect(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        indirect_buffer: &IndirectArgsBuffer,
+        life_color: [f32; 4],
+        dead_color: [f32; 4],
+        timer: Option<&GpuTimer>,
+    ) -> Box<dyn GpuFuture> {
+        let usage = indirect_buffer.usage();
+        assert!(
+            usage.storage_buffer && usage.indirect_buffer,
+            "Indirect args buffer needs STORAGE_BUFFER and INDIRECT_BUFFER usage"
+        );
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        if let Some(timer) = timer {
+            timer.begin(&mut builder);
+        }
+
+        // The builder inserts the barrier between the shader write of the args and the indirect
+        // read of the dispatches
+        self.fill_indirect_args(&mut builder, indirect_buffer);
+        self.dispatch(
+            &mut builder,
+            life_color,
+            dead_color,
+            0,
+            Some(indirect_buffer),
+        );
+        self.dispatch(
+            &mut builder,
+            life_color,
+            dead_color,
+            1,
+            Some(indirect_buffer),
+        );
+        if let Some(timer) = timer {
+            timer.end(&mut builder);
+        }
+
+        let command_buffer = builder.build().unwrap();
+        let after_pipeline = before_future
+            .then_execute(self.compute_queue.clone(), command_buffer)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .boxed();
+
+        std::mem::swap(&mut self.life_in, &mut self.life_out);
+
+        after_pipeline
+    }
+
+    /// Build the command writing the workgroup counts covering the image to `indirect_buffer`.
+    fn fill_indirect_args(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        indirect_buffer: &IndirectArgsBuffer,
+    ) {
+        let img_dims = self.image.image().dimensions().width_height();
+        let pipeline_layout = self.indirect_args_pipeline.layout();
+        let desc_layout = pipeline_layout.set_layouts().get(0).unwrap();
+        let set =
+            PersistentDescriptorSet::new(&self.descriptor_set_allocator, desc_layout.clone(), [
+                WriteDescriptorSet::buffer(0, indirect_buffer.clone()),
+            ])
+            .unwrap();
+        let push_constants = indirect_args_cs::ty::PushConstants {
+            grid_size: img_dims,
+            local_size: self.local_size,
+        };
+        ComputePass::new(self.indirect_args_pipeline.clone(), set)
+            .record_with_push_constants(builder, push_constants, Dispatch::GroupCounts([1, 1, 1]))
+            .unwrap();
+    }
+
+    /// Build the command for a dispatch. Workgroup counts are read from `indirect_buffer` if set.
     fn dispatch(
         &mut self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
@@ -169,6 +329,7 @@ impl GameOfLifeComputePipeline {
         dead_color: [f32; 4],
         // Step determines whether we color or compute life (see branch in the shader)s
         step: i32,
+        indirect_buffer: Option<&IndirectArgsBuffer>,
     ) {
         // Resize image if needed
         let img_dims = self.image.image().dimensions().width_height();
@@ -187,91 +348,58 @@ impl GameOfLifeComputePipeline {
             dead_color,
             step,
         };
-        builder
-            .bind_pipeline_compute(self.compute_life_pipeline.clone())
-            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
-            .push_constants(pipeline_layout.clone(), 0, push_constants)
-            .dispatch([img_dims[0] / 8, img_dims[1] / 8, 1])
+        let dispatch = match indirect_buffer {
+            Some(indirect_buffer) => Dispatch::Indirect(indirect_buffer.clone()),
+            None => Dispatch::extent([img_dims[0], img_dims[1], 1], [
+                self.local_size[0],
+                self.local_size[1],
+                1,
+            ]),
+        };
+        ComputePass::new(self.compute_life_pipeline.clone(), set)
+            .record_with_push_constants(builder, push_constants, dispatch)
             .unwrap();
     }
 }
 
 mod compute_life_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        // Compile with `glslc compute_life.comp -o compute_life.spv` for hot reloading
+        path: "examples/game_of_life/compute_life.comp",
+        types_meta: {
+            use bytemuck::{Pod, Zeroable};
+
+            #[derive(Clone, Copy, Zeroable, Pod)]
+        },
+    }
+}
+
+mod indirect_args_cs {
     vulkano_shaders::shader! {
         ty: "compute",
         src: "
 #version 450
 
-layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
 
-layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
-layout(set = 0, binding = 1) buffer LifeInBuffer { uint life_in[]; };
-layout(set = 0, binding = 2) buffer LifeOutBuffer { uint life_out[]; };
+// Matches VkDispatchIndirectCommand
+layout(set = 0, binding = 0) writeonly buffer IndirectArgs {
+    uint x;
+    uint y;
+    uint z;
+} args;
 
 layout(push_constant) uniform PushConstants {
-    vec4 life_color;
-    vec4 dead_color;
-    int step;
+    uvec2 grid_size;
+    uvec2 local_size;
 } push_constants;
 
-int get_index(ivec2 pos) {
-    ivec2 dims = ivec2(imageSize(img));
-    return pos.y * dims.x + pos.x;
-}
-
-// https://en.wikipedia.org/wiki/Conway%27s_Game_of_Life
-void compute_life() {
-    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
-    int index = get_index(pos);
-    
-    ivec2 up_left = pos + ivec2(-1, 1);
-    ivec2 up = pos + ivec2(0, 1);
-    ivec2 up_right = pos + ivec2(1, 1);
-    ivec2 right = pos + ivec2(1, 0);
-    ivec2 down_right = pos + ivec2(1, -1);
-    ivec2 down = pos + ivec2(0, -1);
-    ivec2 down_left = pos + ivec2(-1, -1);
-    ivec2 left = pos + ivec2(-1, 0);
-
-    int alive_count = 0;
-    if (life_out[get_index(up_left)] == 1) { alive_count += 1; }
-    if (life_out[get_index(up)] == 1) { alive_count += 1; }
-    if (life_out[get_index(up_right)] == 1) { alive_count += 1; }
-    if (life_out[get_index(right)] == 1) { alive_count += 1; }
-    if (life_out[get_index(down_right)] == 1) { alive_count += 1; }
-    if (life_out[get_index(down)] == 1) { alive_count += 1; }
-    if (life_out[get_index(down_left)] == 1) { alive_count += 1; }
-    if (life_out[get_index(left)] == 1) { alive_count += 1; }
-
-    // Dead becomes alive
-    if (life_out[index] == 0 && alive_count == 3) {
-        life_out[index] = 1;
-    } // Becomes dead
-    else if (life_out[index] == 1 && alive_count < 2 || alive_count > 3) {
-        life_out[index] = 0;
-    } // Else Do nothing
-    else {
-       
-        life_out[index] = life_in[index];
-    }
-}
-
-void compute_color() {
-    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
-    int index = get_index(pos);
-    if (life_out[index] == 1) {
-        imageStore(img, pos, push_constants.life_color);
-    } else {
-        imageStore(img, pos, push_constants.dead_color);
-    }
-}
-
 void main() {
-    if (push_constants.step == 0) {
-        compute_life();
-    } else {
-        compute_color();
-    }
+    uvec2 groups = (push_constants.grid_size + push_constants.local_size - 1) / push_constants.local_size;
+    args.x = groups.x;
+    args.y = groups.y;
+    args.z = 1;
 }",
         types_meta: {
             use bytemuck::{Pod, Zeroable};
@@ -8,14 +8,23 @@ use bevy::{
     app::PluginGroupBuilder,
     prelude::*,
     time::FixedTimestep,
-    window::{close_on_esc, WindowId, WindowMode},
+    window::{close_on_esc, CursorGrabMode, PrimaryWindow, WindowId, WindowMode},
 };
 use bevy_vulkano::{
-    BevyVulkanoContext, BevyVulkanoWindows, VulkanoWinitConfig, VulkanoWinitPlugin,
+    BevyVulkanoContext, BevyVulkanoWindows, FullscreenMode, GpuTimer, ShaderReloadPlugin,
+    ShaderReloaded, ShaderReloader, VulkanoRendererError, VulkanoWinitConfig, VulkanoWinitPlugin,
+    WatchedShaderId,
+};
+use vulkano::{
+    format::Format,
+    image::{ImageAccess, SampleCount},
 };
-use vulkano::image::ImageAccess;
 
-use crate::{game_of_life::GameOfLifeComputePipeline, place_over_frame::RenderPassPlaceOverFrame};
+use crate::{
+    game_of_life::{GameOfLifeComputePipeline, GameOfLifeSpecConstants, IndirectArgsBuffer},
+    pixels_draw_pipeline::{DrawParams, SamplerSettings, Tonemap},
+    place_over_frame::RenderPassPlaceOverFrame,
+};
 
 pub struct PluginBundle;
 
@@ -30,12 +39,17 @@ impl PluginGroup for PluginBundle {
             // Don't add default bevy plugins or WinitPlugin. This owns "core loop" (runner).
             // Bevy winit and render should be excluded
             .add(VulkanoWinitPlugin::default())
+            .add(ShaderReloadPlugin::default())
     }
 }
 
 fn main() {
     App::new()
-        .insert_non_send_resource(VulkanoWinitConfig::default())
+        .insert_non_send_resource(VulkanoWinitConfig {
+            // Composite without render pass objects when the device supports it
+            dynamic_rendering: true,
+            ..VulkanoWinitConfig::default()
+        })
         .add_plugins(PluginBundle.set(VulkanoWinitPlugin {
             window_descriptor: WindowDescriptor {
                 width: 1024.0,
@@ -48,10 +62,15 @@ fn main() {
                 ..WindowDescriptor::default()
             },
         }))
+        .init_resource::<ScreenshotRequested>()
+        .init_resource::<GpuTimings>()
         .add_startup_system(create_pipelines)
         .add_system(close_on_esc)
         .add_system(draw_life_system)
+        .add_system(request_screenshot_system)
+        .add_system(toggle_fullscreen_system)
         .add_system(update_window_title_system)
+        .add_system(reload_game_of_life_system)
         .add_system_set_to_stage(
             // Note that this is `PostUpdate` to ensure we render only after update
             CoreStage::PostUpdate,
@@ -62,78 +81,226 @@ fn main() {
         .run();
 }
 
-fn update_window_title_system(vulkano_windows: NonSend<BevyVulkanoWindows>, time: ResMut<Time>) {
+fn update_window_title_system(
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    time: ResMut<Time>,
+    gpu_timings: Res<GpuTimings>,
+) {
     let primary = vulkano_windows
         .get_winit_window(WindowId::primary())
         .unwrap();
     let fps = 1.0 / time.delta_seconds();
-    primary.set_title(&format!("Bevy Vulkano Game Of Life {fps:.2}"));
+    primary.set_title(&format!(
+        "Bevy Vulkano Game Of Life {fps:.2} (simulate {:.3} ms, composite {:.3} ms)",
+        gpu_timings.simulate_ms, gpu_timings.composite_ms
+    ));
 }
 
+/// GPU timers measuring the simulation and compositing passes, along with their latest results
+#[derive(Default, Resource)]
+struct GpuTimings {
+    simulate: Option<GpuTimer>,
+    composite: Option<GpuTimer>,
+    simulate_ms: f64,
+    composite_ms: f64,
+}
+
+/// Compiled `compute_life.comp` watched for changes, if `GAME_OF_LIFE_SPV` was set
+#[derive(Resource)]
+struct GameOfLifeShader(WatchedShaderId);
+
+/// Workgroup counts of the simulation dispatches, see `GameOfLifeComputePipeline::compute_indirect`
+#[derive(Resource)]
+struct IndirectArgs(IndirectArgsBuffer);
+
 /// Creates our simulation pipeline & render pipeline
 fn create_pipelines(
     mut commands: Commands,
-    context: NonSend<BevyVulkanoContext>,
+    context: Res<BevyVulkanoContext>,
     windows: NonSend<BevyVulkanoWindows>,
+    mut gpu_timings: ResMut<GpuTimings>,
+    mut shader_reloader: ResMut<ShaderReloader>,
 ) {
     let primary_window = windows.get_primary_window_renderer().unwrap();
     // Create compute pipeline to simulate game of life
-    let game_of_life_pipeline = GameOfLifeComputePipeline::new(
+    let mut game_of_life_pipeline = GameOfLifeComputePipeline::new(
         context.context.memory_allocator(),
         primary_window.graphics_queue(),
         [512, 512],
+        GameOfLifeSpecConstants {
+            local_size_x: 8,
+            local_size_y: 8,
+        },
     );
+    // Run with `GAME_OF_LIFE_SPV=path/to/compute_life.spv` and recompile the shader while running
+    // to see the simulation change without restarting
+    if let Ok(path) = std::env::var("GAME_OF_LIFE_SPV") {
+        // Safety: the file is compiled by glslc from `compute_life.comp`
+        match unsafe { shader_reloader.watch(context.context.device().clone(), &path) } {
+            Ok((id, module)) => {
+                if let Err(e) = game_of_life_pipeline.set_compute_shader(&module) {
+                    error!("Failed to use {}: {}", path, e);
+                }
+                commands.insert_resource(GameOfLifeShader(id));
+            }
+            Err(e) => error!("Failed to load {}: {}", path, e),
+        }
+    }
+    // The dispatch size of the simulation is written by the GPU each frame
+    let indirect_args = IndirectArgs(GameOfLifeComputePipeline::create_indirect_args_buffer(
+        context.context.memory_allocator(),
+    ));
+    // The simulation is upscaled to the window. Run with `GAME_OF_LIFE_SMOOTH=1` to smooth the
+    // cells instead of keeping them as crisp squares
+    let sampler_settings = if std::env::var_os("GAME_OF_LIFE_SMOOTH").is_some() {
+        SamplerSettings::smooth()
+    } else {
+        SamplerSettings::pixel_crisp()
+    };
+    // Run with `GAME_OF_LIFE_PICKING=1` to also render the cell under each pixel into a picking
+    // buffer, see `RenderPassPlaceOverFrame::extra_attachment`. It's an integer format, so that
+    // resolving the multisampled pass keeps one cell at cell edges instead of averaging them
+    let extra_formats = if std::env::var_os("GAME_OF_LIFE_PICKING").is_some() {
+        vec![Format::R32G32_UINT]
+    } else {
+        vec![]
+    };
     // Create our render pass
     let place_over_frame = RenderPassPlaceOverFrame::new(
-        context.context.memory_allocator().clone(),
+        &context,
         primary_window.graphics_queue(),
         primary_window.swapchain_format(),
+        SampleCount::Sample4,
+        context.dynamic_rendering_enabled(),
+        sampler_settings,
+        // The simulation's colors are already in 0-1
+        Tonemap::None,
+        extra_formats,
     );
+    // Timers are None if the queue doesn't support timestamps
+    gpu_timings.simulate = GpuTimer::new(&context, &primary_window.graphics_queue());
+    gpu_timings.composite = GpuTimer::new(&context, &primary_window.graphics_queue());
     // Insert resources
     commands.insert_resource(game_of_life_pipeline);
+    commands.insert_resource(indirect_args);
     commands.insert_resource(place_over_frame);
 }
 
-/// Draw life at mouse position on the game of life canvas
+/// Swaps in the simulation shader when its SPIR-V file was recompiled
+fn reload_game_of_life_system(
+    mut reloaded_events: EventReader<ShaderReloaded>,
+    shader: Option<Res<GameOfLifeShader>>,
+    mut game_of_life: ResMut<GameOfLifeComputePipeline>,
+) {
+    let Some(shader) = shader else {
+        return;
+    };
+    for event in reloaded_events.iter().filter(|event| event.id == shader.0) {
+        if let Err(e) = game_of_life.set_compute_shader(&event.module) {
+            error!("Keeping the previous simulation pipeline: {}", e);
+        }
+    }
+}
+
+/// Draw life at mouse position on the game of life canvas. The cursor is confined to the window
+/// while drawing, so that strokes don't leave the canvas.
 fn draw_life_system(
     mut game_of_life: ResMut<GameOfLifeComputePipeline>,
-    windows: ResMut<Windows>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    primary_window: Query<(Entity, &Window), With<PrimaryWindow>>,
     mouse_input: Res<Input<MouseButton>>,
 ) {
-    if mouse_input.pressed(MouseButton::Left) {
-        let primary = windows.get_primary().unwrap();
-        if let Some(pos) = primary.cursor_position() {
-            let width = primary.width();
-            let height = primary.height();
-            let normalized = Vec2::new(
-                (pos.x / width).clamp(0.0, 1.0),
-                (pos.y / height).clamp(0.0, 1.0),
-            );
-            let image_size = game_of_life
-                .color_image()
-                .image()
-                .dimensions()
-                .width_height();
-            let draw_pos = IVec2::new(
-                (image_size[0] as f32 * normalized.x) as i32,
-                (image_size[1] as f32 * normalized.y) as i32,
-            );
-            game_of_life.draw_life(draw_pos);
+    let Ok((entity, window)) = primary_window.get_single() else {
+        return;
+    };
+    let grab_mode = if mouse_input.just_pressed(MouseButton::Left) {
+        Some(CursorGrabMode::Confined)
+    } else if mouse_input.just_released(MouseButton::Left) {
+        Some(CursorGrabMode::None)
+    } else {
+        None
+    };
+    if let Some(grab_mode) = grab_mode {
+        // Confining isn't supported on all platforms, drawing works without it
+        if let Err(e) = vulkano_windows.set_cursor_grab(entity, grab_mode) {
+            debug!("Failed to set cursor grab: {}", e);
         }
     }
+    if !mouse_input.pressed(MouseButton::Left) {
+        return;
+    }
+    let (Some(pos), Some(renderer)) = (
+        window.cursor_position(),
+        vulkano_windows.get_window_renderer(entity),
+    ) else {
+        return;
+    };
+    let image_size = game_of_life
+        .color_image()
+        .image()
+        .dimensions()
+        .width_height();
+    if let Some(draw_pos) = renderer.cursor_to_image_coords(pos, image_size) {
+        game_of_life.draw_life(draw_pos);
+    }
+}
+
+/// Set when a screenshot of the next rendered frame should be saved to disk
+#[derive(Default, Resource)]
+struct ScreenshotRequested(bool);
+
+/// Press S to save the next frame to `game_of_life.png`
+fn request_screenshot_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut screenshot_requested: ResMut<ScreenshotRequested>,
+) {
+    if keyboard_input.just_pressed(KeyCode::S) {
+        screenshot_requested.0 = true;
+    }
+}
+
+/// Press F to toggle borderless fullscreen
+fn toggle_fullscreen_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F) {
+        return;
+    }
+    let Ok(entity) = primary_window.get_single() else {
+        return;
+    };
+    let is_fullscreen = vulkano_windows
+        .get_winit_window(entity)
+        .map_or(false, |window| window.fullscreen().is_some());
+    vulkano_windows.set_fullscreen(
+        entity,
+        if is_fullscreen {
+            FullscreenMode::Windowed
+        } else {
+            FullscreenMode::BorderlessFullscreen
+        },
+    );
 }
 
-/// All render occurs here in one system. If you want to split systems to separate, use
-/// `PipelineSyncData` to update futures. You could have `pre_render_system` and `post_render_system` to start and finish frames
+/// All render occurs here in one system. If you want to split rendering into separate systems, set
+/// `VulkanoWinitConfig::auto_present` and add them to `VulkanoRenderSet::Render`, chaining futures
+/// through `PipelineSyncData`
 fn game_of_life_pipeline_system(
     mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
     mut game_of_life: ResMut<GameOfLifeComputePipeline>,
+    indirect_args: Res<IndirectArgs>,
     mut place_over_frame: ResMut<RenderPassPlaceOverFrame>,
+    mut screenshot_requested: ResMut<ScreenshotRequested>,
+    mut gpu_timings: ResMut<GpuTimings>,
 ) {
     let primary_window = vulkano_windows.get_primary_window_renderer_mut().unwrap();
 
     // Start frame
     let before = match primary_window.acquire() {
+        // Nothing to render while minimized
+        Err(VulkanoRendererError::WindowMinimized) => return,
         Err(e) => {
             bevy::log::error!("Failed to start frame: {}", e);
             return;
@@ -141,11 +308,57 @@ fn game_of_life_pipeline_system(
         Ok(f) => f,
     };
 
-    let after_compute = game_of_life.compute(before, [1.0, 0.0, 0.0, 1.0], [0.0; 4]);
+    let after_compute = game_of_life.compute_indirect(
+        before,
+        &indirect_args.0,
+        [1.0, 0.0, 0.0, 1.0],
+        [0.0; 4],
+        gpu_timings.simulate.as_ref(),
+    );
     let color_image = game_of_life.color_image();
     let final_image = primary_window.swapchain_image_view();
-    let after_render = place_over_frame.render(after_compute, color_image, final_image);
+    let after_render = place_over_frame.render(
+        after_compute,
+        color_image,
+        final_image,
+        DrawParams::default(),
+        gpu_timings.composite.as_ref(),
+    );
+
+    // Copy the frame before presenting it if a screenshot was requested
+    let (readback, after_render) = if screenshot_requested.0 {
+        screenshot_requested.0 = false;
+        primary_window.copy_swapchain_to_cpu(after_render)
+    } else {
+        (None, after_render)
+    };
+
+    // Finish Frame. Waiting on the future ensures the copy is done before we read it
+    if let Err(e) = primary_window.present(after_render, true) {
+        bevy::log::error!("Failed to present frame: {}", e);
+    }
+
+    // Frame was waited on, so timer results should be available
+    if let Some(ms) = gpu_timings.simulate.as_ref().and_then(GpuTimer::resolve) {
+        gpu_timings.simulate_ms = ms;
+    }
+    if let Some(ms) = gpu_timings.composite.as_ref().and_then(GpuTimer::resolve) {
+        gpu_timings.composite_ms = ms;
+    }
 
-    // Finish Frame
-    primary_window.present(after_render, true);
+    if let Some(readback) = readback {
+        if let Some(pixels) = readback.to_rgba8() {
+            let [width, height] = readback.extent;
+            match image::save_buffer(
+                "game_of_life.png",
+                &pixels,
+                width,
+                height,
+                image::ColorType::Rgba8,
+            ) {
+                Ok(_) => bevy::log::info!("Saved screenshot to game_of_life.png"),
+                Err(e) => bevy::log::error!("Failed to save screenshot: {}", e),
+            }
+        }
+    }
 }
@@ -9,29 +9,32 @@
 
 use std::sync::Arc;
 
+use bevy_vulkano::{util, BevyVulkanoContext, DescriptorSetCache, SharedMemoryAllocator};
 use bytemuck::{Pod, Zeroable};
 use vulkano::{
     buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess},
-    command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
-        CommandBufferInheritanceInfo, CommandBufferUsage, SecondaryAutoCommandBuffer,
-    },
+    command_buffer::{allocator::StandardCommandBufferAllocator, SecondaryAutoCommandBuffer},
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
     },
     device::{DeviceOwned, Queue},
-    image::ImageViewAbstract,
-    memory::allocator::StandardMemoryAllocator,
+    format::{Format, NumericType},
+    image::{ImageViewAbstract, SampleCount},
     pipeline::{
         graphics::{
+            color_blend::ColorBlendState,
             input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            render_pass::PipelineRenderPassType,
             vertex_input::BuffersDefinition,
             viewport::{Viewport, ViewportState},
         },
         GraphicsPipeline, Pipeline, PipelineBindPoint,
     },
-    render_pass::Subpass,
-    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode},
+    sampler::{
+        Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerCreationError,
+        SamplerMipmapMode,
+    },
 };
 
 /// Vertex for textured quads
@@ -43,50 +46,165 @@ pub struct TexturedVertex {
 }
 vulkano::impl_vertex!(TexturedVertex, position, tex_coords);
 
+/// Quad centered at the origin. Vulkan's clip space y points down, so the negative y edge is the
+/// top of the screen and maps to the first image row, matching
+/// `VulkanoWindowRenderer::cursor_to_image_coords`.
 pub fn textured_quad(width: f32, height: f32) -> (Vec<TexturedVertex>, Vec<u32>) {
     (
         vec![
             TexturedVertex {
                 position: [-(width / 2.0), -(height / 2.0)],
-                tex_coords: [0.0, 1.0],
+                tex_coords: [0.0, 0.0],
             },
             TexturedVertex {
                 position: [-(width / 2.0), height / 2.0],
-                tex_coords: [0.0, 0.0],
+                tex_coords: [0.0, 1.0],
             },
             TexturedVertex {
                 position: [width / 2.0, height / 2.0],
-                tex_coords: [1.0, 0.0],
+                tex_coords: [1.0, 1.0],
             },
             TexturedVertex {
                 position: [width / 2.0, -(height / 2.0)],
-                tex_coords: [1.0, 1.0],
+                tex_coords: [1.0, 0.0],
             },
         ],
         vec![0, 2, 1, 0, 3, 2],
     )
 }
 
-/// A subpass pipeline that fills a quad over frame
+/// Tonemap operator mapping HDR colors of the drawn image into the output's 0-1 range
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Tonemap {
+    /// Colors above 1 are clipped
+    #[default]
+    None,
+    /// `c / (1 + c)`, which never clips but desaturates highlights
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    Aces,
+}
+
+/// Per draw parameters passed to the fragment shader as push constants
+#[derive(Debug, Copy, Clone)]
+pub struct DrawParams {
+    /// Multiplies the sampled color
+    pub tint: [f32; 4],
+    /// Multiplies the sampled color's rgb after tinting, before tonemapping
+    pub exposure: f32,
+}
+
+impl Default for DrawParams {
+    fn default() -> Self {
+        DrawParams {
+            tint: [1.0; 4],
+            exposure: 1.0,
+        }
+    }
+}
+
+/// How [`PixelsDrawPipeline`] samples the drawn image when it's scaled to the output
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SamplerSettings {
+    /// Magnification & minification filter. `Nearest` keeps pixels crisp when upscaling,
+    /// `Linear` smooths them
+    pub filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    /// Added to the level of detail before selecting a mip level. Clamped to the device's
+    /// `max_sampler_lod_bias`.
+    pub mip_lod_bias: f32,
+    /// Anisotropic filtering samples, clamped to
+    /// `BevyVulkanoContext::max_sampler_anisotropy`. Ignored if unsupported.
+    pub anisotropy: Option<f32>,
+}
+
+impl SamplerSettings {
+    /// Nearest neighbor sampling, each image pixel stays a sharp square
+    pub fn pixel_crisp() -> SamplerSettings {
+        SamplerSettings {
+            filter: Filter::Nearest,
+            mipmap_mode: SamplerMipmapMode::Nearest,
+            mip_lod_bias: 0.0,
+            anisotropy: None,
+        }
+    }
+
+    /// Bilinear sampling, smoothing the image when scaled
+    pub fn smooth() -> SamplerSettings {
+        SamplerSettings {
+            filter: Filter::Linear,
+            mipmap_mode: SamplerMipmapMode::Linear,
+            ..SamplerSettings::pixel_crisp()
+        }
+    }
+
+    /// Returns the shared sampler for these settings, limited to what the device supports
+    pub fn sampler(
+        &self,
+        context: &BevyVulkanoContext,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let max_lod_bias = context
+            .context
+            .device()
+            .physical_device()
+            .properties()
+            .max_sampler_lod_bias;
+        let anisotropy = match (self.anisotropy, context.max_sampler_anisotropy()) {
+            (Some(anisotropy), Some(max)) => Some(anisotropy.clamp(1.0, max)),
+            _ => None,
+        };
+        context.get_sampler(SamplerCreateInfo {
+            mag_filter: self.filter,
+            min_filter: self.filter,
+            address_mode: [SamplerAddressMode::Repeat; 3],
+            mipmap_mode: self.mipmap_mode,
+            mip_lod_bias: self.mip_lod_bias.clamp(-max_lod_bias, max_lod_bias),
+            anisotropy: anisotropy.filter(|anisotropy| *anisotropy > 1.0),
+            ..Default::default()
+        })
+    }
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings::pixel_crisp()
+    }
+}
+
+/// A subpass pipeline that fills a quad over frame. Works within a render pass subpass or with
+/// dynamic rendering. The drawn image holds linear colors, which are tonemapped with the
+/// pipeline's [`Tonemap`] and then sRGB encoded for UNORM outputs, so that output looks the same
+/// as with sRGB outputs encoding them on write. With more than one color attachment, the texel
+/// coordinates of the drawn image plus one are written to location 1 as unsigned integers, e.g.
+/// for picking.
 pub struct PixelsDrawPipeline {
     gfx_queue: Arc<Queue>,
     command_buffer_allocator: StandardCommandBufferAllocator,
-    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    /// The drawn image is usually the same each frame, so its set is reused
+    descriptor_sets: DescriptorSetCache,
     pipeline: Arc<GraphicsPipeline>,
-    subpass: Subpass,
+    render_pass: PipelineRenderPassType,
+    samples: SampleCount,
+    /// Created from [`SamplerSettings`], shared through `BevyVulkanoContext::get_sampler`
+    sampler: Arc<Sampler>,
     vertices: Arc<CpuAccessibleBuffer<[TexturedVertex]>>,
     indices: Arc<CpuAccessibleBuffer<[u32]>>,
 }
 
 impl PixelsDrawPipeline {
     pub fn new(
-        allocator: Arc<StandardMemoryAllocator>,
+        allocator: Arc<SharedMemoryAllocator>,
         gfx_queue: Arc<Queue>,
-        subpass: Subpass,
+        render_pass: PipelineRenderPassType,
+        output_format: Format,
+        color_attachment_count: u32,
+        samples: SampleCount,
+        sampler: Arc<Sampler>,
+        tonemap: Tonemap,
     ) -> PixelsDrawPipeline {
         let (vertices, indices) = textured_quad(2.0, 2.0);
         let vertex_buffer = CpuAccessibleBuffer::<[TexturedVertex]>::from_iter(
-            &allocator,
+            &*allocator,
             BufferUsage {
                 vertex_buffer: true,
                 ..BufferUsage::empty()
@@ -96,7 +214,7 @@ impl PixelsDrawPipeline {
         )
         .unwrap();
         let index_buffer = CpuAccessibleBuffer::<[u32]>::from_iter(
-            &allocator,
+            &*allocator,
             BufferUsage {
                 index_buffer: true,
                 ..BufferUsage::empty()
@@ -113,9 +231,22 @@ impl PixelsDrawPipeline {
                 .vertex_input_state(BuffersDefinition::new().vertex::<TexturedVertex>())
                 .vertex_shader(vs.entry_point("main").unwrap(), ())
                 .input_assembly_state(InputAssemblyState::new())
-                .fragment_shader(fs.entry_point("main").unwrap(), ())
+                .fragment_shader(
+                    fs.entry_point("main").unwrap(),
+                    fs::SpecializationConstants {
+                        encode_srgb: (output_format.type_color() != Some(NumericType::SRGB)) as u32,
+                        tonemap: tonemap as u32,
+                    },
+                )
                 .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
-                .render_pass(subpass.clone())
+                // One blend state per color attachment, without blending
+                .color_blend_state(ColorBlendState::new(color_attachment_count))
+                // Must match the sample count of the color attachments
+                .multisample_state(MultisampleState {
+                    rasterization_samples: samples,
+                    ..Default::default()
+                })
+                .render_pass(render_pass.clone())
                 .build(gfx_queue.device().clone())
                 .unwrap()
         };
@@ -125,70 +256,91 @@ impl PixelsDrawPipeline {
                 allocator.device().clone(),
                 Default::default(),
             ),
-            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
-                allocator.device().clone(),
-            ),
+            descriptor_sets: DescriptorSetCache::new(Arc::new(
+                StandardDescriptorSetAllocator::new(allocator.device().clone()),
+            )),
             pipeline,
-            subpass,
+            render_pass,
+            samples,
+            sampler,
             vertices: vertex_buffer,
             indices: index_buffer,
         }
     }
 
-    fn create_image_sampler_nearest(
+    fn create_image_sampler(
         &self,
         image: Arc<dyn ImageViewAbstract>,
     ) -> Arc<PersistentDescriptorSet> {
         let layout = self.pipeline.layout().set_layouts().get(0).unwrap();
-        let sampler = Sampler::new(self.gfx_queue.device().clone(), SamplerCreateInfo {
-            mag_filter: Filter::Nearest,
-            min_filter: Filter::Nearest,
-            address_mode: [SamplerAddressMode::Repeat; 3],
-            mipmap_mode: SamplerMipmapMode::Nearest,
-            ..Default::default()
-        })
-        .unwrap();
-        PersistentDescriptorSet::new(&self.descriptor_set_allocator, layout.clone(), [
-            WriteDescriptorSet::image_view_sampler(0, image, sampler),
-        ])
-        .unwrap()
+        self.descriptor_sets
+            .get(layout.clone(), [WriteDescriptorSet::image_view_sampler(
+                0,
+                image,
+                self.sampler.clone(),
+            )])
+            .unwrap()
     }
 
-    /// Draw input `image` over a quad of size -1.0 to 1.0
+    /// Draw input `image` over a quad of size -1.0 to 1.0. Each triangle of the quad is recorded
+    /// into its own secondary command buffer on a separate thread, execute them in order.
     pub fn draw(
         &mut self,
         viewport_dimensions: [u32; 2],
         image: Arc<dyn ImageViewAbstract>,
-    ) -> SecondaryAutoCommandBuffer {
-        let mut builder = AutoCommandBufferBuilder::secondary(
-            &self.command_buffer_allocator,
-            self.gfx_queue.queue_family_index(),
-            CommandBufferUsage::MultipleSubmit,
-            CommandBufferInheritanceInfo {
-                render_pass: Some(self.subpass.clone().into()),
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        let desc_set = self.create_image_sampler_nearest(image);
-        builder
-            .set_viewport(0, [Viewport {
-                origin: [0.0, 0.0],
-                dimensions: [viewport_dimensions[0] as f32, viewport_dimensions[1] as f32],
-                depth_range: 0.0..1.0,
-            }])
-            .bind_pipeline_graphics(self.pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                0,
-                desc_set,
-            )
-            .bind_vertex_buffers(0, self.vertices.clone())
-            .bind_index_buffer(self.indices.clone())
-            .draw_indexed(self.indices.len() as u32, 1, 0, 0, 0)
-            .unwrap();
-        builder.build().unwrap()
+        params: DrawParams,
+    ) -> Vec<SecondaryAutoCommandBuffer> {
+        let inheritance = util::render_pass_inheritance(&self.render_pass, self.samples);
+        let desc_set = self.create_image_sampler(image);
+        // Push constants are small enough to update each draw without buffer allocations
+        let push_constants = fs::PushConstants {
+            tint: params.tint,
+            exposure: params.exposure,
+        };
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [viewport_dimensions[0] as f32, viewport_dimensions[1] as f32],
+            depth_range: 0.0..1.0,
+        };
+        let this = &*self;
+        std::thread::scope(|scope| {
+            let recordings = [0, 3]
+                .into_iter()
+                .map(|first_index| {
+                    let inheritance = inheritance.clone();
+                    let desc_set = desc_set.clone();
+                    let viewport = viewport.clone();
+                    scope.spawn(move || {
+                        // The builder must be created on the thread recording it
+                        let mut builder = util::secondary_command_buffer_builder(
+                            &this.command_buffer_allocator,
+                            this.gfx_queue.queue_family_index(),
+                            inheritance,
+                        )
+                        .unwrap();
+                        builder
+                            .set_viewport(0, [viewport])
+                            .bind_pipeline_graphics(this.pipeline.clone())
+                            .bind_descriptor_sets(
+                                PipelineBindPoint::Graphics,
+                                this.pipeline.layout().clone(),
+                                0,
+                                desc_set,
+                            )
+                            .push_constants(this.pipeline.layout().clone(), 0, push_constants)
+                            .bind_vertex_buffers(0, this.vertices.clone())
+                            .bind_index_buffer(this.indices.clone())
+                            .draw_indexed(3, 1, first_index, 0, 0)
+                            .unwrap();
+                        builder.build().unwrap()
+                    })
+                })
+                .collect::<Vec<_>>();
+            recordings
+                .into_iter()
+                .map(|recording| recording.join().unwrap())
+                .collect()
+        })
     }
 }
 
@@ -218,11 +370,43 @@ mod fs {
 layout(location = 0) in vec2 v_tex_coords;
 
 layout(location = 0) out vec4 f_color;
+// Discarded unless a second color attachment is bound
+layout(location = 1) out uvec4 f_texel;
 
 layout(set = 0, binding = 0) uniform sampler2D tex;
 
+// Set when the output format doesn't encode sRGB on write
+layout(constant_id = 0) const bool encode_srgb = false;
+// 0: none, 1: Reinhard, 2: ACES, see `Tonemap`
+layout(constant_id = 1) const uint tonemap = 0;
+
+layout(push_constant) uniform PushConstants {
+    vec4 tint;
+    float exposure;
+} push_constants;
+
+vec3 linear_to_srgb(vec3 color) {
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+vec3 aces(vec3 color) {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
 void main() {
-    f_color = texture(tex, v_tex_coords);
+    vec4 color = texture(tex, v_tex_coords) * push_constants.tint;
+    vec3 rgb = color.rgb * push_constants.exposure;
+    if (tonemap == 1) {
+        rgb = rgb / (1.0 + rgb);
+    } else if (tonemap == 2) {
+        rgb = aces(rgb);
+    }
+    if (encode_srgb) {
+        rgb = linear_to_srgb(clamp(rgb, 0.0, 1.0));
+    }
+    f_color = vec4(rgb, color.a);
+    // Zero is left for where nothing was drawn
+    f_texel = uvec4(uvec2(v_tex_coords * vec2(textureSize(tex, 0))) + 1u, 0u, 0u);
 }
 "
     }
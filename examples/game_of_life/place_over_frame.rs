@@ -9,85 +9,284 @@
 
 use std::sync::Arc;
 
+use bevy_vulkano::{
+    util, BevyVulkanoContext, GpuTimer, SharedMemoryAllocator, TransientAttachmentImage,
+};
 use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        RenderPassBeginInfo, SubpassContents,
+        RenderPassBeginInfo, RenderingAttachmentInfo, RenderingAttachmentResolveInfo,
+        RenderingInfo, SubpassContents,
+    },
+    device::{Device, DeviceOwned, Queue},
+    format::{ClearValue, Format, NumericType},
+    image::{
+        view::ImageView, AttachmentImage, ImageAccess, ImageLayout, ImageUsage, ImageViewAbstract,
+        SampleCount,
+    },
+    pipeline::graphics::render_pass::PipelineRenderingCreateInfo,
+    render_pass::{
+        AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
+        RenderPass, RenderPassCreateInfo, ResolveMode, StoreOp, Subpass, SubpassDescription,
     },
-    device::{DeviceOwned, Queue},
-    format::Format,
-    image::ImageAccess,
-    memory::allocator::StandardMemoryAllocator,
-    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     sync::GpuFuture,
 };
-use vulkano_util::renderer::{DeviceImageView, SwapchainImageView};
+use vulkano_util::renderer::SwapchainImageView;
 
-use crate::{pixels_draw_pipeline::PixelsDrawPipeline, Resource};
+use crate::{
+    pixels_draw_pipeline::{DrawParams, PixelsDrawPipeline, SamplerSettings, Tonemap},
+    Resource,
+};
 
-/// A render pass which places an incoming image over frame filling it
+/// A render pass which places an incoming image over frame filling it.
+///
+/// Besides the output, the pass can render into extra color attachments (multiple render
+/// targets), e.g. a picking buffer. They follow the output at color locations `1..`, and are
+/// cleared to zero each frame. The draw pipeline writes the drawn image's texel coordinates plus
+/// one to location 1, so that zero means nothing was drawn; attachments past it keep their
+/// cleared value.
 #[derive(Resource)]
 pub struct RenderPassPlaceOverFrame {
     gfx_queue: Arc<Queue>,
+    allocator: Arc<SharedMemoryAllocator>,
     command_buffer_allocator: StandardCommandBufferAllocator,
-    render_pass: Arc<RenderPass>,
+    /// `None` when rendering with `begin_rendering` (dynamic rendering) instead
+    render_pass: Option<Arc<RenderPass>>,
     pixels_draw_pipeline: PixelsDrawPipeline,
+    output_format: Format,
+    samples: SampleCount,
+    /// Transient multisampled color attachment which is resolved into the target image.
+    /// Recreated when target dimensions change.
+    msaa_image: Option<Arc<ImageView<TransientAttachmentImage>>>,
+    /// Formats of the extra color attachments
+    extra_formats: Vec<Format>,
+    /// Images of the extra color attachments, created on first render and recreated when target
+    /// dimensions change
+    extra_attachments: Vec<ExtraAttachment>,
+}
+
+/// Images of an extra color attachment of [`RenderPassPlaceOverFrame`]
+struct ExtraAttachment {
+    /// Holds what was rendered, resolved if the pass is multisampled
+    image: Arc<ImageView<AttachmentImage>>,
+    /// Transient multisampled attachment resolved into `image`
+    msaa_image: Option<Arc<ImageView<TransientAttachmentImage>>>,
 }
 
 impl RenderPassPlaceOverFrame {
+    /// Creates the render pass. If `samples` isn't supported by the device for color
+    /// attachments, falls back to a single sample. With `dynamic_rendering` (see
+    /// `BevyVulkanoContext::dynamic_rendering_enabled`), no render pass or framebuffers are
+    /// created and the swapchain image is targeted directly. `sampler_settings` choose between
+    /// pixel-crisp and smoothed scaling of the image. `tonemap` maps HDR views into the output
+    /// range, scaled by the exposure passed to `render`. `extra_formats` adds a color attachment
+    /// of each format after the output, read them back with `extra_attachment`. They must be
+    /// unsigned integer formats, so that multisampled ones resolve to a single sample instead of
+    /// averaging neighboring values.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        allocator: Arc<StandardMemoryAllocator>,
+        context: &BevyVulkanoContext,
         gfx_queue: Arc<Queue>,
         output_format: Format,
+        samples: SampleCount,
+        dynamic_rendering: bool,
+        sampler_settings: SamplerSettings,
+        tonemap: Tonemap,
+        extra_formats: Vec<Format>,
     ) -> RenderPassPlaceOverFrame {
-        let render_pass = vulkano::single_pass_renderpass!(gfx_queue.device().clone(),
-            attachments: {
-                color: {
-                    load: Clear,
-                    store: Store,
-                    format: output_format,
-                    samples: 1,
+        let allocator = context.context.memory_allocator().clone();
+        let sampler = sampler_settings
+            .sampler(context)
+            .expect("Failed to create sampler");
+        let supported_samples = gfx_queue
+            .device()
+            .physical_device()
+            .properties()
+            .framebuffer_color_sample_counts;
+        let samples = if supported_samples.contains_enum(samples) {
+            samples
+        } else {
+            bevy::log::warn!("{:?} is not supported, falling back to Sample1", samples);
+            SampleCount::Sample1
+        };
+        let max_color_attachments = gfx_queue
+            .device()
+            .physical_device()
+            .properties()
+            .max_color_attachments;
+        assert!(
+            extra_formats.len() < max_color_attachments as usize,
+            "The device supports at most {} color attachments",
+            max_color_attachments
+        );
+        assert!(
+            extra_formats
+                .iter()
+                .all(|format| format.type_color() == Some(NumericType::UINT)),
+            "Extra attachments must have unsigned integer formats"
+        );
+        let color_attachment_count = 1 + extra_formats.len() as u32;
+        if dynamic_rendering {
+            let pixels_draw_pipeline = PixelsDrawPipeline::new(
+                allocator.clone(),
+                gfx_queue.clone(),
+                PipelineRenderingCreateInfo {
+                    color_attachment_formats: [output_format]
+                        .iter()
+                        .chain(&extra_formats)
+                        .map(|format| Some(*format))
+                        .collect(),
+                    ..Default::default()
                 }
-            },
-            pass: {
-                    color: [color],
-                    depth_stencil: {}
-            }
-        )
-        .unwrap();
+                .into(),
+                output_format,
+                color_attachment_count,
+                samples,
+                sampler,
+                tonemap,
+            );
+            return RenderPassPlaceOverFrame {
+                gfx_queue,
+                command_buffer_allocator: StandardCommandBufferAllocator::new(
+                    allocator.device().clone(),
+                    Default::default(),
+                ),
+                allocator,
+                render_pass: None,
+                pixels_draw_pipeline,
+                output_format,
+                samples,
+                msaa_image: None,
+                extra_formats,
+                extra_attachments: vec![],
+            };
+        }
+        let render_pass = create_render_pass(
+            gfx_queue.device().clone(),
+            output_format,
+            &extra_formats,
+            samples,
+        );
         let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
-        let pixels_draw_pipeline =
-            PixelsDrawPipeline::new(allocator.clone(), gfx_queue.clone(), subpass);
+        let pixels_draw_pipeline = PixelsDrawPipeline::new(
+            allocator.clone(),
+            gfx_queue.clone(),
+            subpass.into(),
+            output_format,
+            color_attachment_count,
+            samples,
+            sampler,
+            tonemap,
+        );
         RenderPassPlaceOverFrame {
             gfx_queue,
             command_buffer_allocator: StandardCommandBufferAllocator::new(
                 allocator.device().clone(),
                 Default::default(),
             ),
-            render_pass,
+            allocator,
+            render_pass: Some(render_pass),
             pixels_draw_pipeline,
+            output_format,
+            samples,
+            msaa_image: None,
+            extra_formats,
+            extra_attachments: vec![],
+        }
+    }
+
+    /// Sample count actually used by the render pass
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    /// Extra color attachment `index` (in the order of `extra_formats` passed to `new`) holding
+    /// what the last `render` wrote to it. `None` before the first render. The image can be
+    /// sampled or copied from once the future returned by that `render` has completed.
+    pub fn extra_attachment(&self, index: usize) -> Option<Arc<ImageView<AttachmentImage>>> {
+        self.extra_attachments
+            .get(index)
+            .map(|attachment| attachment.image.clone())
+    }
+
+    /// Returns the extra attachments, recreating them if target size has changed (resize)
+    fn extra_attachments(&mut self, dimensions: [u32; 2]) -> &[ExtraAttachment] {
+        let up_to_date = self.extra_attachments.len() == self.extra_formats.len()
+            && self.extra_attachments.iter().all(|attachment| {
+                attachment.image.image().dimensions().width_height() == dimensions
+            });
+        if !up_to_date {
+            self.extra_attachments = self
+                .extra_formats
+                .iter()
+                .map(|format| ExtraAttachment {
+                    image: ImageView::new_default(
+                        AttachmentImage::with_usage(
+                            &*self.allocator,
+                            dimensions,
+                            *format,
+                            ImageUsage::COLOR_ATTACHMENT
+                                | ImageUsage::SAMPLED
+                                | ImageUsage::TRANSFER_SRC,
+                        )
+                        .unwrap(),
+                    )
+                    .unwrap(),
+                    msaa_image: (self.samples != SampleCount::Sample1).then(|| {
+                        ImageView::new_default(
+                            TransientAttachmentImage::new(
+                                &*self.allocator,
+                                dimensions,
+                                *format,
+                                self.samples,
+                            )
+                            .unwrap(),
+                        )
+                        .unwrap()
+                    }),
+                })
+                .collect();
+        }
+        &self.extra_attachments
+    }
+
+    /// Returns the multisampled attachment, recreating it if target size has changed (resize)
+    fn msaa_image(&mut self, dimensions: [u32; 2]) -> Arc<ImageView<TransientAttachmentImage>> {
+        match &self.msaa_image {
+            Some(image) if image.image().dimensions().width_height() == dimensions => image.clone(),
+            _ => {
+                let image = ImageView::new_default(
+                    TransientAttachmentImage::new(
+                        &*self.allocator,
+                        dimensions,
+                        self.output_format,
+                        self.samples,
+                    )
+                    .unwrap(),
+                )
+                .unwrap();
+                self.msaa_image = Some(image.clone());
+                image
+            }
         }
     }
 
     /// Place view exactly over swapchain image target.
-    /// Texture draw pipeline uses a quad onto which it places the view.
+    /// Texture draw pipeline uses a quad onto which it places the view, tinted by `params`. Adjust
+    /// `params.exposure` each frame, e.g. for auto exposure of HDR views.
     pub fn render<F>(
         &mut self,
         before_future: F,
-        view: DeviceImageView,
+        view: Arc<dyn ImageViewAbstract>,
         target: SwapchainImageView,
+        params: DrawParams,
+        timer: Option<&GpuTimer>,
     ) -> Box<dyn GpuFuture>
     where
         F: GpuFuture + 'static,
     {
         // Get dimensions
         let img_dims = target.image().dimensions();
-        // Create framebuffer (must be in same order as render pass description in `new`
-        let framebuffer = Framebuffer::new(self.render_pass.clone(), FramebufferCreateInfo {
-            attachments: vec![target],
-            ..Default::default()
-        })
-        .unwrap();
         // Create primary command buffer builder
         let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
             &self.command_buffer_allocator,
@@ -95,24 +294,144 @@ impl RenderPassPlaceOverFrame {
             CommandBufferUsage::OneTimeSubmit,
         )
         .unwrap();
+        // Timer must be started outside the render pass
+        if let Some(timer) = timer {
+            timer.begin(&mut command_buffer_builder);
+        }
         // Begin render pass
-        command_buffer_builder
-            .begin_render_pass(
-                RenderPassBeginInfo {
-                    clear_values: vec![Some([0.0; 4].into())],
-                    ..RenderPassBeginInfo::framebuffer(framebuffer)
-                },
-                SubpassContents::SecondaryCommandBuffers,
-            )
-            .unwrap();
-        // Create secondary command buffer from texture pipeline & send draw commands
-        let cb = self
+        match self.render_pass.clone() {
+            Some(render_pass) => {
+                // Create framebuffer (must be in same order as render pass description in
+                // `create_render_pass`)
+                let extra_clear_values = self
+                    .extra_formats
+                    .iter()
+                    .map(|format| Some(zero_clear_value(*format)))
+                    .collect::<Vec<_>>();
+                let extra_attachments = self.extra_attachments(img_dims.width_height());
+                let (attachments, clear_values) = if self.samples == SampleCount::Sample1 {
+                    let mut attachments = vec![target as Arc<dyn ImageViewAbstract>];
+                    attachments.extend(
+                        extra_attachments
+                            .iter()
+                            .map(|a| a.image.clone() as Arc<dyn ImageViewAbstract>),
+                    );
+                    let mut clear_values = vec![Some([0.0; 4].into())];
+                    clear_values.extend(extra_clear_values);
+                    (attachments, clear_values)
+                } else {
+                    let extra_msaa_images = extra_attachments
+                        .iter()
+                        .map(|a| a.msaa_image.clone().unwrap() as Arc<dyn ImageViewAbstract>)
+                        .collect::<Vec<_>>();
+                    let extra_images = extra_attachments
+                        .iter()
+                        .map(|a| a.image.clone() as Arc<dyn ImageViewAbstract>)
+                        .collect::<Vec<_>>();
+                    let msaa_image = self.msaa_image(img_dims.width_height());
+                    // Multisampled attachments, then what they're resolved into
+                    let mut attachments = vec![msaa_image as Arc<dyn ImageViewAbstract>];
+                    attachments.extend(extra_msaa_images);
+                    attachments.push(target);
+                    attachments.extend(extra_images);
+                    let mut clear_values = vec![Some([0.0; 4].into())];
+                    clear_values.extend(extra_clear_values);
+                    clear_values.resize(attachments.len(), None);
+                    (attachments, clear_values)
+                };
+                let framebuffer = Framebuffer::new(render_pass, FramebufferCreateInfo {
+                    attachments,
+                    ..Default::default()
+                })
+                .unwrap();
+                command_buffer_builder
+                    .begin_render_pass(
+                        RenderPassBeginInfo {
+                            clear_values,
+                            ..RenderPassBeginInfo::framebuffer(framebuffer)
+                        },
+                        SubpassContents::SecondaryCommandBuffers,
+                    )
+                    .unwrap();
+            }
+            None => {
+                // Render to the swapchain image directly, resolving into it when multisampled
+                let color_attachment = if self.samples == SampleCount::Sample1 {
+                    RenderingAttachmentInfo {
+                        load_op: LoadOp::Clear,
+                        store_op: StoreOp::Store,
+                        clear_value: Some([0.0; 4].into()),
+                        ..RenderingAttachmentInfo::image_view(target)
+                    }
+                } else {
+                    let msaa_image = self.msaa_image(img_dims.width_height());
+                    RenderingAttachmentInfo {
+                        load_op: LoadOp::Clear,
+                        store_op: StoreOp::DontCare,
+                        clear_value: Some([0.0; 4].into()),
+                        resolve_info: Some(RenderingAttachmentResolveInfo::image_view(target)),
+                        ..RenderingAttachmentInfo::image_view(msaa_image)
+                    }
+                };
+                let samples = self.samples;
+                let extra_color_attachments = self
+                    .extra_formats
+                    .clone()
+                    .into_iter()
+                    .zip(self.extra_attachments(img_dims.width_height()))
+                    .map(|(format, attachment)| {
+                        let clear_value = Some(zero_clear_value(format));
+                        Some(if samples == SampleCount::Sample1 {
+                            RenderingAttachmentInfo {
+                                load_op: LoadOp::Clear,
+                                store_op: StoreOp::Store,
+                                clear_value,
+                                ..RenderingAttachmentInfo::image_view(attachment.image.clone())
+                            }
+                        } else {
+                            RenderingAttachmentInfo {
+                                load_op: LoadOp::Clear,
+                                store_op: StoreOp::DontCare,
+                                clear_value,
+                                resolve_info: Some(RenderingAttachmentResolveInfo {
+                                    mode: ResolveMode::SampleZero,
+                                    ..RenderingAttachmentResolveInfo::image_view(
+                                        attachment.image.clone(),
+                                    )
+                                }),
+                                ..RenderingAttachmentInfo::image_view(
+                                    attachment.msaa_image.clone().unwrap(),
+                                )
+                            }
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let mut color_attachments = vec![Some(color_attachment)];
+                color_attachments.extend(extra_color_attachments);
+                command_buffer_builder
+                    .begin_rendering(RenderingInfo {
+                        color_attachments,
+                        contents: SubpassContents::SecondaryCommandBuffers,
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+        }
+        // Create secondary command buffers from texture pipeline & send draw commands
+        let command_buffers = self
             .pixels_draw_pipeline
-            .draw(img_dims.width_height(), view);
+            .draw(img_dims.width_height(), view, params);
         // Execute above commands (subpass)
-        command_buffer_builder.execute_commands(cb).unwrap();
+        util::execute_secondaries(&mut command_buffer_builder, command_buffers).unwrap();
         // End render pass
-        command_buffer_builder.end_render_pass().unwrap();
+        if self.render_pass.is_some() {
+            command_buffer_builder.end_render_pass().unwrap();
+        } else {
+            command_buffer_builder.end_rendering().unwrap();
+        }
+        if let Some(timer) = timer {
+            timer.end(&mut command_buffer_builder);
+        }
         // Build command buffer
         let command_buffer = command_buffer_builder.build().unwrap();
         // Execute primary command buffer
@@ -123,3 +442,75 @@ impl RenderPassPlaceOverFrame {
         after_future.boxed()
     }
 }
+
+/// Creates a single subpass render pass drawing to the output and an attachment of each of
+/// `extra_formats`, in that order of color locations. When multisampled, the multisampled
+/// attachments come first, followed by those they're resolved into.
+fn create_render_pass(
+    device: Arc<Device>,
+    output_format: Format,
+    extra_formats: &[Format],
+    samples: SampleCount,
+) -> Arc<RenderPass> {
+    let formats = [output_format]
+        .into_iter()
+        .chain(extra_formats.iter().copied())
+        .collect::<Vec<_>>();
+    let attachment = |format, samples, load_op, store_op| AttachmentDescription {
+        format: Some(format),
+        samples,
+        load_op,
+        store_op,
+        initial_layout: ImageLayout::ColorAttachmentOptimal,
+        final_layout: ImageLayout::ColorAttachmentOptimal,
+        ..Default::default()
+    };
+    let reference = |index: usize| {
+        Some(AttachmentReference {
+            attachment: index as u32,
+            layout: ImageLayout::ColorAttachmentOptimal,
+            ..Default::default()
+        })
+    };
+    let count = formats.len();
+    let (attachments, resolve_attachments) = if samples == SampleCount::Sample1 {
+        let attachments = formats
+            .iter()
+            .map(|format| attachment(*format, samples, LoadOp::Clear, StoreOp::Store))
+            .collect::<Vec<_>>();
+        (attachments, vec![])
+    } else {
+        let mut attachments = formats
+            .iter()
+            .map(|format| attachment(*format, samples, LoadOp::Clear, StoreOp::DontCare))
+            .collect::<Vec<_>>();
+        attachments.extend(formats.iter().map(|format| {
+            attachment(
+                *format,
+                SampleCount::Sample1,
+                LoadOp::DontCare,
+                StoreOp::Store,
+            )
+        }));
+        (attachments, (count..2 * count).map(reference).collect())
+    };
+    RenderPass::new(device, RenderPassCreateInfo {
+        attachments,
+        subpasses: vec![SubpassDescription {
+            color_attachments: (0..count).map(reference).collect(),
+            resolve_attachments,
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Clears an attachment of `format` to zero, integer formats need integer clear values
+fn zero_clear_value(format: Format) -> ClearValue {
+    match format.type_color() {
+        Some(NumericType::UINT) => ClearValue::Uint([0; 4]),
+        Some(NumericType::SINT) => ClearValue::Int([0; 4]),
+        _ => ClearValue::Float([0.0; 4]),
+    }
+}
@@ -1,15 +1,14 @@
 #[cfg(feature = "example_has_gui")]
 use bevy::window::close_on_esc;
 #[cfg(feature = "example_has_gui")]
-use bevy::{
-    app::PluginGroupBuilder,
-    prelude::*,
-    window::{CreateWindow, WindowId, WindowMode},
-};
+use bevy::{app::PluginGroupBuilder, prelude::*, window::WindowMode};
 #[cfg(feature = "example_has_gui")]
 use bevy_vulkano::egui_winit_vulkano::egui;
 #[cfg(feature = "example_has_gui")]
-use bevy_vulkano::{BevyVulkanoWindows, VulkanoWinitConfig, VulkanoWinitPlugin};
+use bevy_vulkano::{
+    egui::{present_with_gui, EguiVulkanoPlugin},
+    BevyVulkanoWindows, VulkanoWinitConfig, VulkanoWinitPlugin,
+};
 
 #[cfg(feature = "example_has_gui")]
 pub struct PluginBundle;
@@ -23,6 +22,7 @@ impl PluginGroup for PluginBundle {
             // Don't add default bevy plugins or WinitPlugin. This owns "core loop" (runner).
             // Bevy winit and render should be excluded
             .add(VulkanoWinitPlugin::default())
+            .add(EguiVulkanoPlugin)
     }
 }
 
@@ -68,38 +68,27 @@ fn main() {
 }
 
 #[cfg(feature = "example_has_gui")]
-fn create_new_window_system(mut create_window_events: EventWriter<CreateWindow>) {
-    let window_id = WindowId::new();
-    create_window_events.send(CreateWindow {
-        id: window_id,
-        descriptor: WindowDescriptor {
-            width: 512.,
-            height: 512.,
-            present_mode: bevy::window::PresentMode::Fifo,
-            title: "Secondary window".to_string(),
-            ..Default::default()
-        },
-    });
+fn create_new_window_system(mut commands: Commands) {
+    // Spawning a window entity creates its winit window & renderer on the next frame
+    commands.spawn(secondary_window());
+}
+
+#[cfg(feature = "example_has_gui")]
+fn secondary_window() -> Window {
+    Window {
+        resolution: (512., 512.).into(),
+        // Each window has its own swapchain, so secondary windows don't need vsync
+        present_mode: bevy::window::PresentMode::Immediate,
+        title: "Secondary window".to_string(),
+        ..Default::default()
+    }
 }
 
 /// Adds new window when space is pressed
 #[cfg(feature = "example_has_gui")]
-fn create_new_window_on_space_system(
-    keys: Res<Input<KeyCode>>,
-    mut create_window_events: EventWriter<CreateWindow>,
-) {
+fn create_new_window_on_space_system(keys: Res<Input<KeyCode>>, mut commands: Commands) {
     if keys.just_pressed(KeyCode::Space) {
-        let window_id = WindowId::new();
-        create_window_events.send(CreateWindow {
-            id: window_id,
-            descriptor: WindowDescriptor {
-                width: 512.,
-                height: 512.,
-                present_mode: bevy::window::PresentMode::Fifo,
-                title: "Secondary window".to_string(),
-                ..Default::default()
-            },
-        });
+        commands.spawn(secondary_window());
     }
 }
 
@@ -121,11 +110,10 @@ pub fn main_render_system_primary_window(mut vulkano_windows: NonSendMut<BevyVul
         .show(&ctx, |ui| {
             ui.label("Primary Window");
         });
-    let final_image = window_renderer.swapchain_image_view();
-    // Render egui
-    let after = gui.draw_on_image(before, final_image);
-    // Finish frame
-    window_renderer.present(after, true);
+    // Render egui over the frame & finish frame
+    if let Err(e) = present_with_gui(window_renderer, gui, before, true) {
+        bevy::log::error!("Failed to present frame: {}", e);
+    }
 }
 
 #[cfg(feature = "example_has_gui")]
@@ -156,10 +144,9 @@ pub fn main_render_system_secondary_window(mut vulkano_windows: NonSendMut<BevyV
                     .clicked()
                     .then(|| println!("Clicked me!"));
             });
-        // Render egui
-        let final_image = window_renderer.swapchain_image_view();
-        let after = gui.draw_on_image(before, final_image);
-        // Finish frame
-        window_renderer.present(after, false);
+        // Render egui over the frame & finish frame
+        if let Err(e) = present_with_gui(window_renderer, gui, before, false) {
+            bevy::log::error!("Failed to present frame: {}", e);
+        }
     }
 }
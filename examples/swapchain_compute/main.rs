@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use bevy::{
+    app::PluginGroupBuilder,
+    prelude::*,
+    window::{close_on_esc, PrimaryWindow, WindowMode},
+};
+use bevy_vulkano::{
+    BevyVulkanoContext, BevyVulkanoWindows, VulkanoRendererError, VulkanoWinitConfig,
+    VulkanoWinitPlugin,
+};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::Features,
+    format::Format,
+    image::{ImageAccess, ImageUsage},
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    swapchain::ColorSpace,
+    sync::GpuFuture,
+};
+
+/// Local workgroup size of the compute shader in both dimensions
+const LOCAL_SIZE: u32 = 8;
+
+pub struct PluginBundle;
+
+impl PluginGroup for PluginBundle {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<PluginBundle>()
+            .add(bevy::core::TaskPoolPlugin::default())
+            .add(bevy::core::TypeRegistrationPlugin)
+            .add(bevy::core::FrameCountPlugin)
+            .add(bevy::input::InputPlugin)
+            .add(bevy::time::TimePlugin)
+            .add(VulkanoWinitPlugin::default())
+    }
+}
+
+fn main() {
+    App::new()
+        .insert_non_send_resource(VulkanoWinitConfig {
+            // Write the swapchain images from a compute shader. sRGB formats don't support storage
+            // images, so the shader encodes sRGB itself
+            swapchain_image_usage: ImageUsage::STORAGE,
+            preferred_surface_formats: vec![
+                (Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear),
+                (Format::R8G8B8A8_UNORM, ColorSpace::SrgbNonLinear),
+            ],
+            // The swapchain may be BGRA or RGBA, so the shader writes without a format qualifier
+            required_features: Features {
+                shader_storage_image_write_without_format: true,
+                ..Features::empty()
+            },
+            ..VulkanoWinitConfig::default()
+        })
+        .add_plugins(PluginBundle.set(VulkanoWinitPlugin {
+            window_descriptor: Window {
+                resolution: (1024.0, 768.0).into(),
+                title: "Bevy Vulkano Swapchain Compute".to_string(),
+                present_mode: bevy::window::PresentMode::Fifo,
+                resizable: true,
+                mode: WindowMode::Windowed,
+                ..default()
+            },
+        }))
+        .add_startup_system(create_pipeline)
+        .add_system(close_on_esc)
+        .add_system(render_system.in_base_set(CoreSet::PostUpdate))
+        .run();
+}
+
+/// Fullscreen effect written directly to the swapchain image
+#[derive(Resource)]
+struct EffectPipeline(Arc<ComputePipeline>);
+
+fn create_pipeline(mut commands: Commands, context: Res<BevyVulkanoContext>) {
+    let shader = cs::load(context.context.device().clone()).unwrap();
+    let pipeline = ComputePipeline::new(
+        context.context.device().clone(),
+        shader.entry_point("main").unwrap(),
+        &(),
+        Some(context.pipeline_cache()),
+        |_| {},
+    )
+    .unwrap();
+    commands.insert_resource(EffectPipeline(pipeline));
+}
+
+fn render_system(
+    context: Res<BevyVulkanoContext>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    pipeline: Res<EffectPipeline>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    let Ok(window) = primary_window.get_single() else {
+        return;
+    };
+    let Some(primary_window) = vulkano_windows.get_window_renderer_mut(window) else {
+        return;
+    };
+    let before = match primary_window.acquire() {
+        Err(VulkanoRendererError::WindowMinimized) => return,
+        Err(e) => {
+            bevy::log::error!("Failed to start frame: {}", e);
+            return;
+        }
+        Ok(f) => f,
+    };
+
+    // The acquired image changes each frame, so is the descriptor set
+    let Some(target) = primary_window.swapchain_storage_image_view() else {
+        bevy::log::error!("Swapchain images can't be used as storage images on this device");
+        primary_window.present(before, false).ok();
+        return;
+    };
+    let dimensions = target.image().dimensions().width_height();
+    let pipeline = &pipeline.0;
+    let layout = pipeline.layout().set_layouts().first().unwrap();
+    let set = PersistentDescriptorSet::new(&*context.descriptor_set_allocator(), layout.clone(), [
+        WriteDescriptorSet::image_view(0, target),
+    ])
+    .unwrap();
+
+    // Swapchain images are exclusive to the graphics queue family, so dispatch there. The builder
+    // transitions the image to `General` for the dispatch, and present to `PresentSrc`
+    let queue = primary_window.graphics_queue();
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &*context.command_buffer_allocator(),
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+    builder
+        .bind_pipeline_compute(pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            pipeline.layout().clone(),
+            0,
+            set,
+        )
+        .push_constants(pipeline.layout().clone(), 0, cs::PushConstants {
+            time: time.elapsed_seconds(),
+        })
+        .dispatch([
+            dimensions[0].div_ceil(LOCAL_SIZE),
+            dimensions[1].div_ceil(LOCAL_SIZE),
+            1,
+        ])
+        .unwrap();
+    let command_buffer = builder.build().unwrap();
+    let after = before.then_execute(queue, command_buffer).unwrap().boxed();
+
+    if let Err(e) = primary_window.present(after, false) {
+        bevy::log::error!("Failed to present frame: {}", e);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: "
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform writeonly image2D img;
+
+layout(push_constant) uniform PushConstants {
+    float time;
+} push_constants;
+
+vec3 linear_to_srgb(vec3 color) {
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+void main() {
+    ivec2 size = imageSize(img);
+    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+    // Workgroups may overhang the image when its size isn't a multiple of the local size
+    if (any(greaterThanEqual(pos, size))) {
+        return;
+    }
+    vec2 uv = vec2(pos) / vec2(size);
+    float t = push_constants.time;
+    float v = sin(uv.x * 10.0 + t) + sin(uv.y * 10.0 + t * 1.3) + sin((uv.x + uv.y) * 10.0 + t * 0.7);
+    vec3 color = 0.5 + 0.5 * cos(v + vec3(0.0, 2.0, 4.0));
+    imageStore(img, pos, vec4(linear_to_srgb(color), 1.0));
+}"
+    }
+}
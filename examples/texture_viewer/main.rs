@@ -0,0 +1,155 @@
+use std::{path::PathBuf, sync::Arc};
+
+use bevy::{
+    app::PluginGroupBuilder,
+    prelude::*,
+    window::{close_on_esc, PrimaryWindow, WindowMode},
+};
+use bevy_vulkano::{
+    BevyVulkanoContext, BevyVulkanoWindows, VulkanoRendererError, VulkanoWinitPlugin,
+};
+use vulkano::{
+    format::Format,
+    image::{view::ImageView, ImmutableImage, SampleCount},
+    sync::GpuFuture,
+};
+
+// The composite pass of the game of life example draws any sampled image over a window
+#[allow(dead_code)]
+#[path = "../game_of_life/pixels_draw_pipeline.rs"]
+mod pixels_draw_pipeline;
+#[allow(dead_code)]
+#[path = "../game_of_life/place_over_frame.rs"]
+mod place_over_frame;
+
+use crate::{
+    pixels_draw_pipeline::{DrawParams, SamplerSettings, Tonemap},
+    place_over_frame::RenderPassPlaceOverFrame,
+};
+
+pub struct PluginBundle;
+
+impl PluginGroup for PluginBundle {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<PluginBundle>()
+            .add(bevy::core::TaskPoolPlugin::default())
+            .add(bevy::core::TypeRegistrationPlugin)
+            .add(bevy::core::FrameCountPlugin)
+            .add(bevy::input::InputPlugin)
+            .add(bevy::time::TimePlugin)
+            .add(VulkanoWinitPlugin::default())
+    }
+}
+
+/// Shows a PNG (or any format the `image` crate reads) scaled to the window:
+/// `cargo run --example texture_viewer -- path/to/image.png`
+fn main() {
+    let Some(path) = std::env::args_os().nth(1).map(PathBuf::from) else {
+        eprintln!("Usage: cargo run --example texture_viewer -- <image path>");
+        std::process::exit(1);
+    };
+    App::new()
+        .insert_resource(TexturePath(path))
+        .add_plugins(PluginBundle.set(VulkanoWinitPlugin {
+            window_descriptor: Window {
+                resolution: (1024.0, 768.0).into(),
+                title: "Bevy Vulkano Texture Viewer".to_string(),
+                present_mode: bevy::window::PresentMode::AutoVsync,
+                resizable: true,
+                mode: WindowMode::Windowed,
+                ..default()
+            },
+        }))
+        .add_startup_system(load_texture)
+        .add_system(close_on_esc)
+        .add_system(render_system.in_base_set(CoreSet::PostUpdate))
+        .run();
+}
+
+#[derive(Resource)]
+struct TexturePath(PathBuf);
+
+/// The uploaded texture & the pass drawing it
+#[derive(Resource)]
+struct TextureViewer {
+    texture: Arc<ImageView<ImmutableImage>>,
+    place_over_frame: RenderPassPlaceOverFrame,
+}
+
+fn load_texture(
+    mut commands: Commands,
+    path: Res<TexturePath>,
+    context: Res<BevyVulkanoContext>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let image = image::open(&path.0)
+        .unwrap_or_else(|e| panic!("Failed to load {}: {}", path.0.display(), e))
+        .to_rgba8();
+    // PNG colors are sRGB encoded. An sRGB format decodes them to linear when sampled, and the
+    // draw pipeline encodes them again for the swapchain, so filtering happens on linear colors
+    let (texture, upload_future) = context
+        .context
+        .upload_mipmapped_image_async(&image, image.dimensions().into(), Format::R8G8B8A8_SRGB)
+        .unwrap();
+    // Wait for the upload & mipmap generation once here instead of joining it with the first
+    // frame
+    upload_future
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let window_renderer = vulkano_windows
+        .get_window_renderer(primary_window.single())
+        .unwrap();
+    let place_over_frame = RenderPassPlaceOverFrame::new(
+        &context,
+        window_renderer.graphics_queue(),
+        window_renderer.swapchain_format(),
+        SampleCount::Sample1,
+        context.dynamic_rendering_enabled(),
+        // Bilinear filtering between trilinearly selected mip levels keeps minified images from
+        // aliasing
+        SamplerSettings::smooth(),
+        Tonemap::None,
+        vec![],
+    );
+    commands.insert_resource(TextureViewer {
+        texture,
+        place_over_frame,
+    });
+}
+
+fn render_system(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut viewer: ResMut<TextureViewer>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let Ok(window) = primary_window.get_single() else {
+        return;
+    };
+    let Some(window_renderer) = vulkano_windows.get_window_renderer_mut(window) else {
+        return;
+    };
+    // Resizes recreate the swapchain on acquire, and the pass follows the new swapchain image size
+    let before = match window_renderer.acquire() {
+        Err(VulkanoRendererError::WindowMinimized) => return,
+        Err(e) => {
+            bevy::log::error!("Failed to start frame: {}", e);
+            return;
+        }
+        Ok(f) => f,
+    };
+    let texture = viewer.texture.clone();
+    let after = viewer.place_over_frame.render(
+        before,
+        texture,
+        window_renderer.swapchain_image_view(),
+        DrawParams::default(),
+        None,
+    );
+    if let Err(e) = window_renderer.present(after, false) {
+        bevy::log::error!("Failed to present frame: {}", e);
+    }
+}
@@ -0,0 +1,364 @@
+use std::sync::Arc;
+
+use bevy::{
+    app::PluginGroupBuilder,
+    math::{Mat4, Vec3},
+    prelude::*,
+    window::{close_on_esc, PrimaryWindow, WindowMode},
+};
+use bevy_vulkano::{
+    BevyVulkanoContext, BevyVulkanoWindows, SwapchainImageView, TransientAttachmentImage,
+    VulkanoRendererError, VulkanoWinitPlugin,
+};
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        RenderPassBeginInfo, SubpassContents,
+    },
+    format::Format,
+    image::{view::ImageView, ImageAccess, ImageViewAbstract, SampleCount},
+    pipeline::{
+        graphics::{
+            depth_stencil::DepthStencilState,
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::{CullMode, RasterizationState},
+            vertex_input::Vertex,
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline, Pipeline,
+    },
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
+    sync::GpuFuture,
+};
+
+/// Vulkan guarantees 4 samples for color & depth attachments of all formats that support them
+const SAMPLES: SampleCount = SampleCount::Sample4;
+/// Required to be supported as a depth attachment on all devices
+const DEPTH_FORMAT: Format = Format::D16_UNORM;
+
+pub struct PluginBundle;
+
+impl PluginGroup for PluginBundle {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<PluginBundle>()
+            .add(bevy::core::TaskPoolPlugin::default())
+            .add(bevy::core::TypeRegistrationPlugin)
+            .add(bevy::core::FrameCountPlugin)
+            .add(bevy::input::InputPlugin)
+            .add(bevy::time::TimePlugin)
+            .add(VulkanoWinitPlugin::default())
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(PluginBundle.set(VulkanoWinitPlugin {
+            window_descriptor: Window {
+                resolution: (1024.0, 768.0).into(),
+                title: "Bevy Vulkano Triangle 3D".to_string(),
+                present_mode: bevy::window::PresentMode::AutoVsync,
+                resizable: true,
+                mode: WindowMode::Windowed,
+                ..default()
+            },
+        }))
+        .add_startup_system(create_mesh_renderer)
+        .add_system(close_on_esc)
+        .add_system(render_system.in_base_set(CoreSet::PostUpdate))
+        .run();
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct MeshVertex {
+    #[format(R32G32B32_SFLOAT)]
+    position: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    color: [f32; 3],
+}
+
+/// A tetrahedron with a color per face, so that depth testing is visible as it rotates
+fn tetrahedron() -> Vec<MeshVertex> {
+    let corners = [[1.0, 1.0, 1.0], [1.0, -1.0, -1.0], [-1.0, 1.0, -1.0], [
+        -1.0, -1.0, 1.0,
+    ]];
+    // Counter-clockwise when seen from outside
+    let faces = [
+        ([0, 2, 3], [1.0, 0.2, 0.2]),
+        ([0, 3, 1], [0.2, 1.0, 0.2]),
+        ([0, 1, 2], [0.2, 0.2, 1.0]),
+        ([1, 3, 2], [1.0, 1.0, 0.2]),
+    ];
+    faces
+        .into_iter()
+        .flat_map(|(indices, color)| {
+            indices.map(|index| MeshVertex {
+                position: corners[index],
+                color,
+            })
+        })
+        .collect()
+}
+
+type AttachmentView = Arc<ImageView<TransientAttachmentImage>>;
+
+/// Renders a rotating mesh into a multisampled color attachment with a depth attachment, and
+/// resolves it into the swapchain image
+#[derive(Resource)]
+struct MeshRenderer {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    vertex_buffer: Subbuffer<[MeshVertex]>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    /// Transient multisampled color & depth attachments, recreated when the window is resized
+    attachments: Option<(AttachmentView, AttachmentView)>,
+}
+
+fn create_mesh_renderer(
+    mut commands: Commands,
+    context: Res<BevyVulkanoContext>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let window_renderer = vulkano_windows
+        .get_window_renderer(primary_window.single())
+        .unwrap();
+    let device = context.context.device().clone();
+    let output_format = window_renderer.swapchain_format();
+    let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+        attachments: {
+            intermediary: {
+                load: Clear,
+                store: DontCare,
+                format: output_format,
+                samples: SAMPLES,
+            },
+            depth: {
+                load: Clear,
+                store: DontCare,
+                format: DEPTH_FORMAT,
+                samples: SAMPLES,
+            },
+            color: {
+                load: DontCare,
+                store: Store,
+                format: output_format,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [intermediary],
+            depth_stencil: {depth},
+            resolve: [color],
+        }
+    )
+    .unwrap();
+    let vs = vs::load(device.clone()).unwrap();
+    let fs = fs::load(device.clone()).unwrap();
+    let pipeline = GraphicsPipeline::start()
+        .vertex_input_state(MeshVertex::per_vertex())
+        .vertex_shader(vs.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        // The viewport follows the window size, so it's set when drawing
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(fs.entry_point("main").unwrap(), ())
+        .rasterization_state(RasterizationState::new().cull_mode(CullMode::Back))
+        .depth_stencil_state(DepthStencilState::simple_depth_test())
+        .multisample_state(MultisampleState {
+            rasterization_samples: SAMPLES,
+            ..Default::default()
+        })
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build_with_cache(context.pipeline_cache())
+        .build(device)
+        .unwrap();
+
+    // Upload through a staging buffer on the transfer queue, and wait for it once here instead of
+    // joining it with the first frame
+    let (vertex_buffer, upload_future) = context
+        .context
+        .upload_buffer_async(tetrahedron(), BufferUsage::VERTEX_BUFFER)
+        .unwrap();
+    upload_future
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    commands.insert_resource(MeshRenderer {
+        render_pass,
+        pipeline,
+        vertex_buffer,
+        command_buffer_allocator: context.command_buffer_allocator(),
+        attachments: None,
+    });
+}
+
+impl MeshRenderer {
+    /// Returns the multisampled color & depth attachments, recreating them if the target size has
+    /// changed (resize)
+    fn attachments(
+        &mut self,
+        context: &BevyVulkanoContext,
+        dimensions: [u32; 2],
+        output_format: Format,
+    ) -> (AttachmentView, AttachmentView) {
+        match &self.attachments {
+            Some((color, depth)) if color.image().dimensions().width_height() == dimensions => {
+                (color.clone(), depth.clone())
+            }
+            _ => {
+                let allocator = context.context.memory_allocator();
+                let [color, depth] = [output_format, DEPTH_FORMAT].map(|format| {
+                    ImageView::new_default(
+                        TransientAttachmentImage::new(&**allocator, dimensions, format, SAMPLES)
+                            .unwrap(),
+                    )
+                    .unwrap()
+                });
+                self.attachments = Some((color.clone(), depth.clone()));
+                (color, depth)
+            }
+        }
+    }
+
+    fn render(
+        &mut self,
+        context: &BevyVulkanoContext,
+        before_future: Box<dyn GpuFuture>,
+        target: SwapchainImageView,
+        seconds: f32,
+        aspect_ratio: f32,
+        pre_transform: Mat4,
+    ) -> Box<dyn GpuFuture> {
+        let dimensions = target.image().dimensions().width_height();
+        let (color, depth) = self.attachments(context, dimensions, target.format().unwrap());
+        let framebuffer = Framebuffer::new(self.render_pass.clone(), FramebufferCreateInfo {
+            attachments: vec![color, depth, target],
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Projection keeps the aspect ratio of the current window size. Vulkan's clip space has Y
+        // pointing down, so flip it. On rotated displays, the swapchain image is in the display's
+        // native orientation and clip space is rotated to match
+        let mut projection = Mat4::perspective_rh(45f32.to_radians(), aspect_ratio, 0.1, 100.0);
+        projection.y_axis.y *= -1.0;
+        let projection = pre_transform * projection;
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 1.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let model = Mat4::from_rotation_y(seconds) * Mat4::from_rotation_x(seconds * 0.7);
+        let push_constants = vs::PushConstants {
+            mvp: (projection * view * model).to_cols_array_2d(),
+        };
+
+        let queue = context.context.graphics_queue().clone();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &*self.command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into()), Some(1.0.into()), None],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .set_viewport(0, [Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                depth_range: 0.0..1.0,
+            }])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .push_constants(self.pipeline.layout().clone(), 0, push_constants)
+            .bind_vertex_buffers(0, self.vertex_buffer.clone())
+            .draw(self.vertex_buffer.len() as u32, 1, 0, 0)
+            .unwrap()
+            .end_render_pass()
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        before_future
+            .then_execute(queue, command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+fn render_system(
+    context: Res<BevyVulkanoContext>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut mesh_renderer: ResMut<MeshRenderer>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    let Ok(window) = primary_window.get_single() else {
+        return;
+    };
+    let Some(window_renderer) = vulkano_windows.get_window_renderer_mut(window) else {
+        return;
+    };
+    // Resizes are handled by the plugin, which recreates the swapchain on next acquire. The
+    // mesh renderer follows the new swapchain image size
+    let before = match window_renderer.acquire() {
+        Err(VulkanoRendererError::WindowMinimized) => return,
+        Err(e) => {
+            bevy::log::error!("Failed to start frame: {}", e);
+            return;
+        }
+        Ok(f) => f,
+    };
+    let after = mesh_renderer.render(
+        &context,
+        before,
+        window_renderer.swapchain_image_view(),
+        time.elapsed_seconds(),
+        window_renderer.aspect_ratio(),
+        window_renderer.pre_transform_matrix(),
+    );
+    if let Err(e) = window_renderer.present(after, false) {
+        bevy::log::error!("Failed to present frame: {}", e);
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 color;
+
+layout(location = 0) out vec3 v_color;
+
+layout(push_constant) uniform PushConstants {
+    mat4 mvp;
+} push_constants;
+
+void main() {
+    gl_Position = push_constants.mvp * vec4(position, 1.0);
+    v_color = color;
+}"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location = 0) in vec3 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(v_color, 1.0);
+}"
+    }
+}
@@ -1,13 +1,9 @@
 use bevy::{app::AppExit, prelude::*};
-use bevy_vulkano::{BevyVulkanoContext, VulkanoWinitConfig, VulkanoWinitPlugin};
+use bevy_vulkano::{BevyVulkanoContext, HeadlessVulkanoPlugin};
 use vulkano::{
     buffer::{BufferUsage, CpuAccessibleBuffer},
-    command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-    },
-    descriptor_set::{
-        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
-    },
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
     pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
     sync,
     sync::GpuFuture,
@@ -17,11 +13,8 @@ use vulkano::{
 
 fn main() {
     App::new()
-        .insert_non_send_resource(VulkanoWinitConfig {
-            add_primary_window: false,
-            ..default()
-        })
-        .add_plugin(VulkanoWinitPlugin::default())
+        // No winit event loop or windows, just the Vulkano context
+        .add_plugin(HeadlessVulkanoPlugin)
         .add_startup_system(run_compute_shader_once_then_exit)
         .run();
 }
@@ -66,7 +59,7 @@ fn run_compute_shader_once_then_exit(
     let data_buffer = {
         let data_iter = (0..65536u32).collect::<Vec<u32>>();
         CpuAccessibleBuffer::from_iter(
-            context.context.memory_allocator(),
+            &**context.context.memory_allocator(),
             BufferUsage {
                 storage_buffer: true,
                 ..BufferUsage::empty()
@@ -77,23 +70,26 @@ fn run_compute_shader_once_then_exit(
         .unwrap()
     };
 
-    let command_buffer_allocator =
-        StandardCommandBufferAllocator::new(context.context.device().clone(), Default::default());
-
-    let descriptor_set_allocator =
-        StandardDescriptorSetAllocator::new(context.context.device().clone());
+    // Allocators are shared through the context
+    let command_buffer_allocator = context.command_buffer_allocator();
+    let descriptor_set_allocator = context.descriptor_set_allocator();
 
     // Create pipeline layout & descriptor set (data inputs)
     let layout = pipeline.layout().set_layouts().get(0).unwrap();
-    let set = PersistentDescriptorSet::new(&descriptor_set_allocator, layout.clone(), [
+    let set = PersistentDescriptorSet::new(&*descriptor_set_allocator, layout.clone(), [
         WriteDescriptorSet::buffer(0, data_buffer.clone()),
     ])
     .unwrap();
 
+    // Use the async compute queue if available
+    let queue = context
+        .compute_queue()
+        .unwrap_or_else(|| context.context.graphics_queue().clone());
+
     // Build command buffer
     let mut builder = AutoCommandBufferBuilder::primary(
-        &command_buffer_allocator,
-        context.context.compute_queue().queue_family_index(),
+        &*command_buffer_allocator,
+        queue.queue_family_index(),
         CommandBufferUsage::OneTimeSubmit,
     )
     .unwrap();
@@ -111,7 +107,7 @@ fn run_compute_shader_once_then_exit(
 
     // Execute the command buffer & wait on it to finish
     let future = sync::now(context.context.device().clone())
-        .then_execute(context.context.compute_queue().clone(), command_buffer)
+        .then_execute(queue, command_buffer)
         .unwrap()
         .then_signal_fence_and_flush()
         .unwrap();
@@ -0,0 +1,228 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::utils::HashMap;
+use vulkano::{
+    device::{Device, DeviceOwned},
+    memory::{
+        allocator::{
+            AllocationCreateInfo, AllocationCreationError, AllocationType, MemoryAlloc,
+            MemoryAllocator, MemoryTypeFilter, SuballocationCreateInfo,
+        },
+        DedicatedAllocation, ExternalMemoryHandleTypes, MemoryPropertyFlags, MemoryRequirements,
+    },
+    DeviceSize, VulkanObject,
+};
+
+use crate::SharedMemoryAllocator;
+
+/// Memory blocks of the context's memory allocator, see
+/// [`VulkanoContext::memory_allocator_stats`](crate::VulkanoContext::memory_allocator_stats).
+///
+/// vulkano's allocators don't expose their pools, and allocations free themselves when dropped
+/// without going through the allocator, so live allocations can't be counted. What's tracked
+/// instead are the blocks allocations are suballocated from, which vulkano's standard allocator
+/// keeps until it's dropped: normal churn reuses them, while memory that keeps growing shows up as
+/// an increasing block count. Dedicated allocations are freed along with their resource, so
+/// they're only included in `device_memory_allocations`, as is memory heap usage in
+/// [`VulkanoContext::memory_budget`](crate::VulkanoContext::memory_budget).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAllocatorStats {
+    /// Live `VkDeviceMemory` objects of the device, whether blocks, dedicated allocations or
+    /// allocated by other code.
+    pub device_memory_allocations: u32,
+    /// Blocks of the allocator per memory type.
+    pub memory_types: Vec<MemoryTypeStats>,
+}
+
+impl MemoryAllocatorStats {
+    /// Bytes of the allocator's blocks in `DEVICE_LOCAL` memory types
+    pub fn device_local_block_bytes(&self) -> DeviceSize {
+        self.memory_types
+            .iter()
+            .filter(|stats| {
+                stats
+                    .property_flags
+                    .intersects(MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .map(|stats| stats.block_bytes)
+            .sum()
+    }
+}
+
+/// Blocks of a memory type, see [`MemoryAllocatorStats`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryTypeStats {
+    pub memory_type_index: u32,
+    pub heap_index: u32,
+    pub property_flags: MemoryPropertyFlags,
+    /// Number of blocks allocations were suballocated from
+    pub blocks: u32,
+    /// Sum of the sizes of the blocks
+    pub block_bytes: DeviceSize,
+}
+
+/// Blocks suballocated from by the allocator, assumed to live as long as it like those of
+/// vulkano's standard allocator
+#[derive(Default)]
+pub(crate) struct AllocatorBlocks {
+    /// Memory type index & size of each block
+    blocks: Mutex<HashMap<ash::vk::DeviceMemory, (u32, DeviceSize)>>,
+}
+
+impl AllocatorBlocks {
+    fn record(&self, result: &Result<MemoryAlloc, AllocationCreationError>) {
+        let Ok(alloc) = result else {
+            return;
+        };
+        // Root & dedicated allocations own their memory and free it when dropped
+        if alloc.parent_allocation().is_none() {
+            return;
+        }
+        let device_memory = alloc.device_memory();
+        self.blocks
+            .lock()
+            .unwrap()
+            .entry(device_memory.handle())
+            .or_insert((
+                device_memory.memory_type_index(),
+                device_memory.allocation_size(),
+            ));
+    }
+
+    pub(crate) fn stats(&self, device: &Device) -> MemoryAllocatorStats {
+        let memory_types = &device.physical_device().memory_properties().memory_types;
+        let mut memory_type_stats: Vec<MemoryTypeStats> = memory_types
+            .iter()
+            .enumerate()
+            .map(|(i, memory_type)| MemoryTypeStats {
+                memory_type_index: i as u32,
+                heap_index: memory_type.heap_index,
+                property_flags: memory_type.property_flags,
+                blocks: 0,
+                block_bytes: 0,
+            })
+            .collect();
+        for (memory_type_index, size) in self.blocks.lock().unwrap().values() {
+            if let Some(stats) = memory_type_stats.get_mut(*memory_type_index as usize) {
+                stats.blocks += 1;
+                stats.block_bytes += size;
+            }
+        }
+        MemoryAllocatorStats {
+            device_memory_allocations: device.allocation_count(),
+            memory_types: memory_type_stats,
+        }
+    }
+}
+
+/// Forwards to the allocator created by the context's allocator factory, tracking the blocks of
+/// its allocations
+pub(crate) struct TrackingMemoryAllocator {
+    inner: Arc<SharedMemoryAllocator>,
+    blocks: Arc<AllocatorBlocks>,
+}
+
+impl TrackingMemoryAllocator {
+    pub(crate) fn new(inner: Arc<SharedMemoryAllocator>) -> TrackingMemoryAllocator {
+        TrackingMemoryAllocator {
+            inner,
+            blocks: Arc::default(),
+        }
+    }
+
+    pub(crate) fn blocks(&self) -> Arc<AllocatorBlocks> {
+        self.blocks.clone()
+    }
+}
+
+unsafe impl DeviceOwned for TrackingMemoryAllocator {
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+unsafe impl MemoryAllocator for TrackingMemoryAllocator {
+    fn find_memory_type_index(
+        &self,
+        memory_type_bits: u32,
+        filter: MemoryTypeFilter,
+    ) -> Option<u32> {
+        self.inner.find_memory_type_index(memory_type_bits, filter)
+    }
+
+    fn allocate_from_type(
+        &self,
+        memory_type_index: u32,
+        create_info: SuballocationCreateInfo,
+    ) -> Result<MemoryAlloc, AllocationCreationError> {
+        let result = self
+            .inner
+            .allocate_from_type(memory_type_index, create_info);
+        self.blocks.record(&result);
+        result
+    }
+
+    unsafe fn allocate_from_type_unchecked(
+        &self,
+        memory_type_index: u32,
+        create_info: SuballocationCreateInfo,
+        never_allocate: bool,
+    ) -> Result<MemoryAlloc, AllocationCreationError> {
+        let result =
+            self.inner
+                .allocate_from_type_unchecked(memory_type_index, create_info, never_allocate);
+        self.blocks.record(&result);
+        result
+    }
+
+    fn allocate(
+        &self,
+        requirements: MemoryRequirements,
+        allocation_type: AllocationType,
+        create_info: AllocationCreateInfo,
+        dedicated_allocation: Option<DedicatedAllocation<'_>>,
+    ) -> Result<MemoryAlloc, AllocationCreationError> {
+        let result = self.inner.allocate(
+            requirements,
+            allocation_type,
+            create_info,
+            dedicated_allocation,
+        );
+        self.blocks.record(&result);
+        result
+    }
+
+    unsafe fn allocate_unchecked(
+        &self,
+        requirements: MemoryRequirements,
+        allocation_type: AllocationType,
+        create_info: AllocationCreateInfo,
+        dedicated_allocation: Option<DedicatedAllocation<'_>>,
+    ) -> Result<MemoryAlloc, AllocationCreationError> {
+        let result = self.inner.allocate_unchecked(
+            requirements,
+            allocation_type,
+            create_info,
+            dedicated_allocation,
+        );
+        self.blocks.record(&result);
+        result
+    }
+
+    unsafe fn allocate_dedicated_unchecked(
+        &self,
+        memory_type_index: u32,
+        allocation_size: DeviceSize,
+        dedicated_allocation: Option<DedicatedAllocation<'_>>,
+        export_handle_types: ExternalMemoryHandleTypes,
+    ) -> Result<MemoryAlloc, AllocationCreationError> {
+        let result = self.inner.allocate_dedicated_unchecked(
+            memory_type_index,
+            allocation_size,
+            dedicated_allocation,
+            export_handle_types,
+        );
+        self.blocks.record(&result);
+        result
+    }
+}
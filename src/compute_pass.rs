@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    command_buffer::{
+        allocator::CommandBufferAllocator, AutoCommandBufferBuilder, DispatchIndirectCommand,
+        PipelineExecutionError,
+    },
+    descriptor_set::{DescriptorSetWithOffsets, DescriptorSetsCollection},
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+};
+
+/// Records the bind & dispatch of a compute pipeline with its descriptor sets, which compute
+/// passes otherwise repeat for every dispatch.
+///
+/// ```ignore
+/// ComputePass::new(pipeline, set).record_with_push_constants(
+///     &mut builder,
+///     push_constants,
+///     Dispatch::extent([width, height, 1], [8, 8, 1]),
+/// )?;
+/// ```
+#[derive(Clone)]
+pub struct ComputePass {
+    pipeline: Arc<ComputePipeline>,
+    descriptor_sets: Vec<DescriptorSetWithOffsets>,
+}
+
+#[allow(clippy::result_large_err)]
+impl ComputePass {
+    /// Descriptor sets are bound starting from set 0
+    pub fn new(
+        pipeline: Arc<ComputePipeline>,
+        descriptor_sets: impl DescriptorSetsCollection,
+    ) -> ComputePass {
+        ComputePass {
+            pipeline,
+            descriptor_sets: descriptor_sets.into_vec(),
+        }
+    }
+
+    pub fn pipeline(&self) -> &Arc<ComputePipeline> {
+        &self.pipeline
+    }
+
+    /// Records binding the pipeline & descriptor sets followed by the dispatch
+    pub fn record<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        dispatch: Dispatch,
+    ) -> Result<(), PipelineExecutionError> {
+        self.bind(builder);
+        dispatch.record(builder)
+    }
+
+    /// Like [`ComputePass::record`], pushing `push_constants` at offset 0 before the dispatch
+    pub fn record_with_push_constants<L, A: CommandBufferAllocator, Pc: BufferContents>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        push_constants: Pc,
+        dispatch: Dispatch,
+    ) -> Result<(), PipelineExecutionError> {
+        self.bind(builder);
+        builder.push_constants(self.pipeline.layout().clone(), 0, push_constants);
+        dispatch.record(builder)
+    }
+
+    fn bind<L, A: CommandBufferAllocator>(&self, builder: &mut AutoCommandBufferBuilder<L, A>) {
+        builder.bind_pipeline_compute(self.pipeline.clone());
+        if !self.descriptor_sets.is_empty() {
+            builder.bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.pipeline.layout().clone(),
+                0,
+                self.descriptor_sets.clone(),
+            );
+        }
+    }
+}
+
+/// Workgroup counts of a [`ComputePass`] dispatch
+#[derive(Clone)]
+pub enum Dispatch {
+    /// Workgroups in each dimension
+    GroupCounts([u32; 3]),
+    /// Workgroup counts written to a buffer, e.g. by a previous pass on the GPU
+    Indirect(Subbuffer<[DispatchIndirectCommand]>),
+}
+
+#[allow(clippy::result_large_err)]
+impl Dispatch {
+    /// Enough workgroups of `local_size` to cover `extent`, see [`workgroup_counts`]
+    pub fn extent(extent: [u32; 3], local_size: [u32; 3]) -> Dispatch {
+        Dispatch::GroupCounts(workgroup_counts(extent, local_size))
+    }
+
+    fn record<L, A: CommandBufferAllocator>(
+        self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+    ) -> Result<(), PipelineExecutionError> {
+        match self {
+            Dispatch::GroupCounts(group_counts) => builder.dispatch(group_counts)?,
+            Dispatch::Indirect(indirect_buffer) => builder.dispatch_indirect(indirect_buffer)?,
+        };
+        Ok(())
+    }
+}
+
+/// Number of workgroups of `local_size` needed to cover `extent` in each dimension, rounding up
+/// so that partial workgroups at the edges are dispatched too. Shaders should skip invocations
+/// outside `extent`.
+///
+/// # Panics
+///
+/// - Panics if a dimension of `local_size` is 0
+pub fn workgroup_counts(extent: [u32; 3], local_size: [u32; 3]) -> [u32; 3] {
+    assert!(
+        local_size.iter().all(|size| *size > 0),
+        "local size must not be 0"
+    );
+    [0, 1, 2].map(|i| {
+        let (extent, local_size) = (extent[i], local_size[i]);
+        extent / local_size + u32::from(extent % local_size != 0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::workgroup_counts;
+
+    #[test]
+    fn exact_multiples() {
+        assert_eq!(workgroup_counts([64, 32, 1], [8, 8, 1]), [8, 4, 1]);
+        assert_eq!(workgroup_counts([256, 1, 1], [256, 1, 1]), [1, 1, 1]);
+    }
+
+    #[test]
+    fn partial_workgroups_round_up() {
+        assert_eq!(workgroup_counts([65, 33, 1], [8, 8, 1]), [9, 5, 1]);
+        assert_eq!(workgroup_counts([1, 7, 3], [8, 8, 2]), [1, 1, 2]);
+        assert_eq!(workgroup_counts([u32::MAX, 1, 1], [2, 1, 1]), [
+            u32::MAX / 2 + 1,
+            1,
+            1
+        ]);
+    }
+
+    #[test]
+    fn zero_extents_need_no_workgroups() {
+        assert_eq!(workgroup_counts([0, 0, 0], [8, 8, 1]), [0, 0, 0]);
+        assert_eq!(workgroup_counts([64, 0, 1], [8, 8, 1]), [8, 0, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "local size must not be 0")]
+    fn zero_local_size_panics() {
+        workgroup_counts([64, 64, 1], [8, 0, 1]);
+    }
+}
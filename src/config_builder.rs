@@ -0,0 +1,262 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use vulkano::{
+    command_buffer::allocator::StandardCommandBufferAllocatorCreateInfo,
+    device::{Device, DeviceExtensions, Features},
+    format::Format,
+    image::ImageUsage,
+    instance::{
+        debug::{DebugUtilsMessageSeverity, DebugUtilsMessageType},
+        Instance, InstanceExtensions,
+    },
+    swapchain::{ColorSpace, SurfaceTransform},
+};
+
+use crate::{
+    ApplicationInfo, ContextKind, DeviceFilter, QueueFamilyOverrides, SharedMemoryAllocator,
+    VulkanoConfig, VulkanoWinitConfig,
+};
+
+/// Chainable construction of a [`VulkanoWinitConfig`], see [`VulkanoWinitConfig::builder`]. Each
+/// setter sets the config field of the same name (`validation` sets `enable_validation`), fields
+/// not set keep their defaults, e.g.
+/// `VulkanoWinitConfig::builder().validation(true).frames_in_flight(2).build()`. Present modes are
+/// set per window, see `Window::present_mode`.
+#[derive(Default)]
+pub struct VulkanoWinitConfigBuilder {
+    config: VulkanoWinitConfig,
+}
+
+impl VulkanoWinitConfigBuilder {
+    pub fn new() -> VulkanoWinitConfigBuilder {
+        VulkanoWinitConfigBuilder::default()
+    }
+
+    pub fn build(self) -> VulkanoWinitConfig {
+        self.config
+    }
+
+    /// See [`VulkanoWinitConfig::return_from_run`]
+    pub fn return_from_run(mut self, return_from_run: bool) -> Self {
+        self.config.return_from_run = return_from_run;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::vulkano_config`]
+    pub fn vulkano_config(mut self, vulkano_config: VulkanoConfig) -> Self {
+        self.config.vulkano_config = vulkano_config;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::is_gui_overlay`]
+    #[cfg(feature = "gui")]
+    pub fn is_gui_overlay(mut self, is_gui_overlay: bool) -> Self {
+        self.config.is_gui_overlay = is_gui_overlay;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::add_primary_window`]
+    pub fn add_primary_window(mut self, add_primary_window: bool) -> Self {
+        self.config.add_primary_window = add_primary_window;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::depth_format`]
+    pub fn depth_format(mut self, depth_format: Format) -> Self {
+        self.config.depth_format = Some(depth_format);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::device_filter`]
+    pub fn device_filter(mut self, device_filter: DeviceFilter) -> Self {
+        self.config.device_filter = Some(device_filter);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::min_image_count`]
+    pub fn min_image_count(mut self, min_image_count: u32) -> Self {
+        self.config.min_image_count = Some(min_image_count);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::frames_in_flight`]
+    pub fn frames_in_flight(mut self, frames_in_flight: usize) -> Self {
+        self.config.frames_in_flight = frames_in_flight;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::frame_rate_limit`]
+    pub fn frame_rate_limit(mut self, frame_rate_limit: f64) -> Self {
+        self.config.frame_rate_limit = Some(frame_rate_limit);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::enable_validation`]
+    pub fn validation(mut self, validation: bool) -> Self {
+        self.config.enable_validation = validation;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::debug_message_severity`]
+    pub fn debug_message_severity(
+        mut self,
+        debug_message_severity: DebugUtilsMessageSeverity,
+    ) -> Self {
+        self.config.debug_message_severity = debug_message_severity;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::debug_message_type`]
+    pub fn debug_message_type(mut self, debug_message_type: DebugUtilsMessageType) -> Self {
+        self.config.debug_message_type = debug_message_type;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::required_features`]
+    pub fn required_features(mut self, required_features: Features) -> Self {
+        self.config.required_features = required_features;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::required_extensions`]
+    pub fn required_extensions(mut self, required_extensions: DeviceExtensions) -> Self {
+        self.config.required_extensions = required_extensions;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::instance_extensions`]
+    pub fn instance_extensions(mut self, instance_extensions: InstanceExtensions) -> Self {
+        self.config.instance_extensions = instance_extensions;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::dynamic_rendering`]
+    pub fn dynamic_rendering(mut self, dynamic_rendering: bool) -> Self {
+        self.config.dynamic_rendering = dynamic_rendering;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::application_info`]
+    pub fn application_info(mut self, application_info: ApplicationInfo) -> Self {
+        self.config.application_info = Some(application_info);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::pipeline_cache_path`]
+    pub fn pipeline_cache_path(mut self, pipeline_cache_path: impl Into<PathBuf>) -> Self {
+        self.config.pipeline_cache_path = Some(pipeline_cache_path.into());
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::preferred_surface_formats`]
+    pub fn preferred_surface_formats(
+        mut self,
+        preferred_surface_formats: Vec<(Format, ColorSpace)>,
+    ) -> Self {
+        self.config.preferred_surface_formats = preferred_surface_formats;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::swapchain_image_usage`]
+    pub fn swapchain_image_usage(mut self, swapchain_image_usage: ImageUsage) -> Self {
+        self.config.swapchain_image_usage = swapchain_image_usage;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::memory_allocator_factory`]
+    pub fn memory_allocator_factory(
+        mut self,
+        memory_allocator_factory: impl Fn(Arc<Device>) -> Arc<SharedMemoryAllocator> + 'static,
+    ) -> Self {
+        self.config.memory_allocator_factory = Some(Box::new(memory_allocator_factory));
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::auto_present`]
+    pub fn auto_present(mut self, auto_present: bool) -> Self {
+        self.config.auto_present = auto_present;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::window_icon`]
+    pub fn window_icon(mut self, window_icon: winit::window::Icon) -> Self {
+        self.config.window_icon = Some(window_icon);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::graphics_queue_priorities`]
+    pub fn graphics_queue_priorities(mut self, graphics_queue_priorities: Vec<f32>) -> Self {
+        self.config.graphics_queue_priorities = Some(graphics_queue_priorities);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::max_frame_latency`]
+    pub fn max_frame_latency(mut self, max_frame_latency: u32) -> Self {
+        self.config.max_frame_latency = Some(max_frame_latency);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::display_timing`]
+    pub fn display_timing(mut self, display_timing: bool) -> Self {
+        self.config.display_timing = display_timing;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::command_buffer_allocator_create_info`]
+    pub fn command_buffer_allocator_create_info(
+        mut self,
+        command_buffer_allocator_create_info: StandardCommandBufferAllocatorCreateInfo,
+    ) -> Self {
+        self.config.command_buffer_allocator_create_info =
+            Some(command_buffer_allocator_create_info);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::context_kind`]
+    pub fn context_kind(mut self, context_kind: ContextKind) -> Self {
+        self.config.context_kind = context_kind;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::instance`]
+    pub fn instance(mut self, instance: Arc<Instance>) -> Self {
+        self.config.instance = Some(instance);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::full_screen_exclusive`]
+    pub fn full_screen_exclusive(mut self, full_screen_exclusive: bool) -> Self {
+        self.config.full_screen_exclusive = full_screen_exclusive;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::queue_family_overrides`]
+    pub fn queue_family_overrides(mut self, queue_family_overrides: QueueFamilyOverrides) -> Self {
+        self.config.queue_family_overrides = queue_family_overrides;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::acquire_timeout`]
+    pub fn acquire_timeout(mut self, acquire_timeout: Duration) -> Self {
+        self.config.acquire_timeout = Some(acquire_timeout);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::drive_schedule_on_present`]
+    pub fn drive_schedule_on_present(mut self, drive_schedule_on_present: bool) -> Self {
+        self.config.drive_schedule_on_present = drive_schedule_on_present;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::smooth_resize`]
+    pub fn smooth_resize(mut self, smooth_resize: bool) -> Self {
+        self.config.smooth_resize = smooth_resize;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::pre_transform`]
+    pub fn pre_transform(mut self, pre_transform: SurfaceTransform) -> Self {
+        self.config.pre_transform = Some(pre_transform);
+        self
+    }
+}
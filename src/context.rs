@@ -0,0 +1,1329 @@
+// Copyright (c) 2022 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+/*
+Adapted from vulkano_util's context, so that device & queue creation can be extended with features
+this crate needs (e.g. a separate compute queue) without waiting on upstream.
+ */
+use std::{error::Error, ffi::c_void, fmt, sync::Arc};
+
+use bevy::log::{info, warn};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
+        AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo, CopyBufferToImageInfo,
+        PrimaryAutoCommandBuffer, SecondaryAutoCommandBuffer,
+    },
+    descriptor_set::allocator::StandardDescriptorSetAllocator,
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType},
+        Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo, QueueFlags,
+    },
+    format::Format,
+    image::{
+        view::ImageView, ImageCreateFlags, ImageDimensions, ImageLayout, ImageUsage,
+        ImmutableImage, MipmapsCount, StorageImage,
+    },
+    instance::{
+        debug::{DebugUtilsMessenger, DebugUtilsMessengerCreateInfo},
+        Instance, InstanceCreateInfo, InstanceExtensions,
+    },
+    memory::{
+        allocator::{AllocationCreateInfo, MemoryAllocator, MemoryUsage, StandardMemoryAllocator},
+        MemoryHeapFlags,
+    },
+    sync::{self, FlushError, GpuFuture, Sharing},
+    DeviceSize, OomError, Version, VulkanLibrary, VulkanObject,
+};
+
+use crate::{
+    allocator_stats::{AllocatorBlocks, TrackingMemoryAllocator},
+    util::{generate_mipmaps, MipmapError},
+    DeviceImageView, MemoryAllocatorStats,
+};
+
+/// Memory allocator shared by the context, window renderers and user pipelines. This is a
+/// [`StandardMemoryAllocator`] unless a custom one was given, see
+/// [`VulkanoContext::with_memory_allocator`].
+pub type SharedMemoryAllocator = dyn MemoryAllocator + Send + Sync;
+
+// Shared allocators and what's recorded with them must be usable from worker threads, see
+// `BevyVulkanoWindows::frame_targets`
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+    assert_send_sync::<SharedMemoryAllocator>();
+    assert_send_sync::<StandardCommandBufferAllocator>();
+    assert_send_sync::<StandardDescriptorSetAllocator>();
+    assert_send_sync::<PrimaryAutoCommandBuffer>();
+    assert_send_sync::<SecondaryAutoCommandBuffer>();
+    assert_send_sync::<crate::WindowFrameTarget>();
+};
+
+/// Kind of work the context is created for, which determines the queues & extensions it needs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContextKind {
+    /// Rendering to windows: requires a graphics queue & `VK_KHR_swapchain`
+    #[default]
+    Graphics,
+    /// Compute & transfers only, e.g. batch processing on server GPUs without display outputs.
+    /// Only a compute queue is required and no surface or swapchain extensions are enabled, so
+    /// windows can't be created. [`VulkanoContext::graphics_queue`] is then a compute queue,
+    /// which may not support graphics.
+    ComputeOnly,
+}
+
+/// Queue families to create the context's queues from instead of selecting them automatically,
+/// e.g. to match the families another API imports shared resources on. `None` keeps the automatic
+/// choice. Physical devices without the pinned families, or whose pinned families lack the
+/// required queue flags, are skipped.
+///
+/// Pinning compute or transfer to a family already used for graphics (or transfer to the compute
+/// family) creates no separate queue for it, see [`QueueFamilyIndices`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct QueueFamilyOverrides {
+    /// Family of the graphics queues, which must support graphics (compute for
+    /// [`ContextKind::ComputeOnly`])
+    pub graphics: Option<u32>,
+    /// Family of the async compute queue, which must support compute
+    pub compute: Option<u32>,
+    /// Family of the transfer queue, which must support transfers
+    pub transfer: Option<u32>,
+}
+
+impl QueueFamilyOverrides {
+    /// Whether `physical_device` has the pinned families with the flags their queues need
+    fn is_supported_by(
+        &self,
+        physical_device: &PhysicalDevice,
+        main_queue_flags: QueueFlags,
+    ) -> bool {
+        let families = physical_device.queue_family_properties();
+        // Graphics & compute families support transfers even if they don't report it
+        [
+            (self.graphics, main_queue_flags),
+            (self.compute, QueueFlags::COMPUTE),
+            (
+                self.transfer,
+                QueueFlags::TRANSFER | QueueFlags::GRAPHICS | QueueFlags::COMPUTE,
+            ),
+        ]
+        .into_iter()
+        .all(|(index, flags)| match index {
+            Some(index) => families
+                .get(index as usize)
+                .is_some_and(|family| family.queue_flags.intersects(flags)),
+            None => true,
+        })
+    }
+}
+
+/// Queue families the context's queues were created from, see
+/// [`VulkanoContext::queue_family_indices`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueueFamilyIndices {
+    /// Family of [`VulkanoContext::graphics_queues`]
+    pub graphics: u32,
+    /// Family of [`VulkanoContext::compute_queue`], `None` if there's no separate compute queue
+    pub compute: Option<u32>,
+    /// Family of [`VulkanoContext::transfer_queue`], `None` if it's the graphics queue
+    pub transfer: Option<u32>,
+}
+
+/// A configuration struct to pass various creation options to create [`VulkanoContext`].
+///
+/// Instance extensions that are required for surface creation will be appended to the config when
+/// creating [`VulkanoContext`].
+pub struct VulkanoConfig {
+    pub instance_create_info: InstanceCreateInfo,
+
+    /// Pass the `DebugUtilsMessengerCreateInfo` to create the debug callback
+    /// for printing debug information at runtime.
+    pub debug_create_info: Option<DebugUtilsMessengerCreateInfo>,
+
+    /// Pass filter function for your physical device selection. See default for example.
+    pub device_filter_fn: Arc<dyn Fn(&PhysicalDevice) -> bool>,
+
+    /// Pass priority order function for your physical device selection. See default for example.
+    pub device_priority_fn: Arc<dyn Fn(&PhysicalDevice) -> u32>,
+
+    pub device_extensions: DeviceExtensions,
+
+    pub device_features: Features,
+
+    /// Device extensions enabled only if the selected device supports them. Unlike
+    /// `device_extensions`, these don't affect device selection.
+    pub optional_device_extensions: DeviceExtensions,
+
+    /// Device features enabled only if the selected device supports them. Unlike
+    /// `device_features`, these don't affect device selection.
+    pub optional_device_features: Features,
+
+    /// Priorities (between 0.0 and 1.0) of the queues created from the graphics queue family, one
+    /// per queue. Queues beyond the family's queue count are dropped, see
+    /// [`VulkanoContext::graphics_queues`]. Default is a single queue with priority 0.5.
+    pub graphics_queue_priorities: Vec<f32>,
+
+    /// Print your selected device name at start.
+    pub print_device_name: bool,
+
+    /// Pool sizes of the command buffer allocator shared through
+    /// [`VulkanoContext::command_buffer_allocator`].
+    pub command_buffer_allocator_create_info: StandardCommandBufferAllocatorCreateInfo,
+
+    /// Kind of work the context is for. With [`ContextKind::ComputeOnly`], swapchain related
+    /// extensions & features are removed from the requested ones.
+    pub context_kind: ContextKind,
+
+    /// Queue families to use instead of the automatically selected ones. Default pins none.
+    pub queue_family_overrides: QueueFamilyOverrides,
+}
+
+impl Default for VulkanoConfig {
+    #[inline]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn default() -> Self {
+        let device_extensions = DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::empty()
+        };
+        VulkanoConfig {
+            instance_create_info: InstanceCreateInfo {
+                application_version: Version::V1_3,
+                enabled_extensions: InstanceExtensions {
+                    #[cfg(target_os = "macos")]
+                    khr_portability_enumeration: true,
+                    ..InstanceExtensions::empty()
+                },
+                #[cfg(target_os = "macos")]
+                enumerate_portability: true,
+                ..Default::default()
+            },
+            debug_create_info: None,
+            // Required extensions are checked during device selection anyway, so that they can be
+            // changed (e.g. by `ContextKind::ComputeOnly`) without replacing the filter
+            device_filter_fn: Arc::new(|_| true),
+            device_priority_fn: Arc::new(|p| match p.properties().device_type {
+                PhysicalDeviceType::DiscreteGpu => 1,
+                PhysicalDeviceType::IntegratedGpu => 2,
+                PhysicalDeviceType::VirtualGpu => 3,
+                PhysicalDeviceType::Cpu => 4,
+                PhysicalDeviceType::Other => 5,
+                _ => 6,
+            }),
+            print_device_name: false,
+            command_buffer_allocator_create_info: Default::default(),
+            device_extensions,
+            device_features: Features::empty(),
+            optional_device_extensions: DeviceExtensions::empty(),
+            optional_device_features: Features::empty(),
+            graphics_queue_priorities: vec![0.5],
+            context_kind: ContextKind::Graphics,
+            queue_family_overrides: QueueFamilyOverrides::default(),
+        }
+    }
+}
+
+/// Budget & usage of a device memory heap, see [`VulkanoContext::memory_budget`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryHeapBudget {
+    /// Size of the heap in bytes.
+    pub size: DeviceSize,
+    /// Attributes of the heap, e.g. `DEVICE_LOCAL` for VRAM.
+    pub flags: MemoryHeapFlags,
+    /// Estimated bytes this process can allocate from the heap before allocations may fail or
+    /// degrade performance. This is the heap size without `VK_EXT_memory_budget`.
+    pub budget: DeviceSize,
+    /// Estimated bytes currently allocated from the heap by this process, or `None` without
+    /// `VK_EXT_memory_budget`.
+    pub usage: Option<DeviceSize>,
+}
+
+/// A utility struct to create, access and hold alive Vulkano device, instance and queues.
+///
+/// Vulkano context is used in the creation of your graphics or compute pipelines, images and
+/// in the creation of [`VulkanoWindowRenderer`](crate::VulkanoWindowRenderer) through
+/// [`BevyVulkanoWindows`](crate::BevyVulkanoWindows).
+pub struct VulkanoContext {
+    instance: Arc<Instance>,
+    _debug_utils_messenger: Option<DebugUtilsMessenger>,
+    device: Arc<Device>,
+    graphics_queues: Vec<Arc<Queue>>,
+    compute_queue: Option<Arc<Queue>>,
+    transfer_queue: Option<Arc<Queue>>,
+    memory_allocator: Arc<SharedMemoryAllocator>,
+    /// Allocations counted by the wrapper around `memory_allocator`
+    allocator_blocks: Arc<AllocatorBlocks>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+}
+
+impl Default for VulkanoContext {
+    #[inline]
+    fn default() -> Self {
+        VulkanoContext::new(VulkanoConfig::default())
+    }
+}
+
+impl VulkanoContext {
+    /// Creates a new [`VulkanoContext`] with a [`StandardMemoryAllocator`].
+    ///
+    /// # Panics
+    ///
+    /// - Panics where the underlying Vulkano struct creations fail
+    pub fn new(config: VulkanoConfig) -> Self {
+        Self::with_memory_allocator(config, |device| {
+            Arc::new(StandardMemoryAllocator::new_default(device))
+        })
+    }
+
+    /// Creates a new [`VulkanoContext`] whose memory allocator is created by
+    /// `memory_allocator_factory` once the device exists.
+    ///
+    /// # Panics
+    ///
+    /// - Panics where the underlying Vulkano struct creations fail
+    pub fn with_memory_allocator(
+        mut config: VulkanoConfig,
+        memory_allocator_factory: impl FnOnce(Arc<Device>) -> Arc<SharedMemoryAllocator>,
+    ) -> Self {
+        let library = match VulkanLibrary::new() {
+            Ok(x) => x,
+            #[cfg(target_os = "macos")]
+            Err(vulkano::library::LoadingError::LibraryLoadFailure(err)) => {
+                panic!("Failed to load Vulkan library: {}. Did you install vulkanSDK from https://vulkan.lunarg.com/sdk/home ?", err);
+            }
+            Err(err) => {
+                panic!("Failed to load Vulkan library: {}.", err);
+            }
+        };
+
+        if config.context_kind == ContextKind::Graphics {
+            // Append required extensions
+            config.instance_create_info.enabled_extensions =
+                vulkano_win::required_extensions(&library)
+                    .union(&config.instance_create_info.enabled_extensions);
+        }
+
+        // Enumerate portability subset devices too (e.g. MoltenVK on macOS). Vulkano enables
+        // `VK_KHR_portability_enumeration` and the `ENUMERATE_PORTABILITY` flag for this, and
+        // `VK_KHR_portability_subset` on devices that require it
+        if library.supported_extensions().khr_portability_enumeration {
+            config.instance_create_info.enumerate_portability = true;
+        }
+
+        // `VK_EXT_full_screen_exclusive` depends on these, only request it if they're supported
+        if config.optional_device_extensions.ext_full_screen_exclusive {
+            let supported_extensions = library.supported_extensions();
+            if supported_extensions.khr_get_surface_capabilities2
+                && supported_extensions.khr_get_physical_device_properties2
+            {
+                let enabled_extensions = &mut config.instance_create_info.enabled_extensions;
+                enabled_extensions.khr_get_surface_capabilities2 = true;
+                enabled_extensions.khr_get_physical_device_properties2 = true;
+            } else {
+                config.optional_device_extensions.ext_full_screen_exclusive = false;
+            }
+        }
+
+        // Skip requested layers which aren't installed instead of failing instance creation
+        let available_layers: Vec<String> = library
+            .layer_properties()
+            .map(|layers| layers.map(|layer| layer.name().to_owned()).collect())
+            .unwrap_or_default();
+        config.instance_create_info.enabled_layers.retain(|layer| {
+            let is_available = available_layers.contains(layer);
+            if !is_available {
+                warn!(
+                    "Vulkan layer {} is not installed, continuing without it",
+                    layer
+                );
+            }
+            is_available
+        });
+        // Debug utils is typically provided by the validation layer, so check it too
+        if config
+            .instance_create_info
+            .enabled_extensions
+            .ext_debug_utils
+            && !library
+                .supported_extensions_with_layers(
+                    config
+                        .instance_create_info
+                        .enabled_layers
+                        .iter()
+                        .map(String::as_str),
+                )
+                .is_ok_and(|extensions| extensions.ext_debug_utils)
+        {
+            warn!("VK_EXT_debug_utils is not supported, continuing without it");
+            config
+                .instance_create_info
+                .enabled_extensions
+                .ext_debug_utils = false;
+            config.debug_create_info = None;
+        }
+
+        // Fail with a clear message instead of a bare `ExtensionNotPresent`
+        let supported_extensions = library
+            .supported_extensions_with_layers(
+                config
+                    .instance_create_info
+                    .enabled_layers
+                    .iter()
+                    .map(String::as_str),
+            )
+            .expect("Failed to query supported instance extensions");
+        let missing_extensions = config
+            .instance_create_info
+            .enabled_extensions
+            .difference(&supported_extensions);
+        if missing_extensions != InstanceExtensions::empty() {
+            panic!(
+                "Requested instance extensions are not supported: {:?}",
+                missing_extensions
+            );
+        }
+
+        // Create instance
+        let instance = Instance::new(library, std::mem::take(&mut config.instance_create_info))
+            .expect("Failed to create instance");
+
+        // Create debug callback
+        let debug_utils_messenger = config
+            .debug_create_info
+            .take()
+            .map(|dbg_create_info| unsafe {
+                DebugUtilsMessenger::new(instance.clone(), dbg_create_info)
+                    .expect("Failed to create debug callback")
+            });
+
+        Self::with_instance(
+            instance,
+            debug_utils_messenger,
+            config,
+            memory_allocator_factory,
+        )
+    }
+
+    /// Creates a new [`VulkanoContext`] with a [`StandardMemoryAllocator`] from an instance
+    /// created elsewhere, e.g. by an OpenXR runtime requiring specific extensions.
+    /// `config.instance_create_info` is ignored. See
+    /// [`VulkanoContext::from_instance_with_memory_allocator`].
+    pub fn from_instance(
+        instance: Arc<Instance>,
+        config: VulkanoConfig,
+    ) -> Result<Self, ContextCreationError> {
+        Self::from_instance_with_memory_allocator(instance, config, |device| {
+            Arc::new(StandardMemoryAllocator::new_default(device))
+        })
+    }
+
+    /// Creates a new [`VulkanoContext`] from an instance created elsewhere, whose memory allocator
+    /// is created by `memory_allocator_factory`. `config.instance_create_info` is ignored, and
+    /// the debug callback is only created if the instance has `VK_EXT_debug_utils` enabled.
+    ///
+    /// Unless [`VulkanoConfig::context_kind`] is [`ContextKind::ComputeOnly`], the instance must
+    /// have `VK_KHR_surface` and a platform surface extension enabled so that windows can be
+    /// rendered to, otherwise [`ContextCreationError::MissingSurfaceExtensions`] is returned.
+    ///
+    /// # Panics
+    ///
+    /// - Panics where the underlying Vulkano struct creations fail
+    pub fn from_instance_with_memory_allocator(
+        instance: Arc<Instance>,
+        mut config: VulkanoConfig,
+        memory_allocator_factory: impl FnOnce(Arc<Device>) -> Arc<SharedMemoryAllocator>,
+    ) -> Result<Self, ContextCreationError> {
+        if config.context_kind == ContextKind::Graphics {
+            check_surface_extensions(&instance)?;
+        }
+        let debug_create_info = config.debug_create_info.take();
+        let debug_utils_messenger = match debug_create_info {
+            Some(dbg_create_info) if instance.enabled_extensions().ext_debug_utils => unsafe {
+                Some(
+                    DebugUtilsMessenger::new(instance.clone(), dbg_create_info)
+                        .expect("Failed to create debug callback"),
+                )
+            },
+            Some(_) => {
+                warn!("VK_EXT_debug_utils is not enabled on the instance, skipping debug callback");
+                None
+            }
+            None => None,
+        };
+        Ok(Self::with_instance(
+            instance,
+            debug_utils_messenger,
+            config,
+            memory_allocator_factory,
+        ))
+    }
+
+    /// Selects the device & creates the rest of the context on `instance`
+    fn with_instance(
+        instance: Arc<Instance>,
+        _debug_utils_messenger: Option<DebugUtilsMessenger>,
+        mut config: VulkanoConfig,
+        memory_allocator_factory: impl FnOnce(Arc<Device>) -> Arc<SharedMemoryAllocator>,
+    ) -> Self {
+        let main_queue_flags = match config.context_kind {
+            ContextKind::Graphics => QueueFlags::GRAPHICS,
+            ContextKind::ComputeOnly => QueueFlags::COMPUTE,
+        };
+        if config.context_kind == ContextKind::ComputeOnly {
+            remove_swapchain_extensions(&mut config);
+        }
+
+        // Get prioritized device, out of those supporting required extensions & features
+        let physical_device = instance
+            .enumerate_physical_devices()
+            .expect("Failed to enumerate physical devices")
+            .filter(|p| {
+                p.supported_extensions().contains(&config.device_extensions)
+                    && p.supported_features().contains(&config.device_features)
+                    && p.queue_family_properties()
+                        .iter()
+                        .any(|q| q.queue_flags.intersects(main_queue_flags))
+                    && config
+                        .queue_family_overrides
+                        .is_supported_by(p, main_queue_flags)
+            })
+            .filter(|p| (config.device_filter_fn)(p))
+            // Fully conformant devices are preferred over portability subset devices
+            .min_by_key(|p| {
+                (
+                    p.supported_extensions().khr_portability_subset,
+                    (config.device_priority_fn)(p),
+                )
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Failed to find a physical device supporting the required extensions {:?}, \
+                     features {:?} and queue families {:?}",
+                    config.device_extensions, config.device_features, config.queue_family_overrides
+                )
+            });
+        // Print used device
+        if config.print_device_name {
+            info!(
+                "Using device {}, type: {:?}",
+                physical_device.properties().device_name,
+                physical_device.properties().device_type,
+            );
+        }
+
+        // Create device. Memory budget is requested whenever available, as its query needs
+        // `vkGetPhysicalDeviceMemoryProperties2`
+        let mut optional_device_extensions = config.optional_device_extensions;
+        optional_device_extensions.ext_memory_budget |= (instance.api_version() >= Version::V1_1
+            && physical_device.api_version() >= Version::V1_1)
+            || instance
+                .enabled_extensions()
+                .khr_get_physical_device_properties2;
+        // External instances may lack the instance extensions it depends on
+        optional_device_extensions.ext_full_screen_exclusive &=
+            instance.enabled_extensions().khr_get_surface_capabilities2
+                && (instance.api_version() >= Version::V1_1
+                    || instance
+                        .enabled_extensions()
+                        .khr_get_physical_device_properties2);
+        // External memory depends on instance extensions which are core in Vulkan 1.1
+        if instance.api_version() < Version::V1_1 || physical_device.api_version() < Version::V1_1 {
+            optional_device_extensions.khr_external_memory = false;
+            optional_device_extensions.khr_external_memory_fd = false;
+            optional_device_extensions.khr_external_memory_win32 = false;
+        }
+        let device_extensions = config.device_extensions.union(
+            &physical_device
+                .supported_extensions()
+                .intersection(&optional_device_extensions),
+        );
+        let device_features = config.device_features.union(
+            &physical_device
+                .supported_features()
+                .intersection(&config.optional_device_features),
+        );
+        let (device, graphics_queues, compute_queue, transfer_queue) = Self::create_device(
+            physical_device,
+            device_extensions,
+            device_features,
+            &config.graphics_queue_priorities,
+            main_queue_flags,
+            config.queue_family_overrides,
+        );
+
+        let memory_allocator =
+            TrackingMemoryAllocator::new(memory_allocator_factory(device.clone()));
+        let allocator_blocks = memory_allocator.blocks();
+        let memory_allocator: Arc<SharedMemoryAllocator> = Arc::new(memory_allocator);
+        let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+            device.clone(),
+            config.command_buffer_allocator_create_info.clone(),
+        ));
+        let descriptor_set_allocator =
+            Arc::new(StandardDescriptorSetAllocator::new(device.clone()));
+
+        Self {
+            instance,
+            _debug_utils_messenger,
+            device,
+            graphics_queues,
+            compute_queue,
+            transfer_queue,
+            memory_allocator,
+            allocator_blocks,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        }
+    }
+
+    /// Creates vulkano device with required queue families and required extensions. Creates
+    /// separate queues for compute and transfer if the device has capable queue families other
+    /// than the graphics one. The main ("graphics") queue family is the first supporting
+    /// `main_queue_flags`. Families pinned by `overrides` are used as is, they've been validated
+    /// during device selection.
+    fn create_device(
+        physical_device: Arc<PhysicalDevice>,
+        device_extensions: DeviceExtensions,
+        features: Features,
+        graphics_queue_priorities: &[f32],
+        main_queue_flags: QueueFlags,
+        overrides: QueueFamilyOverrides,
+    ) -> (
+        Arc<Device>,
+        Vec<Arc<Queue>>,
+        Option<Arc<Queue>>,
+        Option<Arc<Queue>>,
+    ) {
+        let queue_family_graphics = physical_device
+            .queue_family_properties()
+            .iter()
+            .enumerate()
+            .map(|(i, q)| (i as u32, q))
+            .find(|(i, q)| match overrides.graphics {
+                Some(index) => *i == index,
+                None => q.queue_flags.intersects(main_queue_flags),
+            })
+            .map(|(i, q)| (i, q.queue_count))
+            .unwrap_or_else(|| {
+                panic!(
+                    "Could not find a queue that supports {:?}",
+                    main_queue_flags
+                )
+            });
+        let (queue_family_graphics, graphics_queue_count) = queue_family_graphics;
+        let mut graphics_priorities: Vec<f32> = graphics_queue_priorities
+            .iter()
+            .take(graphics_queue_count as usize)
+            .map(|priority| priority.clamp(0.0, 1.0))
+            .collect();
+        if graphics_priorities.is_empty() {
+            graphics_priorities.push(0.5);
+        }
+        // Try finding a separate queue for compute, preferring a dedicated (async) compute family
+        let queue_family_compute = match overrides.compute {
+            Some(index) => (index != queue_family_graphics).then_some(index),
+            None => physical_device
+                .queue_family_properties()
+                .iter()
+                .enumerate()
+                .map(|(i, q)| (i as u32, q))
+                .filter(|(i, q)| {
+                    q.queue_flags.intersects(QueueFlags::COMPUTE) && *i != queue_family_graphics
+                })
+                .min_by_key(|(_i, q)| q.queue_flags.intersects(QueueFlags::GRAPHICS))
+                .map(|(i, _)| i),
+        };
+
+        // Try finding a separate queue for transfers, preferring a dedicated (DMA) transfer family
+        let queue_family_transfer = match overrides.transfer {
+            Some(index) => (index != queue_family_graphics && Some(index) != queue_family_compute)
+                .then_some(index),
+            None => physical_device
+                .queue_family_properties()
+                .iter()
+                .enumerate()
+                .map(|(i, q)| (i as u32, q))
+                .filter(|(i, q)| {
+                    q.queue_flags.intersects(QueueFlags::TRANSFER)
+                        && *i != queue_family_graphics
+                        && Some(*i) != queue_family_compute
+                })
+                .min_by_key(|(_i, q)| {
+                    q.queue_flags
+                        .intersects(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)
+                })
+                .map(|(i, _)| i),
+        };
+
+        let queue_create_infos = [
+            Some(queue_family_graphics),
+            queue_family_compute,
+            queue_family_transfer,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|queue_family_index| QueueCreateInfo {
+            queue_family_index,
+            queues: if queue_family_index == queue_family_graphics {
+                graphics_priorities.clone()
+            } else {
+                vec![0.5]
+            },
+            ..Default::default()
+        })
+        .collect();
+
+        let (device, mut queues) = {
+            Device::new(physical_device, DeviceCreateInfo {
+                enabled_extensions: device_extensions,
+                enabled_features: features,
+                queue_create_infos,
+                ..Default::default()
+            })
+            .expect("Failed to create device")
+        };
+        let gfx_queues: Vec<_> = queues.by_ref().take(graphics_priorities.len()).collect();
+        let compute_queue = queue_family_compute.and_then(|_| queues.next());
+        let transfer_queue = queue_family_transfer.and_then(|_| queues.next());
+        (device, gfx_queues, compute_queue, transfer_queue)
+    }
+
+    /// Returns the name of the device.
+    #[inline]
+    pub fn device_name(&self) -> &str {
+        &self.device.physical_device().properties().device_name
+    }
+
+    /// Returns the type of the device.
+    #[inline]
+    pub fn device_type(&self) -> PhysicalDeviceType {
+        self.device.physical_device().properties().device_type
+    }
+
+    /// Returns the maximum memory allocation of the device.
+    #[inline]
+    pub fn max_memory(&self) -> u32 {
+        self.device
+            .physical_device()
+            .properties()
+            .max_memory_allocation_count
+    }
+
+    /// Returns the instance.
+    #[inline]
+    pub fn instance(&self) -> &Arc<Instance> {
+        &self.instance
+    }
+
+    /// Returns the device.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the budget & usage of each memory heap of the device, queried on each call. Usage is
+    /// only known if the device supports `VK_EXT_memory_budget`, which is enabled automatically.
+    /// Otherwise the budget is the heap size.
+    pub fn memory_budget(&self) -> Vec<MemoryHeapBudget> {
+        let physical_device = self.device.physical_device();
+        let budget = self
+            .device
+            .enabled_extensions()
+            .ext_memory_budget
+            .then(|| query_memory_budget(physical_device));
+        physical_device
+            .memory_properties()
+            .memory_heaps
+            .iter()
+            .enumerate()
+            .map(|(i, heap)| MemoryHeapBudget {
+                size: heap.size,
+                flags: heap.flags,
+                budget: budget.map_or(heap.size, |budget| budget.heap_budget[i]),
+                usage: budget.map(|budget| budget.heap_usage[i]),
+            })
+            .collect()
+    }
+
+    /// Returns the blocks of [`VulkanoContext::memory_allocator`] per memory type, along with the
+    /// device's live `VkDeviceMemory` count, e.g. to watch for memory growing over time. See
+    /// [`MemoryAllocatorStats`].
+    pub fn memory_allocator_stats(&self) -> MemoryAllocatorStats {
+        self.allocator_blocks.stats(&self.device)
+    }
+
+    /// Blocks until all work submitted to the context's queues has finished, like
+    /// `vkDeviceWaitIdle`. Each queue is locked while waited on, so this is safe while other
+    /// threads submit, though their later submissions aren't waited for. Call it before dropping
+    /// resources that may still be in use, e.g. when hot-swapping pipelines.
+    pub fn wait_idle(&self) -> Result<(), OomError> {
+        let queues = self
+            .graphics_queues
+            .iter()
+            .chain(&self.compute_queue)
+            .chain(&self.transfer_queue);
+        for queue in queues {
+            queue.with(|mut queue| queue.wait_idle())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the graphics queue. This is the first of [`VulkanoContext::graphics_queues`].
+    #[inline]
+    pub fn graphics_queue(&self) -> &Arc<Queue> {
+        &self.graphics_queues[0]
+    }
+
+    /// Returns all queues created from the graphics queue family, in the order of
+    /// [`VulkanoConfig::graphics_queue_priorities`]. Contains a single queue if the family only
+    /// supports one.
+    #[inline]
+    pub fn graphics_queues(&self) -> &[Arc<Queue>] {
+        &self.graphics_queues
+    }
+
+    /// Returns the async compute queue from a queue family separate from graphics, or `None` if
+    /// the device has no such family. In that case, use [`VulkanoContext::graphics_queue`] for
+    /// compute work instead.
+    #[inline]
+    pub fn compute_queue(&self) -> Option<Arc<Queue>> {
+        self.compute_queue.clone()
+    }
+
+    /// Returns the transfer queue from a queue family separate from graphics, or the graphics
+    /// queue if the device has no such family.
+    #[inline]
+    pub fn transfer_queue(&self) -> Arc<Queue> {
+        self.transfer_queue
+            .clone()
+            .unwrap_or_else(|| self.graphics_queue().clone())
+    }
+
+    /// Returns the queue families the context's queues were created from, e.g. for sharing
+    /// resources with another API that must use matching families. See
+    /// [`VulkanoConfig::queue_family_overrides`] to pin them.
+    pub fn queue_family_indices(&self) -> QueueFamilyIndices {
+        QueueFamilyIndices {
+            graphics: self.graphics_queue().queue_family_index(),
+            compute: self
+                .compute_queue
+                .as_ref()
+                .map(|queue| queue.queue_family_index()),
+            transfer: self
+                .transfer_queue
+                .as_ref()
+                .map(|queue| queue.queue_family_index()),
+        }
+    }
+
+    /// Returns the memory allocator.
+    #[inline]
+    pub fn memory_allocator(&self) -> &Arc<SharedMemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    /// Returns the command buffer allocator shared by window renderers. Use it for your own command
+    /// buffers too, so that the app uses one set of pools.
+    #[inline]
+    pub fn command_buffer_allocator(&self) -> &Arc<StandardCommandBufferAllocator> {
+        &self.command_buffer_allocator
+    }
+
+    /// Returns the descriptor set allocator to share between your pipelines.
+    #[inline]
+    pub fn descriptor_set_allocator(&self) -> &Arc<StandardDescriptorSetAllocator> {
+        &self.descriptor_set_allocator
+    }
+
+    /// Uploads `data` into a new 2D device local image through a staging buffer. The copy is
+    /// recorded and submitted on [`VulkanoContext::transfer_queue`], so it won't stall graphics.
+    /// Join the returned future with your render future before using the image.
+    ///
+    /// The image is shared concurrently between the graphics, compute and transfer queue families,
+    /// so no queue family ownership transfer is needed when using it on other queues.
+    /// `TRANSFER_DST` is added to `usage`.
+    ///
+    /// Returns an error if the submission fails, e.g. `FlushError::DeviceLost`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `data` doesn't fit `dimensions` and `format`, or if the image can't be created
+    pub fn upload_image_async(
+        &self,
+        data: &[u8],
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+    ) -> Result<(DeviceImageView, Box<dyn GpuFuture>), FlushError> {
+        assert_image_data_size(data, dimensions, format);
+        let transfer_queue = self.transfer_queue();
+        let staging_buffer = Buffer::from_iter(
+            &*self.memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )
+        .expect("Failed to create staging buffer");
+        let mut queue_family_indices = vec![
+            self.graphics_queue().queue_family_index(),
+            transfer_queue.queue_family_index(),
+        ];
+        if let Some(compute_queue) = &self.compute_queue {
+            queue_family_indices.push(compute_queue.queue_family_index());
+        }
+        queue_family_indices.dedup();
+        let image = StorageImage::with_usage(
+            &*self.memory_allocator,
+            ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
+            format,
+            usage | ImageUsage::TRANSFER_DST,
+            ImageCreateFlags::empty(),
+            queue_family_indices,
+        )
+        .expect("Failed to create image");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            transfer_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+                staging_buffer,
+                image.clone(),
+            ))
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        // Signal a semaphore so that the future can be waited on from another queue
+        let future = sync::now(self.device.clone())
+            .then_execute(transfer_queue, command_buffer)
+            .unwrap()
+            .then_signal_semaphore_and_flush()?
+            .boxed();
+        Ok((ImageView::new_default(image).unwrap(), future))
+    }
+
+    /// Uploads `data` into a new 2D sampled image with a full mip chain, e.g. for textures drawn
+    /// minified. Like [`VulkanoContext::upload_image_async`], level 0 is copied from a staging
+    /// buffer on [`VulkanoContext::transfer_queue`] and the image is shared concurrently between
+    /// the queue families. The other levels are then generated with linear blits on
+    /// [`VulkanoContext::graphics_queue`], as blits need a graphics queue, see
+    /// [`generate_mipmaps`]. The image ends up in the shader read only layout. Join the returned
+    /// future with your render future before sampling the image.
+    ///
+    /// Returns an error if the format doesn't support linear blits, in which case nothing is
+    /// submitted, or if the submission fails.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `data` doesn't fit `dimensions` and `format`, or if the image can't be created
+    #[allow(clippy::result_large_err)]
+    pub fn upload_mipmapped_image_async(
+        &self,
+        data: &[u8],
+        dimensions: [u32; 2],
+        format: Format,
+    ) -> Result<(Arc<ImageView<ImmutableImage>>, Box<dyn GpuFuture>), ImageUploadError> {
+        assert_image_data_size(data, dimensions, format);
+        let transfer_queue = self.transfer_queue();
+        let graphics_queue = self.graphics_queue();
+        let mut queue_family_indices = vec![
+            graphics_queue.queue_family_index(),
+            transfer_queue.queue_family_index(),
+        ];
+        if let Some(compute_queue) = &self.compute_queue {
+            queue_family_indices.push(compute_queue.queue_family_index());
+        }
+        queue_family_indices.dedup();
+        let (image, initializer) = ImmutableImage::uninitialized(
+            &*self.memory_allocator,
+            ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
+            format,
+            MipmapsCount::Log2,
+            ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+            ImageCreateFlags::empty(),
+            ImageLayout::ShaderReadOnlyOptimal,
+            queue_family_indices,
+        )
+        .expect("Failed to create image");
+
+        // Recorded first, so that unsupported formats fail before anything is submitted
+        let mut mipmaps_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            graphics_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        generate_mipmaps(&mut mipmaps_builder, image.clone())?;
+        let mipmaps_command_buffer = mipmaps_builder.build().unwrap();
+
+        let staging_buffer = Buffer::from_iter(
+            &*self.memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )
+        .expect("Failed to create staging buffer");
+        let mut upload_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            transfer_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        upload_builder
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+                staging_buffer,
+                initializer,
+            ))
+            .unwrap();
+        let upload_command_buffer = upload_builder.build().unwrap();
+        let future = sync::now(self.device.clone())
+            .then_execute(transfer_queue, upload_command_buffer)
+            .unwrap()
+            .then_signal_semaphore_and_flush()?
+            .then_execute(graphics_queue.clone(), mipmaps_command_buffer)
+            .unwrap()
+            .then_signal_semaphore_and_flush()?
+            .boxed();
+        Ok((ImageView::new_default(image).unwrap(), future))
+    }
+
+    /// Uploads `data` into a new device local buffer through a staging buffer, e.g. for vertex &
+    /// index buffers. Like [`VulkanoContext::upload_image_async`], the copy is submitted on
+    /// [`VulkanoContext::transfer_queue`] and the buffer is shared concurrently between the queue
+    /// families. Join the returned future with your render future before using the buffer.
+    /// `TRANSFER_DST` is added to `usage`.
+    ///
+    /// Returns an error if the submission fails, e.g. `FlushError::DeviceLost`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `data` is empty, or if the buffers can't be created
+    pub fn upload_buffer_async<T, I>(
+        &self,
+        data: I,
+        usage: BufferUsage,
+    ) -> Result<(Subbuffer<[T]>, Box<dyn GpuFuture>), FlushError>
+    where
+        T: BufferContents,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let transfer_queue = self.transfer_queue();
+        let staging_buffer = Buffer::from_iter(
+            &*self.memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            data,
+        )
+        .expect("Failed to create staging buffer");
+        let mut queue_family_indices: Vec<u32> = [
+            Some(self.graphics_queue().queue_family_index()),
+            Some(transfer_queue.queue_family_index()),
+            self.compute_queue
+                .as_ref()
+                .map(|queue| queue.queue_family_index()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        queue_family_indices.sort_unstable();
+        queue_family_indices.dedup();
+        let buffer = Buffer::new_slice::<T>(
+            &*self.memory_allocator,
+            BufferCreateInfo {
+                usage: usage | BufferUsage::TRANSFER_DST,
+                sharing: if queue_family_indices.len() >= 2 {
+                    Sharing::Concurrent(queue_family_indices.into())
+                } else {
+                    Sharing::Exclusive
+                },
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::DeviceOnly,
+                ..Default::default()
+            },
+            staging_buffer.len(),
+        )
+        .expect("Failed to create buffer");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            transfer_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .copy_buffer(CopyBufferInfo::buffers(staging_buffer, buffer.clone()))
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        // Signal a semaphore so that the future can be waited on from another queue
+        let future = sync::now(self.device.clone())
+            .then_execute(transfer_queue, command_buffer)
+            .unwrap()
+            .then_signal_semaphore_and_flush()?
+            .boxed();
+        Ok((buffer, future))
+    }
+
+    /// Creates an image whose memory can be exported to other APIs, shared between the graphics
+    /// and compute queue families. See [`crate::ExternalImage::new_exportable`].
+    #[cfg(feature = "external_memory")]
+    pub fn create_exportable_image(
+        &self,
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+    ) -> Result<Arc<crate::ExternalImage>, crate::ExternalMemoryError> {
+        crate::ExternalImage::new_exportable(
+            self.device.clone(),
+            dimensions,
+            format,
+            usage,
+            &self.shared_queue_family_indices(),
+        )
+    }
+
+    /// Creates an image bound to memory exported by another API, shared between the graphics and
+    /// compute queue families. See [`crate::ExternalImage::import`].
+    ///
+    /// # Safety
+    ///
+    /// See [`crate::ExternalImage::import`].
+    #[cfg(feature = "external_memory")]
+    pub unsafe fn import_image(
+        &self,
+        handle: crate::ExternalMemoryHandle,
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+        memory_type_index: u32,
+    ) -> Result<Arc<crate::ExternalImage>, crate::ExternalMemoryError> {
+        crate::ExternalImage::import(
+            self.device.clone(),
+            handle,
+            dimensions,
+            format,
+            usage,
+            &self.shared_queue_family_indices(),
+            memory_type_index,
+        )
+    }
+
+    /// Whether external memory is enabled on the device, see [`crate::external_memory_enabled`].
+    #[cfg(feature = "external_memory")]
+    pub fn external_memory_enabled(&self) -> bool {
+        crate::external_memory_enabled(&self.device)
+    }
+
+    #[cfg(feature = "external_memory")]
+    fn shared_queue_family_indices(&self) -> Vec<u32> {
+        let indices = self.queue_family_indices();
+        [Some(indices.graphics), indices.compute]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// Checks that `instance` has the extensions needed to create surfaces for winit windows enabled
+fn check_surface_extensions(instance: &Instance) -> Result<(), ContextCreationError> {
+    let enabled_extensions = instance.enabled_extensions();
+    let platform_surface_extensions = vulkano_win::required_extensions(instance.library())
+        .intersection(&InstanceExtensions {
+            khr_xlib_surface: true,
+            khr_xcb_surface: true,
+            khr_wayland_surface: true,
+            khr_android_surface: true,
+            khr_win32_surface: true,
+            mvk_ios_surface: true,
+            mvk_macos_surface: true,
+            ..InstanceExtensions::empty()
+        });
+    let has_platform_surface = enabled_extensions.intersects(&platform_surface_extensions);
+    if enabled_extensions.khr_surface && has_platform_surface {
+        return Ok(());
+    }
+    let mut missing_extensions = InstanceExtensions {
+        khr_surface: !enabled_extensions.khr_surface,
+        ..InstanceExtensions::empty()
+    };
+    if !has_platform_surface {
+        missing_extensions = missing_extensions.union(&platform_surface_extensions);
+    }
+    Err(ContextCreationError::MissingSurfaceExtensions(
+        missing_extensions,
+    ))
+}
+
+/// Error returned when creating a [`VulkanoContext`] from an existing instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextCreationError {
+    /// The instance lacks extensions needed to render to windows. Contains the missing surface
+    /// extension, or all platform surface extensions supported if none is enabled.
+    MissingSurfaceExtensions(InstanceExtensions),
+}
+
+impl fmt::Display for ContextCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContextCreationError::MissingSurfaceExtensions(extensions) => write!(
+                f,
+                "the instance is missing extensions required for window surfaces: {:?}",
+                extensions
+            ),
+        }
+    }
+}
+
+impl Error for ContextCreationError {}
+
+/// Error returned by [`VulkanoContext::upload_mipmapped_image_async`]
+#[derive(Debug)]
+pub enum ImageUploadError {
+    /// The mip levels can't be generated, e.g. as the format doesn't support linear blits.
+    Mipmap(MipmapError),
+    Flush(FlushError),
+}
+
+impl fmt::Display for ImageUploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageUploadError::Mipmap(e) => write!(f, "failed to generate mipmaps: {}", e),
+            ImageUploadError::Flush(e) => write!(f, "failed to submit image upload: {}", e),
+        }
+    }
+}
+
+impl Error for ImageUploadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImageUploadError::Mipmap(e) => Some(e),
+            ImageUploadError::Flush(e) => Some(e),
+        }
+    }
+}
+
+impl From<MipmapError> for ImageUploadError {
+    fn from(e: MipmapError) -> Self {
+        ImageUploadError::Mipmap(e)
+    }
+}
+
+impl From<FlushError> for ImageUploadError {
+    fn from(e: FlushError) -> Self {
+        ImageUploadError::Flush(e)
+    }
+}
+
+/// Panics if `data` isn't the tightly packed size of a 2D image of `dimensions` and `format`.
+/// Formats without a single block size, e.g. multi-planar ones, aren't checked here.
+fn assert_image_data_size(data: &[u8], dimensions: [u32; 2], format: Format) {
+    let Some(block_size) = format.block_size() else {
+        return;
+    };
+    let [block_width, block_height, _] = format.block_extent();
+    let blocks = dimensions[0].div_ceil(block_width) as DeviceSize
+        * dimensions[1].div_ceil(block_height) as DeviceSize;
+    assert_eq!(
+        data.len() as DeviceSize,
+        blocks * block_size,
+        "image data doesn't match {}x{} {:?}",
+        dimensions[0],
+        dimensions[1],
+        format
+    );
+}
+
+/// Removes `VK_KHR_swapchain` and the extensions & features depending on it from `config`, for
+/// [`ContextKind::ComputeOnly`]
+fn remove_swapchain_extensions(config: &mut VulkanoConfig) {
+    for extensions in [
+        &mut config.device_extensions,
+        &mut config.optional_device_extensions,
+    ] {
+        extensions.khr_swapchain = false;
+        extensions.khr_present_id = false;
+        extensions.khr_present_wait = false;
+        extensions.google_display_timing = false;
+        extensions.khr_incremental_present = false;
+        extensions.ext_full_screen_exclusive = false;
+    }
+    for features in [
+        &mut config.device_features,
+        &mut config.optional_device_features,
+    ] {
+        features.present_id = false;
+        features.present_wait = false;
+    }
+}
+
+/// Queries `VkPhysicalDeviceMemoryBudgetPropertiesEXT`, which vulkano doesn't expose. The device
+/// must have been created with `ext_memory_budget`.
+fn query_memory_budget(
+    physical_device: &PhysicalDevice,
+) -> ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT {
+    let mut budget = ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut properties = ash::vk::PhysicalDeviceMemoryProperties2 {
+        p_next: &mut budget as *mut _ as *mut c_void,
+        ..Default::default()
+    };
+    let instance = physical_device.instance();
+    let fns = instance.fns();
+    // Safety: `budget` outlives the call, and the function is available as the extension's
+    // requirements were met at device creation
+    unsafe {
+        if instance.api_version() >= Version::V1_1 {
+            (fns.v1_1.get_physical_device_memory_properties2)(
+                physical_device.handle(),
+                &mut properties,
+            );
+        } else {
+            (fns.khr_get_physical_device_properties2
+                .get_physical_device_memory_properties2_khr)(
+                physical_device.handle(),
+                &mut properties,
+            );
+        }
+    }
+    budget
+}
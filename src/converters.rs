@@ -6,7 +6,7 @@ use bevy::{
         ButtonState,
     },
     math::Vec2,
-    window::CursorIcon,
+    window::{CursorGrabMode, CursorIcon, PresentMode},
 };
 
 pub fn convert_keyboard_input(keyboard_input: &winit::event::KeyboardInput) -> KeyboardInput {
@@ -268,3 +268,26 @@ pub fn convert_cursor_icon(cursor_icon: CursorIcon) -> winit::window::CursorIcon
         CursorIcon::RowResize => winit::window::CursorIcon::RowResize,
     }
 }
+
+/// Maps bevy's present mode to the Vulkan present mode requested first. The `Auto` modes map to the
+/// head of bevy's preference lists, the renderer falls back along the rest if the surface doesn't
+/// support them (see `select_present_mode`).
+pub fn convert_present_mode(present_mode: PresentMode) -> vulkano::swapchain::PresentMode {
+    match present_mode {
+        PresentMode::Fifo => vulkano::swapchain::PresentMode::Fifo,
+        PresentMode::Immediate => vulkano::swapchain::PresentMode::Immediate,
+        PresentMode::Mailbox => vulkano::swapchain::PresentMode::Mailbox,
+        // Immediate, then Mailbox, then Fifo
+        PresentMode::AutoNoVsync => vulkano::swapchain::PresentMode::Immediate,
+        // FifoRelaxed, then Fifo
+        PresentMode::AutoVsync => vulkano::swapchain::PresentMode::FifoRelaxed,
+    }
+}
+
+pub fn convert_cursor_grab_mode(grab_mode: CursorGrabMode) -> winit::window::CursorGrabMode {
+    match grab_mode {
+        CursorGrabMode::None => winit::window::CursorGrabMode::None,
+        CursorGrabMode::Confined => winit::window::CursorGrabMode::Confined,
+        CursorGrabMode::Locked => winit::window::CursorGrabMode::Locked,
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::utils::HashMap;
+use vulkano::{
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorSetLayout,
+        DescriptorSetCreationError, PersistentDescriptorSet, WriteDescriptorSet,
+        WriteDescriptorSetElements,
+    },
+    DeviceSize,
+};
+
+/// Reuses descriptor sets of static resources instead of rebuilding identical sets each frame.
+/// Access it through
+/// [`BevyVulkanoContext::get_descriptor_set`](crate::BevyVulkanoContext::get_descriptor_set).
+///
+/// Sets are keyed by their layout and written resources, compared by identity: writing a
+/// different image view or buffer creates a new set, while changing the contents of the same
+/// buffer or image doesn't. Cached sets keep their resources alive until
+/// [`DescriptorSetCache::clear`], so sets written with per-frame resources should bypass the
+/// cache with [`DescriptorSetCache::create`].
+pub struct DescriptorSetCache {
+    allocator: Arc<StandardDescriptorSetAllocator>,
+    sets: Mutex<HashMap<DescriptorSetKey, Arc<PersistentDescriptorSet>>>,
+}
+
+impl DescriptorSetCache {
+    pub fn new(allocator: Arc<StandardDescriptorSetAllocator>) -> DescriptorSetCache {
+        DescriptorSetCache {
+            allocator,
+            sets: Mutex::new(HashMap::default()),
+        }
+    }
+
+    /// Returns the set of `layout` written with `descriptor_writes`, creating it on first use.
+    pub fn get(
+        &self,
+        layout: Arc<DescriptorSetLayout>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+    ) -> Result<Arc<PersistentDescriptorSet>, DescriptorSetCreationError> {
+        let descriptor_writes = descriptor_writes.into_iter().collect::<Vec<_>>();
+        let key = DescriptorSetKey::new(&layout, &descriptor_writes);
+        let mut sets = self.sets.lock().unwrap();
+        if let Some(set) = sets.get(&key) {
+            return Ok(set.clone());
+        }
+        let set = PersistentDescriptorSet::new(&*self.allocator, layout, descriptor_writes)?;
+        sets.insert(key, set.clone());
+        Ok(set)
+    }
+
+    /// Creates a set without caching it, for sets whose resources change every frame.
+    pub fn create(
+        &self,
+        layout: Arc<DescriptorSetLayout>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+    ) -> Result<Arc<PersistentDescriptorSet>, DescriptorSetCreationError> {
+        PersistentDescriptorSet::new(&*self.allocator, layout, descriptor_writes)
+    }
+
+    /// Drops all cached sets, releasing the resources they were written with.
+    pub fn clear(&self) {
+        self.sets.lock().unwrap().clear();
+    }
+
+    /// Number of sets cached
+    pub fn len(&self) -> usize {
+        self.sets.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Identity of a descriptor set's layout & writes. Objects are compared by address, which stays
+/// unique as long as the cached set keeps them alive.
+#[derive(PartialEq, Eq, Hash)]
+struct DescriptorSetKey {
+    layout: usize,
+    writes: Vec<(u32, u32, Vec<ElementKey>)>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ElementKey {
+    None(u32),
+    /// Buffer, subbuffer offset & size, and the range written
+    Buffer(usize, DeviceSize, DeviceSize, DeviceSize, DeviceSize),
+    BufferView(usize),
+    ImageView(usize),
+    ImageViewSampler(usize, usize),
+    Sampler(usize),
+}
+
+impl DescriptorSetKey {
+    fn new(
+        layout: &Arc<DescriptorSetLayout>,
+        descriptor_writes: &[WriteDescriptorSet],
+    ) -> DescriptorSetKey {
+        fn address<T: ?Sized>(object: &Arc<T>) -> usize {
+            Arc::as_ptr(object) as *const () as usize
+        }
+        let writes = descriptor_writes
+            .iter()
+            .map(|write| {
+                let elements = match write.elements() {
+                    WriteDescriptorSetElements::None(count) => vec![ElementKey::None(*count)],
+                    WriteDescriptorSetElements::Buffer(buffers) => buffers
+                        .iter()
+                        .map(|(buffer, range)| {
+                            ElementKey::Buffer(
+                                address(buffer.buffer()),
+                                buffer.offset(),
+                                buffer.size(),
+                                range.start,
+                                range.end,
+                            )
+                        })
+                        .collect(),
+                    WriteDescriptorSetElements::BufferView(views) => views
+                        .iter()
+                        .map(|view| ElementKey::BufferView(address(view)))
+                        .collect(),
+                    WriteDescriptorSetElements::ImageView(views) => views
+                        .iter()
+                        .map(|view| ElementKey::ImageView(address(view)))
+                        .collect(),
+                    WriteDescriptorSetElements::ImageViewSampler(views) => views
+                        .iter()
+                        .map(|(view, sampler)| {
+                            ElementKey::ImageViewSampler(address(view), address(sampler))
+                        })
+                        .collect(),
+                    WriteDescriptorSetElements::Sampler(samplers) => samplers
+                        .iter()
+                        .map(|sampler| ElementKey::Sampler(address(sampler)))
+                        .collect(),
+                };
+                (write.binding(), write.first_array_element(), elements)
+            })
+            .collect();
+        DescriptorSetKey {
+            layout: address(layout),
+            writes,
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use bevy::{
+    app::{App, Plugin},
+    prelude::*,
+    utils::HashMap,
+    window::{WindowClosed, WindowCreated},
+};
+use egui_winit_vulkano::{egui, Gui};
+use vulkano::sync::GpuFuture;
+
+use crate::{BevyVulkanoWindows, VulkanoRendererError, VulkanoWindowRenderer};
+
+/// Plugin integrating [egui](https://github.com/emilk/egui) with bevy_vulkano windows. Each
+/// window gets its own [`Gui`] (see [`BevyVulkanoWindows::get_window_renderer_mut`]), which
+/// receives the window's winit events (including scale factor changes) before bevy does.
+///
+/// The plugin begins a gui frame for each window in `PreUpdate` and keeps [`EguiContext`] up to
+/// date. Build your ui in any system, then finish your frame with [`present_with_gui`] so that
+/// gui is drawn over your content right before presenting.
+#[derive(Default)]
+pub struct EguiVulkanoPlugin;
+
+impl Plugin for EguiVulkanoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EguiContext>().add_systems(
+            (update_egui_context_system, begin_egui_frame_system)
+                .chain()
+                .in_base_set(CoreSet::PreUpdate),
+        );
+    }
+}
+
+/// Egui contexts per window entity. Contexts are cheap to clone and can be used from any system
+/// to build ui for the window.
+#[derive(Default, Resource)]
+pub struct EguiContext {
+    contexts: HashMap<Entity, egui::Context>,
+}
+
+impl EguiContext {
+    pub fn get(&self, window_entity: Entity) -> Option<&egui::Context> {
+        self.contexts.get(&window_entity)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Entity, &egui::Context)> {
+        self.contexts.iter()
+    }
+}
+
+fn update_egui_context_system(
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    mut egui_context: ResMut<EguiContext>,
+    mut window_created_events: EventReader<WindowCreated>,
+    mut window_closed_events: EventReader<WindowClosed>,
+) {
+    for event in window_created_events.iter() {
+        if let Some((_, gui)) = vulkano_windows.get_window_renderer(event.window) {
+            egui_context.contexts.insert(event.window, gui.context());
+        }
+    }
+    for event in window_closed_events.iter() {
+        egui_context.contexts.remove(&event.window);
+    }
+}
+
+pub fn begin_egui_frame_system(mut vulkano_windows: NonSendMut<BevyVulkanoWindows>) {
+    for (_, (_, g)) in vulkano_windows.windows.iter_mut() {
+        g.begin_frame();
+    }
+}
+
+/// Draws the gui over the current swapchain image after `after_future` (your render), then
+/// presents. Use this in place of [`VulkanoWindowRenderer::present`].
+pub fn present_with_gui(
+    window_renderer: &mut VulkanoWindowRenderer,
+    gui: &mut Gui,
+    after_future: Box<dyn GpuFuture>,
+    wait_future: bool,
+) -> Result<(), VulkanoRendererError> {
+    let after_gui = gui.draw_on_image(after_future, window_renderer.swapchain_image_view());
+    window_renderer.present(after_gui, wait_future)
+}
@@ -0,0 +1,440 @@
+use std::{
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use vulkano::{
+    device::{Device, DeviceOwned},
+    format::Format,
+    image::{
+        sys::{Image, ImageCreateInfo, ImageMemory, RawImage},
+        ImageAccess, ImageDescriptorLayouts, ImageDimensions, ImageError, ImageFormatInfo,
+        ImageInner, ImageLayout, ImageUsage,
+    },
+    memory::{
+        allocator::MemoryAlloc, DedicatedAllocation, DeviceMemory, DeviceMemoryError,
+        ExternalMemoryHandleType, ExternalMemoryHandleTypes, MemoryAllocateInfo, MemoryImportInfo,
+        MemoryPropertyFlags,
+    },
+    sync::Sharing,
+    DeviceSize,
+};
+
+/// Handle type used to share memory on this platform: opaque file descriptors on Unix and opaque
+/// NT handles on Windows. These are what CUDA & OpenGL (`GL_EXT_memory_object_fd` /
+/// `GL_EXT_memory_object_win32`) import.
+#[cfg(not(windows))]
+pub const EXTERNAL_MEMORY_HANDLE_TYPE: ExternalMemoryHandleType =
+    ExternalMemoryHandleType::OpaqueFd;
+/// Handle type used to share memory on this platform: opaque file descriptors on Unix and opaque
+/// NT handles on Windows. These are what CUDA & OpenGL (`GL_EXT_memory_object_fd` /
+/// `GL_EXT_memory_object_win32`) import.
+#[cfg(windows)]
+pub const EXTERNAL_MEMORY_HANDLE_TYPE: ExternalMemoryHandleType =
+    ExternalMemoryHandleType::OpaqueWin32;
+
+/// OS handle of memory shared with another API, see [`EXTERNAL_MEMORY_HANDLE_TYPE`]
+#[derive(Debug)]
+pub enum ExternalMemoryHandle {
+    /// Owned file descriptor, closed when dropped
+    #[cfg(unix)]
+    Fd(std::fs::File),
+    /// NT handle, which the owner must close with `CloseHandle`
+    #[cfg(windows)]
+    Win32(ash::vk::HANDLE),
+}
+
+/// 2D image whose memory is shared with other APIs through an OS handle, e.g. to hand rendered
+/// frames to CUDA, OpenGL or video encoders. Requires the `external_memory` feature, which
+/// requests `VK_KHR_external_memory` and the platform's handle extension when the device supports
+/// them, see [`external_memory_enabled`].
+///
+/// Export an image created with [`ExternalImage::new_exportable`] with
+/// [`ExternalImage::export_handle`], and give the other API the handle along with
+/// [`ExternalImage::allocation_size`] & [`ExternalImage::memory_type_index`]. Memory exported
+/// elsewhere is imported with [`ExternalImage::import`].
+///
+/// Synchronizing access with the other API (e.g. through external semaphores) is up to you. The
+/// image starts in `General` layout, which the other API should expect too.
+#[derive(Debug)]
+pub struct ExternalImage {
+    inner: Arc<Image>,
+    layout_initialized: AtomicBool,
+    allocation_size: DeviceSize,
+    memory_type_index: u32,
+}
+
+/// Whether `device` was created with the extensions needed by [`ExternalImage`] on this platform
+pub fn external_memory_enabled(device: &Device) -> bool {
+    let extensions = device.enabled_extensions();
+    extensions.khr_external_memory
+        && if cfg!(windows) {
+            extensions.khr_external_memory_win32
+        } else {
+            extensions.khr_external_memory_fd
+        }
+}
+
+impl ExternalImage {
+    /// Creates an image with dedicated device local memory which can be exported with
+    /// [`ExternalImage::export_handle`]. `queue_family_indices` are the families the image is
+    /// used on, it's shared concurrently if there are several.
+    pub fn new_exportable(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+        queue_family_indices: &[u32],
+    ) -> Result<Arc<ExternalImage>, ExternalMemoryError> {
+        check_support(&device, format, usage, true)?;
+        let raw_image = create_raw_image(&device, dimensions, format, usage, queue_family_indices)?;
+        let requirements = raw_image.memory_requirements()[0];
+        let memory_type_index = device_local_memory_type(&device, requirements.memory_type_bits)
+            .ok_or(ExternalMemoryError::NoMemoryType)?;
+        let allocation_size = requirements.layout.size();
+        let memory = DeviceMemory::allocate(device, MemoryAllocateInfo {
+            allocation_size,
+            memory_type_index,
+            export_handle_types: ExternalMemoryHandleTypes::from(EXTERNAL_MEMORY_HANDLE_TYPE),
+            ..MemoryAllocateInfo::dedicated_allocation(DedicatedAllocation::Image(&raw_image))
+        })?;
+        Self::bind(raw_image, memory, allocation_size, memory_type_index)
+    }
+
+    /// Creates an image bound to memory exported by another API or Vulkan device.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of [`EXTERNAL_MEMORY_HANDLE_TYPE`] to memory of a
+    ///   dedicated image allocation of `memory_type_index` on the same physical device.
+    /// - The exported image must have been created identically, i.e. with the same dimensions,
+    ///   format, usage and sharing.
+    /// - A file descriptor is owned by Vulkan afterwards, don't use it or its duplicates. A Win32
+    ///   handle isn't, and must still be closed by its owner.
+    ///
+    /// See [`MemoryImportInfo`] for details.
+    pub unsafe fn import(
+        device: Arc<Device>,
+        handle: ExternalMemoryHandle,
+        dimensions: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+        queue_family_indices: &[u32],
+        memory_type_index: u32,
+    ) -> Result<Arc<ExternalImage>, ExternalMemoryError> {
+        check_support(&device, format, usage, false)?;
+        let raw_image = create_raw_image(&device, dimensions, format, usage, queue_family_indices)?;
+        let requirements = raw_image.memory_requirements()[0];
+        if requirements.memory_type_bits & (1 << memory_type_index) == 0 {
+            return Err(ExternalMemoryError::NoMemoryType);
+        }
+        let allocation_size = requirements.layout.size();
+        let import_info = match handle {
+            #[cfg(unix)]
+            ExternalMemoryHandle::Fd(file) => MemoryImportInfo::Fd {
+                handle_type: EXTERNAL_MEMORY_HANDLE_TYPE,
+                file,
+            },
+            #[cfg(windows)]
+            ExternalMemoryHandle::Win32(handle) => MemoryImportInfo::Win32 {
+                handle_type: EXTERNAL_MEMORY_HANDLE_TYPE,
+                handle,
+            },
+        };
+        let memory = DeviceMemory::import(
+            device,
+            MemoryAllocateInfo {
+                allocation_size,
+                memory_type_index,
+                ..MemoryAllocateInfo::dedicated_allocation(DedicatedAllocation::Image(&raw_image))
+            },
+            import_info,
+        )?;
+        Self::bind(raw_image, memory, allocation_size, memory_type_index)
+    }
+
+    fn bind(
+        raw_image: RawImage,
+        memory: DeviceMemory,
+        allocation_size: DeviceSize,
+        memory_type_index: u32,
+    ) -> Result<Arc<ExternalImage>, ExternalMemoryError> {
+        let allocation = MemoryAlloc::new(memory).map_err(ImageError::from)?;
+        let inner = Arc::new(
+            raw_image
+                .bind_memory([allocation])
+                .map_err(|(err, _, _)| err)?,
+        );
+        Ok(Arc::new(ExternalImage {
+            inner,
+            layout_initialized: AtomicBool::new(false),
+            allocation_size,
+            memory_type_index,
+        }))
+    }
+
+    /// Exports a new handle to the image's memory. Each call returns a new handle, which the
+    /// caller (or the API importing it) owns.
+    pub fn export_handle(&self) -> Result<ExternalMemoryHandle, ExternalMemoryError> {
+        let memory = self.device_memory();
+        if !memory
+            .export_handle_types()
+            .intersects(EXTERNAL_MEMORY_HANDLE_TYPE.into())
+        {
+            return Err(ExternalMemoryError::NotExportable);
+        }
+        #[cfg(unix)]
+        {
+            Ok(ExternalMemoryHandle::Fd(
+                memory.export_fd(EXTERNAL_MEMORY_HANDLE_TYPE)?,
+            ))
+        }
+        #[cfg(windows)]
+        {
+            // vulkano only exports file descriptors
+            use vulkano::VulkanObject;
+            let device = self.device();
+            let info = ash::vk::MemoryGetWin32HandleInfoKHR {
+                memory: memory.handle(),
+                handle_type: EXTERNAL_MEMORY_HANDLE_TYPE.into(),
+                ..Default::default()
+            };
+            let mut handle = std::ptr::null_mut();
+            let result = unsafe {
+                (device
+                    .fns()
+                    .khr_external_memory_win32
+                    .get_memory_win32_handle_khr)(
+                    device.handle(), &info, &mut handle
+                )
+            };
+            if result != ash::vk::Result::SUCCESS {
+                return Err(ExternalMemoryError::Export(result));
+            }
+            Ok(ExternalMemoryHandle::Win32(handle))
+        }
+    }
+
+    /// Size in bytes of the image's memory, which importers need along with the handle
+    #[inline]
+    pub fn allocation_size(&self) -> DeviceSize {
+        self.allocation_size
+    }
+
+    /// Memory type of the image's memory, which Vulkan importers need along with the handle
+    #[inline]
+    pub fn memory_type_index(&self) -> u32 {
+        self.memory_type_index
+    }
+
+    pub fn dimensions(&self) -> ImageDimensions {
+        self.inner.dimensions()
+    }
+
+    fn device_memory(&self) -> &DeviceMemory {
+        match self.inner.memory() {
+            ImageMemory::Normal(allocations) => allocations[0].device_memory(),
+            _ => unreachable!("external images are bound to a single allocation"),
+        }
+    }
+}
+
+/// Checks that the device can share images of `format` & `usage` through the platform handle type
+fn check_support(
+    device: &Device,
+    format: Format,
+    usage: ImageUsage,
+    export: bool,
+) -> Result<(), ExternalMemoryError> {
+    if !external_memory_enabled(device) {
+        return Err(ExternalMemoryError::ExtensionNotEnabled);
+    }
+    let properties = device
+        .physical_device()
+        .image_format_properties(ImageFormatInfo {
+            format: Some(format),
+            usage,
+            external_memory_handle_type: Some(EXTERNAL_MEMORY_HANDLE_TYPE),
+            ..Default::default()
+        })
+        .ok()
+        .flatten()
+        .ok_or(ExternalMemoryError::NotSupported)?;
+    let external_memory_properties = properties.external_memory_properties;
+    let supported = if export {
+        external_memory_properties.exportable
+    } else {
+        external_memory_properties.importable
+    };
+    if !supported {
+        return Err(ExternalMemoryError::NotSupported);
+    }
+    Ok(())
+}
+
+fn create_raw_image(
+    device: &Arc<Device>,
+    dimensions: [u32; 2],
+    format: Format,
+    usage: ImageUsage,
+    queue_family_indices: &[u32],
+) -> Result<RawImage, ImageError> {
+    let mut queue_family_indices = queue_family_indices.to_vec();
+    queue_family_indices.sort_unstable();
+    queue_family_indices.dedup();
+    RawImage::new(device.clone(), ImageCreateInfo {
+        dimensions: ImageDimensions::Dim2d {
+            width: dimensions[0],
+            height: dimensions[1],
+            array_layers: 1,
+        },
+        format: Some(format),
+        usage,
+        sharing: if queue_family_indices.len() >= 2 {
+            Sharing::Concurrent(queue_family_indices.into())
+        } else {
+            Sharing::Exclusive
+        },
+        external_memory_handle_types: EXTERNAL_MEMORY_HANDLE_TYPE.into(),
+        ..Default::default()
+    })
+}
+
+/// First device local memory type allowed by `memory_type_bits`
+fn device_local_memory_type(device: &Device, memory_type_bits: u32) -> Option<u32> {
+    let memory_types = &device.physical_device().memory_properties().memory_types;
+    (0..memory_types.len() as u32).find(|&index| {
+        memory_type_bits & (1 << index) != 0
+            && memory_types[index as usize]
+                .property_flags
+                .intersects(MemoryPropertyFlags::DEVICE_LOCAL)
+    })
+}
+
+unsafe impl ImageAccess for ExternalImage {
+    fn inner(&self) -> ImageInner<'_> {
+        ImageInner {
+            image: &self.inner,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: 1,
+        }
+    }
+
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::General,
+            sampled_image: ImageLayout::General,
+            input_attachment: ImageLayout::General,
+        })
+    }
+
+    unsafe fn layout_initialized(&self) {
+        self.layout_initialized.store(true, Ordering::SeqCst);
+    }
+
+    fn is_layout_initialized(&self) -> bool {
+        self.layout_initialized.load(Ordering::SeqCst)
+    }
+}
+
+unsafe impl DeviceOwned for ExternalImage {
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+impl PartialEq for ExternalImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for ExternalImage {}
+
+impl Hash for ExternalImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
+/// Errors that can happen when creating, exporting or importing an [`ExternalImage`].
+#[derive(Debug)]
+pub enum ExternalMemoryError {
+    /// `VK_KHR_external_memory` or the platform's handle extension isn't enabled, see
+    /// [`external_memory_enabled`].
+    ExtensionNotEnabled,
+    /// Images of the format & usage can't be exported or imported with
+    /// [`EXTERNAL_MEMORY_HANDLE_TYPE`].
+    NotSupported,
+    /// No device local memory type fits the image, or the memory type to import into doesn't.
+    NoMemoryType,
+    /// The image's memory wasn't allocated for export.
+    NotExportable,
+    /// Creating the image or binding its memory failed.
+    Image(ImageError),
+    /// Allocating, importing or exporting the memory failed.
+    Memory(DeviceMemoryError),
+    /// Exporting a Win32 handle failed.
+    Export(ash::vk::Result),
+}
+
+impl fmt::Display for ExternalMemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalMemoryError::ExtensionNotEnabled => {
+                write!(f, "external memory extensions are not enabled")
+            }
+            ExternalMemoryError::NotSupported => write!(
+                f,
+                "image format & usage can't be shared with {:?} handles",
+                EXTERNAL_MEMORY_HANDLE_TYPE
+            ),
+            ExternalMemoryError::NoMemoryType => {
+                write!(f, "no suitable memory type for the external image")
+            }
+            ExternalMemoryError::NotExportable => write!(f, "image memory is not exportable"),
+            ExternalMemoryError::Image(e) => write!(f, "failed to create image: {}", e),
+            ExternalMemoryError::Memory(e) => write!(f, "failed to share memory: {}", e),
+            ExternalMemoryError::Export(e) => write!(f, "failed to export memory: {}", e),
+        }
+    }
+}
+
+impl Error for ExternalMemoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExternalMemoryError::Image(e) => Some(e),
+            ExternalMemoryError::Memory(e) => Some(e),
+            ExternalMemoryError::Export(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ImageError> for ExternalMemoryError {
+    fn from(err: ImageError) -> Self {
+        ExternalMemoryError::Image(err)
+    }
+}
+
+impl From<DeviceMemoryError> for ExternalMemoryError {
+    fn from(err: DeviceMemoryError) -> Self {
+        ExternalMemoryError::Memory(err)
+    }
+}
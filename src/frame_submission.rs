@@ -0,0 +1,162 @@
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    device::{Device, DeviceOwned},
+    image::{ImageAccess, ImageUsage, ImageViewAbstract},
+    sampler::Filter,
+    sync::GpuFuture,
+};
+
+use crate::{util::blit_image, VulkanoWindowRenderer};
+
+/// Frames produced on other threads (e.g. video decoders or compute workers) to be presented to
+/// windows by [`post_render_system`](crate::post_render_system). The resource is only inserted
+/// with [`VulkanoWinitConfig::auto_present`](crate::VulkanoWinitConfig::auto_present), as nothing
+/// else would consume pushed frames.
+///
+/// Clone the resource into producer threads and [`FrameSubmissionQueue::push`] an image along
+/// with the future of the work writing it. On the next rendered frame, the latest image pushed
+/// for each window is blitted over the frame, scaled to fit, after anything chained to
+/// [`SyncData::after`](crate::SyncData::after). Earlier images pushed for the window are skipped,
+/// but their futures are still waited on.
+#[derive(Resource, Clone)]
+pub struct FrameSubmissionQueue {
+    device: Arc<Device>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    submissions: Arc<Mutex<HashMap<Entity, Vec<FrameSubmission>>>>,
+}
+
+struct FrameSubmission {
+    image_view: Arc<dyn ImageViewAbstract>,
+    future: Box<dyn GpuFuture + Send + Sync>,
+}
+
+impl FrameSubmissionQueue {
+    pub(crate) fn new(
+        device: Arc<Device>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    ) -> FrameSubmissionQueue {
+        FrameSubmissionQueue {
+            device,
+            command_buffer_allocator,
+            submissions: Arc::new(Mutex::new(HashMap::default())),
+        }
+    }
+
+    /// Submits `image_view` to be presented to `window` once `future` completes. The image must
+    /// have `TRANSFER_SRC` usage and be usable from the window's graphics queue family, e.g.
+    /// created with concurrent sharing if written on another queue family. The swapchain images
+    /// need `TRANSFER_DST` usage, see
+    /// [`VulkanoWinitConfig::swapchain_image_usage`](crate::VulkanoWinitConfig::swapchain_image_usage).
+    pub fn push(
+        &self,
+        window: Entity,
+        image_view: Arc<dyn ImageViewAbstract>,
+        future: Box<dyn GpuFuture + Send + Sync>,
+    ) -> Result<(), FrameSubmissionError> {
+        let queue_device = future.queue().map(|queue| queue.device().clone());
+        if image_view.device() != &self.device
+            || future.device() != &self.device
+            || queue_device.is_some_and(|device| device != self.device)
+        {
+            return Err(FrameSubmissionError::DeviceMismatch);
+        }
+        if !image_view.usage().intersects(ImageUsage::TRANSFER_SRC) {
+            return Err(FrameSubmissionError::MissingTransferSrcUsage);
+        }
+        self.submissions
+            .lock()
+            .unwrap()
+            .entry(window)
+            .or_default()
+            .push(FrameSubmission {
+                image_view,
+                future,
+            });
+        Ok(())
+    }
+
+    /// Whether frames were pushed for `window` since its last rendered frame
+    pub fn has_pending(&self, window: Entity) -> bool {
+        self.submissions
+            .lock()
+            .unwrap()
+            .get(&window)
+            .is_some_and(|submissions| !submissions.is_empty())
+    }
+
+    /// Blits the latest image pushed for `window` over its current swapchain image after
+    /// `before_future` and the futures of all pushed images. Returns `Err` with `before_future`
+    /// if nothing was pushed.
+    pub(crate) fn composite(
+        &self,
+        window: Entity,
+        renderer: &VulkanoWindowRenderer,
+        before_future: Box<dyn GpuFuture>,
+    ) -> Result<Box<dyn GpuFuture>, Box<dyn GpuFuture>> {
+        let submissions = match self.submissions.lock().unwrap().remove(&window) {
+            Some(submissions) if !submissions.is_empty() => submissions,
+            _ => return Err(before_future),
+        };
+        let mut future = before_future;
+        let mut latest_image = None;
+        for submission in submissions {
+            future = future.join(submission.future).boxed();
+            latest_image = Some(submission.image_view);
+        }
+        let src_image = latest_image.unwrap().image().clone();
+        let dst_image = renderer.swapchain_image_view().image().clone();
+        if !dst_image.usage().intersects(ImageUsage::TRANSFER_DST) {
+            warn!("Swapchain images don't support TRANSFER_DST usage, skipping submitted frame");
+            return Ok(future);
+        }
+        let queue = renderer.graphics_queue();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &*self.command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        if let Err(e) = blit_image(&mut builder, src_image, dst_image, Filter::Linear) {
+            error!("Failed to blit submitted frame: {}", e);
+            return Ok(future);
+        }
+        let command_buffer = builder.build().unwrap();
+        Ok(future.then_execute(queue, command_buffer).unwrap().boxed())
+    }
+}
+
+/// Error returned by [`FrameSubmissionQueue::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameSubmissionError {
+    /// The image, future or its queue belongs to another device than the context's.
+    DeviceMismatch,
+    /// The image can't be blitted from without `TRANSFER_SRC` usage.
+    MissingTransferSrcUsage,
+}
+
+impl fmt::Display for FrameSubmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameSubmissionError::DeviceMismatch => {
+                write!(
+                    f,
+                    "submitted frame belongs to another device than the context"
+                )
+            }
+            FrameSubmissionError::MissingTransferSrcUsage => {
+                write!(f, "submitted image lacks TRANSFER_SRC usage")
+            }
+        }
+    }
+}
+
+impl Error for FrameSubmissionError {}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder},
+    device::Queue,
+    query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType},
+    sync::PipelineStage,
+};
+
+use crate::BevyVulkanoContext;
+
+/// Measures GPU time between two points of a command buffer using timestamp queries.
+///
+/// Record [`GpuTimer::begin`] and [`GpuTimer::end`] around the commands you want to measure, then
+/// read the elapsed time with [`GpuTimer::resolve`] once the command buffer has finished
+/// executing.
+pub struct GpuTimer {
+    query_pool: Arc<QueryPool>,
+    /// Nanoseconds per timestamp tick
+    timestamp_period: f32,
+    /// Mask of the valid bits of timestamps written on the queue
+    timestamp_mask: u64,
+}
+
+impl GpuTimer {
+    /// Creates a timer for command buffers submitted to `queue`. Returns `None` if the queue family
+    /// doesn't support timestamps.
+    pub fn new(context: &BevyVulkanoContext, queue: &Arc<Queue>) -> Option<GpuTimer> {
+        let device = context.context.device();
+        let timestamp_valid_bits = device.physical_device().queue_family_properties()
+            [queue.queue_family_index() as usize]
+            .timestamp_valid_bits?;
+        let query_pool = QueryPool::new(device.clone(), QueryPoolCreateInfo {
+            query_count: 2,
+            ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+        })
+        .ok()?;
+        Some(GpuTimer {
+            query_pool,
+            timestamp_period: device.physical_device().properties().timestamp_period,
+            timestamp_mask: u64::MAX >> (64 - timestamp_valid_bits),
+        })
+    }
+
+    /// Records the start timestamp. This resets the timer, so it must be called outside a render
+    /// pass.
+    pub fn begin<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+    ) {
+        unsafe {
+            builder
+                .reset_query_pool(self.query_pool.clone(), 0..2)
+                .unwrap()
+                .write_timestamp(self.query_pool.clone(), 0, PipelineStage::TopOfPipe)
+                .unwrap();
+        }
+    }
+
+    /// Records the end timestamp after all previously recorded commands have completed
+    pub fn end<L, A: CommandBufferAllocator>(&self, builder: &mut AutoCommandBufferBuilder<L, A>) {
+        unsafe {
+            builder
+                .write_timestamp(self.query_pool.clone(), 1, PipelineStage::BottomOfPipe)
+                .unwrap();
+        }
+    }
+
+    /// Elapsed milliseconds between [`GpuTimer::begin`] and [`GpuTimer::end`], or `None` if the
+    /// results aren't available yet. Only call this after a command buffer recording the timer has
+    /// been submitted.
+    pub fn resolve(&self) -> Option<f64> {
+        let mut timestamps = [0u64; 2];
+        let available = self
+            .query_pool
+            .queries_range(0..2)?
+            .get_results(&mut timestamps, QueryResultFlags::empty())
+            .ok()?;
+        if !available {
+            return None;
+        }
+        // Masking the difference keeps it right when the counter wrapped between the timestamps
+        let ticks = timestamps[1].wrapping_sub(timestamps[0]) & self.timestamp_mask;
+        Some(ticks as f64 * self.timestamp_period as f64 / 1_000_000.0)
+    }
+}
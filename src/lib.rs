@@ -11,10 +11,40 @@
 Pretty much the same as bevy_winit, but organized to use vulkano renderer backend.
 This allows you to create your own pipelines for rendering.
  */
+mod allocator_stats;
+mod compute_pass;
+mod config_builder;
+mod context;
 mod converters;
+mod descriptor_set_cache;
+#[cfg(feature = "gui")]
+pub mod egui;
+#[cfg(feature = "external_memory")]
+mod external_memory;
+mod frame_submission;
+mod gpu_timer;
+mod occlusion_queries;
+mod offscreen_target;
+mod per_swapchain_image;
+mod pipeline_cache;
 mod pipeline_sync_data;
+mod renderer;
+mod ring_uniform_allocator;
+mod sampler_cache;
+mod shader_reloader;
+mod shared_resources;
+mod transient_image;
+pub mod util;
 mod vulkano_windows;
 
+use std::{
+    any::Any,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub use allocator_stats::{MemoryAllocatorStats, MemoryTypeStats};
 use bevy::{
     app::{App, AppExit, Plugin},
     ecs::{
@@ -35,18 +65,67 @@ use bevy::{
         WindowCreated, WindowFocused, WindowMoved, WindowResized, WindowScaleFactorChanged,
     },
 };
+pub use compute_pass::*;
+pub use config_builder::*;
+pub use context::*;
+pub use descriptor_set_cache::*;
 #[cfg(feature = "gui")]
 pub use egui_winit_vulkano;
+#[cfg(feature = "external_memory")]
+pub use external_memory::*;
+pub use frame_submission::*;
+pub use gpu_timer::*;
+pub use occlusion_queries::*;
+pub use offscreen_target::*;
+pub use per_swapchain_image::*;
+use pipeline_cache::{load_pipeline_cache, save_pipeline_cache};
 pub use pipeline_sync_data::*;
-use vulkano_util::context::{VulkanoConfig, VulkanoContext};
+pub use renderer::*;
+pub use ring_uniform_allocator::*;
+pub use sampler_cache::*;
+pub use shader_reloader::*;
+pub use shared_resources::*;
+pub use transient_image::*;
+use vulkano::{
+    command_buffer::allocator::{
+        StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorSetLayout,
+        DescriptorSetCreationError, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType},
+        Device, DeviceExtensions, Features, Queue,
+    },
+    format::Format,
+    image::ImageUsage,
+    instance::{
+        debug::{DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessengerCreateInfo},
+        Instance, InstanceExtensions,
+    },
+    pipeline::cache::PipelineCache,
+    sampler::{Sampler, SamplerCreateInfo, SamplerCreationError},
+    swapchain::{ColorSpace, SurfaceTransform},
+    sync::GpuFuture,
+    OomError, Version, VulkanObject,
+};
 pub use vulkano_windows::*;
 use winit::{
     event::{self, DeviceEvent, Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    event_loop::{
+        ControlFlow, EventLoop, EventLoopBuilder, EventLoopClosed, EventLoopProxy,
+        EventLoopWindowTarget,
+    },
     window::WindowId,
 };
 
 /// Vulkano & winit related configurations
+///
+/// With the `serde` feature, the config can be loaded from settings files. Fields holding vulkano
+/// types or callbacks are skipped and, like missing fields, take their default values.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct VulkanoWinitConfig {
     /// Configures the winit library to return control to the main thread after
     /// the [run](bevy_app::App::run) loop is exited. Winit strongly recommends
@@ -60,6 +139,7 @@ pub struct VulkanoWinitConfig {
     /// [run](bevy_app::App::run) will panic.
     pub return_from_run: bool,
     /// Vulkano backend related configs
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub vulkano_config: VulkanoConfig,
     /// Whether the image gets cleared each frame by gui integration. This is only relevant if
     /// `gui` feature is set.
@@ -68,6 +148,163 @@ pub struct VulkanoWinitConfig {
     pub is_gui_overlay: bool,
     /// Control whether you want to run the app with or without a window
     pub add_primary_window: bool, // TODO: is this needed?
+    /// Depth (stencil) format for an optional depth attachment owned by each window renderer,
+    /// e.g. `Format::D32_SFLOAT` or `Format::D24_UNORM_S8_UINT`. The attachment is recreated
+    /// with the swapchain. Default is `None` (no depth attachment).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub depth_format: Option<Format>,
+    /// Which physical device to prefer. If the requested device isn't available, or doesn't
+    /// satisfy `vulkano_config.device_filter_fn`, the default device priority is used as a
+    /// fallback (with a warning). Default is `None`, which uses `vulkano_config.device_priority_fn`.
+    pub device_filter: Option<DeviceFilter>,
+    /// Minimum number of swapchain images, e.g. 2 for double and 3 for triple buffering. The value
+    /// is clamped to surface capabilities, see [`VulkanoWindowRenderer::image_count`] for the
+    /// count actually obtained. Default is `None`, which uses the surface minimum.
+    pub min_image_count: Option<u32>,
+    /// Maximum number of frames the CPU can record ahead of the GPU. Each window renderer keeps a
+    /// fence per frame and waits on it only when that frame slot is reused, see
+    /// [`VulkanoWindowRenderer::current_frame_index`]. Default is 0, which doesn't wait on frame
+    /// fences and leaves frame pacing to swapchain image acquisition.
+    pub frames_in_flight: usize,
+    /// Maximum frames per second presented by each window, independent of the present mode. The
+    /// CPU sleeps in [`VulkanoWindowRenderer::present`] for whatever is left of the frame time,
+    /// see [`VulkanoWindowRenderer::set_frame_rate_limit`]. Default is `None` (unlimited).
+    pub frame_rate_limit: Option<f64>,
+    /// Whether to enable `VK_LAYER_KHRONOS_validation` and `VK_EXT_debug_utils`. If the layer
+    /// isn't installed, a warning is logged and the app continues without it.
+    /// Default is `cfg!(debug_assertions)`.
+    pub enable_validation: bool,
+    /// Severities of validation messages which are forwarded to bevy's log. Only relevant if
+    /// `enable_validation` is set and `vulkano_config.debug_create_info` is `None`.
+    /// Default is errors and warnings.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub debug_message_severity: DebugUtilsMessageSeverity,
+    /// Types of validation messages which are forwarded to bevy's log.
+    /// Default is general, validation and performance messages.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub debug_message_type: DebugUtilsMessageType,
+    /// Device features to enable in addition to `vulkano_config.device_features`, e.g.
+    /// `shader_int64`. Physical devices not supporting them are skipped.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub required_features: Features,
+    /// Device extensions to enable in addition to `vulkano_config.device_extensions` (which
+    /// includes `khr_swapchain`), e.g. `khr_ray_query`. Physical devices not supporting them are
+    /// skipped.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub required_extensions: DeviceExtensions,
+    /// Instance extensions to enable in addition to those required for surfaces and validation,
+    /// e.g. for profilers & overlays. Instance creation fails listing any unsupported extensions.
+    /// Default is empty.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub instance_extensions: InstanceExtensions,
+    /// Whether to enable `VK_KHR_dynamic_rendering` (core in Vulkan 1.3) if the device supports
+    /// it, so that you can render with `begin_rendering` without render pass & framebuffer
+    /// objects. Check [`BevyVulkanoContext::dynamic_rendering_enabled`] and fall back to render
+    /// passes if it's not. Default is `false`.
+    pub dynamic_rendering: bool,
+    /// Application name & version reported to the Vulkan instance, which tools like RenderDoc
+    /// can key off. Default is `None`, which leaves vulkano's defaults.
+    pub application_info: Option<ApplicationInfo>,
+    /// File to load the pipeline cache from at startup and to save it to on [`AppExit`], see
+    /// [`BevyVulkanoContext::pipeline_cache`]. Corrupt or incompatible data is discarded.
+    /// Default is `None`, which uses an empty cache that isn't persisted.
+    pub pipeline_cache_path: Option<PathBuf>,
+    /// Swapchain formats & color spaces to try in order against those supported by the surface,
+    /// e.g. `(Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084)` for HDR. If none are
+    /// supported, the default `B8G8R8A8_SRGB` is used. Windows spawned with a
+    /// [`PreferredSurfaceFormats`] component try those first. See
+    /// [`VulkanoWindowRenderer::swapchain_color_space`]. Default is empty.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub preferred_surface_formats: Vec<(Format, ColorSpace)>,
+    /// Additional swapchain image usage, e.g. `ImageUsage::STORAGE` to write to swapchain images
+    /// from compute shaders (which also needs a swapchain format supporting storage, see
+    /// `preferred_surface_formats`). `COLOR_ATTACHMENT` is always set. Bits not supported by the surface
+    /// are ignored with a warning. Default is empty.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub swapchain_image_usage: ImageUsage,
+    /// Creates the memory allocator shared through [`VulkanoContext::memory_allocator`], e.g. an
+    /// arena allocator for per-frame scratch memory. Called once at context creation. Default is
+    /// `None`, which creates a `StandardMemoryAllocator`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub memory_allocator_factory: Option<Box<dyn Fn(Arc<Device>) -> Arc<SharedMemoryAllocator>>>,
+    /// Whether to acquire and present window frames automatically, see [`VulkanoRenderSet`].
+    /// Default is `false`, meaning you call [`VulkanoWindowRenderer::acquire`] and
+    /// [`VulkanoWindowRenderer::present`] yourself.
+    pub auto_present: bool,
+    /// Icon of windows at creation, see `winit::window::Icon::from_rgba`. Change it at runtime with
+    /// [`BevyVulkanoWindows::set_window_icon`]. Default is `None`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub window_icon: Option<winit::window::Icon>,
+    /// Priorities (between 0.0 and 1.0) of the graphics queues to create, one per queue, e.g.
+    /// `vec![1.0, 0.2]` for a high priority UI queue & a low priority background queue. Queues the
+    /// graphics family doesn't support are dropped, see [`VulkanoContext::graphics_queues`].
+    /// Default is `None`, which uses `vulkano_config.graphics_queue_priorities`.
+    pub graphics_queue_priorities: Option<Vec<f32>>,
+    /// Maximum number of presents queued when acquiring the next frame, e.g. 1 to wait for the
+    /// previous frame to be presented, minimizing input latency. Uses `VK_KHR_present_wait` if
+    /// the device supports it, see [`VulkanoWindowRenderer::present_latency`]. Otherwise
+    /// `frames_in_flight` is limited to this value instead. Default is `None`.
+    pub max_frame_latency: Option<u32>,
+    /// Enables `VK_GOOGLE_display_timing` if the device supports it, for
+    /// [`VulkanoWindowRenderer::present_at`] & [`VulkanoWindowRenderer::past_presentation_timings`].
+    /// Default is `false`.
+    pub display_timing: bool,
+    /// Pool sizes of the command buffer allocator shared through
+    /// [`VulkanoContext::command_buffer_allocator`], e.g. larger pools when many threads record
+    /// command buffers for many windows. Default is `None`, which uses
+    /// `vulkano_config.command_buffer_allocator_create_info` (vulkano's defaults).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub command_buffer_allocator_create_info: Option<StandardCommandBufferAllocatorCreateInfo>,
+    /// Kind of context to create. [`ContextKind::ComputeOnly`] selects devices with just a
+    /// compute queue and enables no surface or swapchain extensions, so it can only be used with
+    /// [`HeadlessVulkanoPlugin`]. Default is [`ContextKind::Graphics`].
+    pub context_kind: ContextKind,
+    /// Instance to create the context on instead of creating one, e.g. when an OpenXR runtime
+    /// dictates the instance extensions. `vulkano_config.instance_create_info`,
+    /// `instance_extensions`, `application_info` and validation layers are then ignored. Default
+    /// is `None`. See [`BevyVulkanoContext::from_instance`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub instance: Option<Arc<Instance>>,
+    /// Enables `VK_EXT_full_screen_exclusive` on Windows if the device supports it, so that
+    /// fullscreen windows can take exclusive access of their monitor with
+    /// [`VulkanoWindowRenderer::acquire_full_screen_exclusive`]. Ignored on other platforms.
+    /// Default is `false`.
+    pub full_screen_exclusive: bool,
+    /// Queue families to create the context's queues from, validated against the physical
+    /// devices. Default pins none, which keeps `vulkano_config.queue_family_overrides`. See
+    /// [`BevyVulkanoContext::queue_family_indices`] for the families actually used.
+    pub queue_family_overrides: QueueFamilyOverrides,
+    /// How long [`VulkanoWindowRenderer::acquire`] waits for a swapchain image before returning
+    /// [`VulkanoRendererError::Timeout`], so that a starved swapchain skips frames instead of
+    /// blocking the app. Change it at runtime with
+    /// [`VulkanoWindowRenderer::set_acquire_timeout`]. Default is `None`, which waits
+    /// indefinitely.
+    pub acquire_timeout: Option<Duration>,
+    /// Runs each app update only once the frames presented by the previous update are shown, see
+    /// [`VulkanoWindowRenderer::wait_for_last_present`], so that there's exactly one update per
+    /// displayed frame. Uses `VK_KHR_present_wait` if the device supports it, otherwise the frames'
+    /// GPU work is waited on instead. Updates aren't throttled while no frame is presented, e.g.
+    /// when all windows are minimized.
+    ///
+    /// bevy's `Time` is still measured by the CPU clock at the start of each update, so its
+    /// delta follows the display refresh interval (with some jitter) instead of being a constant
+    /// tick. Use one simulation step per update rather than `FixedTimestep` for exact
+    /// one-tick-per-frame behavior. Default is `false`, which runs updates as fast as the event
+    /// loop allows.
+    pub drive_schedule_on_present: bool,
+    /// Keeps resizes from stalling on frames in flight: [`VulkanoWindowRenderer::recreate_swapchain`]
+    /// doesn't wait for them, and resizes keep the window's [`SyncData`] instead of replacing it.
+    /// Swapchains are always recreated with the old one as `old_swapchain`, and frames still
+    /// using the old swapchain keep it & its images alive until they complete, so it's only
+    /// destroyed once its last frame's fence has signaled. Default is `false`.
+    pub smooth_resize: bool,
+    /// Transform applied to swapchain images before presenting, see
+    /// [`VulkanoWindowRenderer::pre_transform`]. `None` follows the surface's current transform,
+    /// which is the identity on desktop and the display rotation on e.g. Android, sparing the
+    /// compositor a rotation pass. Transforms the surface doesn't support fall back to its
+    /// current transform with a warning. Default is `None`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pre_transform: Option<SurfaceTransform>,
 }
 
 impl Default for VulkanoWinitConfig {
@@ -78,14 +315,289 @@ impl Default for VulkanoWinitConfig {
             #[cfg(feature = "gui")]
             is_gui_overlay: true,
             add_primary_window: true,
+            depth_format: None,
+            device_filter: None,
+            min_image_count: None,
+            frames_in_flight: 0,
+            frame_rate_limit: None,
+            enable_validation: cfg!(debug_assertions),
+            debug_message_severity: DebugUtilsMessageSeverity::ERROR
+                | DebugUtilsMessageSeverity::WARNING,
+            debug_message_type: DebugUtilsMessageType::GENERAL
+                | DebugUtilsMessageType::VALIDATION
+                | DebugUtilsMessageType::PERFORMANCE,
+            required_features: Features::empty(),
+            required_extensions: DeviceExtensions::empty(),
+            instance_extensions: InstanceExtensions::empty(),
+            dynamic_rendering: false,
+            application_info: None,
+            pipeline_cache_path: None,
+            preferred_surface_formats: vec![],
+            swapchain_image_usage: ImageUsage::empty(),
+            memory_allocator_factory: None,
+            auto_present: false,
+            window_icon: None,
+            graphics_queue_priorities: None,
+            max_frame_latency: None,
+            display_timing: false,
+            command_buffer_allocator_create_info: None,
+            context_kind: ContextKind::Graphics,
+            instance: None,
+            full_screen_exclusive: false,
+            queue_family_overrides: QueueFamilyOverrides::default(),
+            acquire_timeout: None,
+            drive_schedule_on_present: false,
+            smooth_resize: false,
+            pre_transform: None,
         }
     }
 }
 
+impl VulkanoWinitConfig {
+    /// Starts building a config from the defaults with chainable setters
+    pub fn builder() -> VulkanoWinitConfigBuilder {
+        VulkanoWinitConfigBuilder::new()
+    }
+}
+
+/// Application info used by [`VulkanoWinitConfig::application_info`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ApplicationInfo {
+    pub application_name: String,
+    #[cfg_attr(feature = "serde", serde(with = "SerdeVersion"))]
+    pub application_version: Version,
+    pub engine_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(with = "SerdeVersion"))]
+    pub engine_version: Version,
+}
+
+/// Serde definition of vulkano's [`Version`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(remote = "Version")]
+struct SerdeVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl Default for ApplicationInfo {
+    fn default() -> Self {
+        ApplicationInfo {
+            application_name: String::new(),
+            application_version: Version::default(),
+            engine_name: Some("bevy_vulkano".to_owned()),
+            engine_version: Version::default(),
+        }
+    }
+}
+
+/// Physical device preference used by [`VulkanoWinitConfig::device_filter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceFilter {
+    PreferDiscrete,
+    PreferIntegrated,
+    /// Index in the order physical devices are enumerated by the instance
+    ByIndex(usize),
+    /// Exact device name, e.g. "NVIDIA GeForce RTX 3070"
+    ByName(String),
+}
+
+impl DeviceFilter {
+    /// Whether the physical device is the one requested by this filter
+    pub fn matches(&self, physical_device: &PhysicalDevice) -> bool {
+        match self {
+            DeviceFilter::PreferDiscrete => {
+                physical_device.properties().device_type == PhysicalDeviceType::DiscreteGpu
+            }
+            DeviceFilter::PreferIntegrated => {
+                physical_device.properties().device_type == PhysicalDeviceType::IntegratedGpu
+            }
+            DeviceFilter::ByIndex(index) => {
+                physical_device
+                    .instance()
+                    .enumerate_physical_devices()
+                    .ok()
+                    .and_then(|mut devices| {
+                        devices.position(|device| device.handle() == physical_device.handle())
+                    })
+                    == Some(*index)
+            }
+            DeviceFilter::ByName(name) => physical_device.properties().device_name == *name,
+        }
+    }
+
+    /// Wraps the device priority function of the config so that the matching device is picked
+    /// first, while others keep their original order as fallback.
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn apply(&self, vulkano_config: &mut VulkanoConfig) {
+        let filter = self.clone();
+        let fallback_priority = vulkano_config.device_priority_fn.clone();
+        vulkano_config.device_priority_fn = Arc::new(move |p| {
+            if filter.matches(p) {
+                0
+            } else {
+                fallback_priority(p).saturating_add(1)
+            }
+        });
+    }
+}
+
 /// Wrapper around [`VulkanoContext`] to allow using them as resources
 #[derive(Resource)]
 pub struct BevyVulkanoContext {
     pub context: VulkanoContext,
+    pipeline_cache: Arc<PipelineCache>,
+    pipeline_cache_path: Option<PathBuf>,
+    sampler_cache: SamplerCache,
+    descriptor_set_cache: DescriptorSetCache,
+}
+
+impl BevyVulkanoContext {
+    /// Vulkan instance the context was created on
+    pub fn instance(&self) -> &Arc<Instance> {
+        self.context.instance()
+    }
+
+    /// Queue families of the context's queues, see [`VulkanoContext::queue_family_indices`]
+    pub fn queue_family_indices(&self) -> QueueFamilyIndices {
+        self.context.queue_family_indices()
+    }
+
+    /// Name of the selected physical device
+    pub fn device_name(&self) -> &str {
+        self.context.device_name()
+    }
+
+    /// Type of the selected physical device
+    pub fn device_type(&self) -> PhysicalDeviceType {
+        self.context.device_type()
+    }
+
+    /// Device features actually enabled, use these to branch on optional features at runtime
+    pub fn enabled_features(&self) -> &Features {
+        self.context.device().enabled_features()
+    }
+
+    /// Device extensions actually enabled
+    pub fn enabled_extensions(&self) -> &DeviceExtensions {
+        self.context.device().enabled_extensions()
+    }
+
+    /// Whether dynamic rendering can be used, see [`VulkanoWinitConfig::dynamic_rendering`]
+    pub fn dynamic_rendering_enabled(&self) -> bool {
+        self.context.device().enabled_features().dynamic_rendering
+    }
+
+    /// Async compute queue from a queue family separate from graphics, or `None` if the device
+    /// doesn't have one. Fall back to the graphics queue in that case.
+    pub fn compute_queue(&self) -> Option<Arc<Queue>> {
+        self.context.compute_queue()
+    }
+
+    /// Transfer queue from a queue family separate from graphics, or the graphics queue if the
+    /// device doesn't have one. See [`VulkanoContext::upload_image_async`] for staged uploads.
+    pub fn transfer_queue(&self) -> Arc<Queue> {
+        self.context.transfer_queue()
+    }
+
+    /// Command buffer allocator shared by the whole app
+    pub fn command_buffer_allocator(&self) -> Arc<StandardCommandBufferAllocator> {
+        self.context.command_buffer_allocator().clone()
+    }
+
+    /// Descriptor set allocator shared by the whole app
+    pub fn descriptor_set_allocator(&self) -> Arc<StandardDescriptorSetAllocator> {
+        self.context.descriptor_set_allocator().clone()
+    }
+
+    /// Pipeline cache to pass to your pipeline builders. Loaded from and saved to
+    /// [`VulkanoWinitConfig::pipeline_cache_path`] if set.
+    pub fn pipeline_cache(&self) -> Arc<PipelineCache> {
+        self.pipeline_cache.clone()
+    }
+
+    /// Returns a sampler shared by every pipeline requesting the same `create_info`, creating it
+    /// only once. See [`SamplerCache`].
+    pub fn get_sampler(
+        &self,
+        create_info: SamplerCreateInfo,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        self.sampler_cache.get(create_info)
+    }
+
+    /// Returns a descriptor set of `layout` written with `descriptor_writes`, reused as long as the
+    /// same resources are written. Create sets of per-frame resources with
+    /// [`BevyVulkanoContext::descriptor_set_cache`] & [`DescriptorSetCache::create`] instead.
+    pub fn get_descriptor_set(
+        &self,
+        layout: Arc<DescriptorSetLayout>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+    ) -> Result<Arc<PersistentDescriptorSet>, DescriptorSetCreationError> {
+        self.descriptor_set_cache.get(layout, descriptor_writes)
+    }
+
+    /// Descriptor sets shared through [`BevyVulkanoContext::get_descriptor_set`]
+    pub fn descriptor_set_cache(&self) -> &DescriptorSetCache {
+        &self.descriptor_set_cache
+    }
+
+    /// Blocks until the GPU has finished all submitted work, see [`VulkanoContext::wait_idle`].
+    /// This is done automatically on [`AppExit`], so that resources aren't freed while in use.
+    pub fn wait_idle(&self) -> Result<(), OomError> {
+        self.context.wait_idle()
+    }
+
+    /// Maximum anisotropy samplers can use, or `None` if the device doesn't support anisotropic
+    /// filtering. `sampler_anisotropy` is enabled automatically when supported.
+    pub fn max_sampler_anisotropy(&self) -> Option<f32> {
+        let device = self.context.device();
+        device
+            .enabled_features()
+            .sampler_anisotropy
+            .then(|| device.physical_device().properties().max_sampler_anisotropy)
+    }
+
+    /// Returns a shared trilinear, repeating sampler with up to `anisotropy` (e.g. 16.0) samples.
+    /// The anisotropy is clamped to [`BevyVulkanoContext::max_sampler_anisotropy`], and dropped
+    /// if anisotropic filtering isn't supported, instead of failing sampler creation.
+    pub fn anisotropic_sampler(
+        &self,
+        anisotropy: f32,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let anisotropy = self
+            .max_sampler_anisotropy()
+            .map(|max| anisotropy.clamp(1.0, max))
+            .filter(|anisotropy| *anisotropy > 1.0);
+        self.get_sampler(SamplerCreateInfo {
+            anisotropy,
+            ..SamplerCreateInfo::simple_repeat_linear()
+        })
+    }
+
+    /// Writes the pipeline cache to [`VulkanoWinitConfig::pipeline_cache_path`]. This is done
+    /// automatically on [`AppExit`]. Does nothing if no path was configured.
+    pub fn save_pipeline_cache(&self) -> std::io::Result<()> {
+        match &self.pipeline_cache_path {
+            Some(path) => save_pipeline_cache(&self.pipeline_cache, path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Waits for the GPU to go idle and persists the pipeline cache when the app exits
+fn app_exit_system(mut app_exit_events: EventReader<AppExit>, context: Res<BevyVulkanoContext>) {
+    if app_exit_events.iter().last().is_some() {
+        if let Err(e) = context.wait_idle() {
+            warn!("Failed to wait for device idle: {}", e);
+        }
+        if let Err(e) = context.save_pipeline_cache() {
+            warn!("Failed to save pipeline cache: {}", e);
+        }
+    }
 }
 
 /// Plugin that allows replacing Bevy's render backend with Vulkano. See examples for usage.
@@ -94,35 +606,248 @@ pub struct VulkanoWinitPlugin {
     pub window_descriptor: Window,
 }
 
+/// [`VulkanoWinitPlugin`] without the runner: creates the context, windows & renderers but leaves
+/// running the app to the caller, e.g. to embed it in a host application's loop. [`App::run`] only
+/// updates the app once without a runner.
+///
+/// winit allows one event loop per process, so a host that creates its own must build it with
+/// [`UserEvent`] as user event type and insert it as a non send resource before adding the
+/// plugin, which then creates windows on it instead of creating another. Take it back with
+/// [`take_event_loop`] and pass its events to a [`WinitEventHandler`], which updates the app.
+///
+/// ```ignore
+/// let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+/// app.insert_non_send_resource(event_loop)
+///     .add_plugin(VulkanoSetupPlugin::default());
+/// let event_loop = take_event_loop(&mut app);
+/// ```
+#[derive(Default)]
+pub struct VulkanoSetupPlugin {
+    pub window_descriptor: Window,
+}
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Debug messenger which forwards Vulkan messages to bevy's log: errors as errors, warnings as
+/// warnings and info & verbose as debug. Validation messages contain the message id and the
+/// objects involved in their description.
+fn log_debug_messenger_create_info(
+    message_severity: DebugUtilsMessageSeverity,
+    message_type: DebugUtilsMessageType,
+) -> DebugUtilsMessengerCreateInfo {
+    DebugUtilsMessengerCreateInfo {
+        message_severity,
+        message_type,
+        ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(|msg| {
+            let layer = msg.layer_prefix.unwrap_or("unknown");
+            if msg.severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+                error!(target: "vulkan", "[{}] {:?}: {}", layer, msg.ty, msg.description);
+            } else if msg.severity.intersects(DebugUtilsMessageSeverity::WARNING) {
+                warn!(target: "vulkan", "[{}] {:?}: {}", layer, msg.ty, msg.description);
+            } else {
+                debug!(target: "vulkan", "[{}] {:?}: {}", layer, msg.ty, msg.description);
+            }
+        }))
+    }
+}
+
+/// Creates [`BevyVulkanoContext`] from [`VulkanoWinitConfig`] (or default config if none was
+/// inserted) and places the config back as a resource.
+fn create_vulkano_context(app: &mut App) -> BevyVulkanoContext {
+    // Retrieve config, or use default.
+    let mut config = if app
+        .world
+        .get_non_send_resource::<VulkanoWinitConfig>()
+        .is_none()
+    {
+        VulkanoWinitConfig::default()
+    } else {
+        app.world
+            .remove_non_send_resource::<VulkanoWinitConfig>()
+            .unwrap()
+    };
+    let context = BevyVulkanoContext::from_config(&mut config)
+        .unwrap_or_else(|e| panic!("Failed to create Vulkano context: {}", e));
+    // Place config back as resource. Vulkano config will be useless at this point.
+    app.insert_non_send_resource(config);
+    context
+}
+
+impl BevyVulkanoContext {
+    /// Creates the context from `config` on an instance created elsewhere, e.g. by an OpenXR
+    /// runtime requiring specific extensions. The instance must satisfy the surface extension
+    /// requirements of [`VulkanoContext::from_instance_with_memory_allocator`]. To have the
+    /// plugins use such an instance, set [`VulkanoWinitConfig::instance`] instead.
+    pub fn from_instance(
+        instance: Arc<Instance>,
+        mut config: VulkanoWinitConfig,
+    ) -> Result<BevyVulkanoContext, ContextCreationError> {
+        config.instance = Some(instance);
+        BevyVulkanoContext::from_config(&mut config)
+    }
+
+    /// Creates the context from `config`, taking its vulkano config & memory allocator factory
+    fn from_config(
+        config: &mut VulkanoWinitConfig,
+    ) -> Result<BevyVulkanoContext, ContextCreationError> {
+        let mut vulkano_config = std::mem::take(&mut config.vulkano_config);
+        if let Some(device_filter) = &config.device_filter {
+            device_filter.apply(&mut vulkano_config);
+        }
+        vulkano_config.device_features = vulkano_config
+            .device_features
+            .union(&config.required_features);
+        vulkano_config.device_extensions = vulkano_config
+            .device_extensions
+            .union(&config.required_extensions);
+        if let Some(graphics_queue_priorities) = &config.graphics_queue_priorities {
+            vulkano_config.graphics_queue_priorities = graphics_queue_priorities.clone();
+        }
+        if let Some(create_info) = &config.command_buffer_allocator_create_info {
+            vulkano_config.command_buffer_allocator_create_info = create_info.clone();
+        }
+        vulkano_config.context_kind = config.context_kind;
+        if config.queue_family_overrides != QueueFamilyOverrides::default() {
+            vulkano_config.queue_family_overrides = config.queue_family_overrides;
+        }
+        if config.max_frame_latency.is_some() || config.drive_schedule_on_present {
+            let optional_device_extensions = &mut vulkano_config.optional_device_extensions;
+            optional_device_extensions.khr_present_id = true;
+            optional_device_extensions.khr_present_wait = true;
+            let optional_device_features = &mut vulkano_config.optional_device_features;
+            optional_device_features.present_id = true;
+            optional_device_features.present_wait = true;
+        }
+        if config.display_timing {
+            vulkano_config
+                .optional_device_extensions
+                .google_display_timing = true;
+        }
+        if config.full_screen_exclusive && cfg!(target_os = "windows") {
+            vulkano_config
+                .optional_device_extensions
+                .ext_full_screen_exclusive = true;
+        }
+        if config.dynamic_rendering {
+            vulkano_config
+                .optional_device_extensions
+                .khr_dynamic_rendering = true;
+            vulkano_config.optional_device_features.dynamic_rendering = true;
+        }
+        // Nearly universally supported, enabled so that anisotropic samplers work when available
+        vulkano_config.optional_device_features.sampler_anisotropy = true;
+        // Only a hint to the presentation engine, enabled so that present regions work when
+        // available
+        vulkano_config
+            .optional_device_extensions
+            .khr_incremental_present = true;
+        #[cfg(feature = "external_memory")]
+        {
+            let optional_device_extensions = &mut vulkano_config.optional_device_extensions;
+            optional_device_extensions.khr_external_memory = true;
+            optional_device_extensions.khr_external_memory_fd = cfg!(unix);
+            optional_device_extensions.khr_external_memory_win32 = cfg!(windows);
+        }
+        let instance_create_info = &mut vulkano_config.instance_create_info;
+        instance_create_info.enabled_extensions = instance_create_info
+            .enabled_extensions
+            .union(&config.instance_extensions);
+        if let Some(application_info) = &config.application_info {
+            instance_create_info.application_name = Some(application_info.application_name.clone());
+            instance_create_info.application_version = application_info.application_version;
+            instance_create_info.engine_name = application_info.engine_name.clone();
+            instance_create_info.engine_version = application_info.engine_version;
+        }
+        if config.enable_validation {
+            let instance_create_info = &mut vulkano_config.instance_create_info;
+            if !instance_create_info
+                .enabled_layers
+                .iter()
+                .any(|layer| layer == VALIDATION_LAYER)
+            {
+                instance_create_info
+                    .enabled_layers
+                    .push(VALIDATION_LAYER.to_owned());
+            }
+            instance_create_info.enabled_extensions.ext_debug_utils = true;
+            if vulkano_config.debug_create_info.is_none() {
+                vulkano_config.debug_create_info = Some(log_debug_messenger_create_info(
+                    config.debug_message_severity,
+                    config.debug_message_type,
+                ));
+            }
+        }
+        let memory_allocator_factory = config.memory_allocator_factory.take();
+        let vulkano_context = match (config.instance.clone(), memory_allocator_factory) {
+            (Some(instance), Some(memory_allocator_factory)) => {
+                VulkanoContext::from_instance_with_memory_allocator(
+                    instance,
+                    vulkano_config,
+                    memory_allocator_factory,
+                )?
+            }
+            (Some(instance), None) => VulkanoContext::from_instance(instance, vulkano_config)?,
+            (None, Some(memory_allocator_factory)) => {
+                VulkanoContext::with_memory_allocator(vulkano_config, memory_allocator_factory)
+            }
+            (None, None) => VulkanoContext::new(vulkano_config),
+        };
+        if let Some(device_filter) = &config.device_filter {
+            if !device_filter.matches(vulkano_context.device().physical_device()) {
+                warn!(
+                    "Requested device {:?} is not available or lacks required capabilities, \
+                     falling back to {}",
+                    device_filter,
+                    vulkano_context.device_name()
+                );
+            }
+        }
+        let pipeline_cache = match &config.pipeline_cache_path {
+            Some(path) => load_pipeline_cache(vulkano_context.device(), path),
+            None => PipelineCache::empty(vulkano_context.device().clone())
+                .expect("Failed to create pipeline cache"),
+        };
+        let pipeline_cache_path = config.pipeline_cache_path.clone();
+        let sampler_cache = SamplerCache::new(vulkano_context.device().clone());
+        let descriptor_set_cache =
+            DescriptorSetCache::new(vulkano_context.descriptor_set_allocator().clone());
+        Ok(BevyVulkanoContext {
+            context: vulkano_context,
+            pipeline_cache,
+            pipeline_cache_path,
+            sampler_cache,
+            descriptor_set_cache,
+        })
+    }
+}
+
 impl Plugin for VulkanoWinitPlugin {
     fn build(&self, app: &mut App) {
-        // Create event loop, window and renderer (tied together...)
-        let event_loop = EventLoop::new();
+        VulkanoSetupPlugin {
+            window_descriptor: self.window_descriptor.clone(),
+        }
+        .build(app);
+        app.set_runner(winit_runner);
+    }
+}
 
-        // Retrieve config, or use default.
-        let config = if app
+impl Plugin for VulkanoSetupPlugin {
+    fn build(&self, app: &mut App) {
+        // Create event loop, window and renderer (tied together...). Use the host's event loop
+        // if it inserted one, as winit panics when creating a second.
+        let event_loop = app
             .world
-            .get_non_send_resource::<VulkanoWinitConfig>()
-            .is_none()
-        {
-            VulkanoWinitConfig::default()
-        } else {
-            app.world
-                .remove_non_send_resource::<VulkanoWinitConfig>()
-                .unwrap()
-        };
+            .remove_non_send_resource::<EventLoop<UserEvent>>()
+            .unwrap_or_else(|| EventLoopBuilder::with_user_event().build());
 
-        // Create vulkano context using the vulkano config from config
-        let VulkanoWinitConfig {
-            vulkano_config, ..
-        } = config;
-        let vulkano_context = VulkanoContext::new(vulkano_config);
-        // Place config back as resource. Vulkano config will be useless at this point.
-        let new_config = VulkanoWinitConfig {
-            vulkano_config: VulkanoConfig::default(),
-            ..config
-        };
-        app.insert_non_send_resource(new_config);
+        let vulkano_context = create_vulkano_context(app);
+        assert_eq!(
+            app.world
+                .non_send_resource::<VulkanoWinitConfig>()
+                .context_kind,
+            ContextKind::Graphics,
+            "Windows can't be created with a compute only context, use HeadlessVulkanoPlugin"
+        );
 
         let window_plugin = bevy::window::WindowPlugin {
             // This lib controls exiting all on close. (true)
@@ -135,25 +860,98 @@ impl Plugin for VulkanoWinitPlugin {
         app.add_plugin(window_plugin)
             .init_non_send_resource::<BevyVulkanoWindows>()
             .init_resource::<PipelineSyncData>()
-            .insert_resource(BevyVulkanoContext {
-                context: vulkano_context,
-            });
+            .add_event::<DeviceLost>()
+            .add_event::<RawWinitWindowEvent>()
+            .add_event::<UserEvent>()
+            .add_event::<FramePresented>()
+            .add_event::<SwapchainRecreated>()
+            .insert_resource(UserEventSender::new(event_loop.create_proxy()))
+            .insert_non_send_resource(event_loop.create_proxy())
+            .insert_resource(SharedResources::new(
+                vulkano_context.context.device().clone(),
+            ))
+            .insert_resource(vulkano_context)
+            .add_system(frame_presented_system.in_base_set(CoreSet::Last))
+            .add_system(swapchain_recreated_system.in_base_set(CoreSet::Last))
+            .add_system(app_exit_system.in_base_set(CoreSet::Last));
 
         // Create initial window
         handle_initial_window_events(&mut app.world, &event_loop);
 
         app.insert_non_send_resource(event_loop)
-            .set_runner(winit_runner)
             .add_systems(
                 (update_on_resize_system, exit_on_window_close_system)
                     .in_base_set(CoreSet::PreUpdate),
             )
-            .add_system(change_window.in_base_set(CoreSet::PostUpdate));
+            .configure_sets(
+                (
+                    VulkanoRenderSet::PreRender,
+                    VulkanoRenderSet::Render,
+                    VulkanoRenderSet::PostRender,
+                )
+                    .chain()
+                    .in_base_set(CoreSet::PostUpdate),
+            )
+            .add_system(
+                change_window
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(VulkanoRenderSet::PreRender),
+            );
 
-        // Add gui begin frame system
-        #[cfg(feature = "gui")]
+        if app
+            .world
+            .non_send_resource::<VulkanoWinitConfig>()
+            .auto_present
         {
-            app.add_system_to_stage(CoreStage::PreUpdate, begin_egui_frame_system);
+            // Submitted frames are only consumed by post_render_system, so the queue isn't
+            // available otherwise
+            let context = &app.world.resource::<BevyVulkanoContext>().context;
+            let frame_submissions = FrameSubmissionQueue::new(
+                context.device().clone(),
+                context.command_buffer_allocator().clone(),
+            );
+            app.insert_resource(frame_submissions)
+                .add_system(pre_render_system.in_set(VulkanoRenderSet::PreRender))
+                .add_system(post_render_system.in_set(VulkanoRenderSet::PostRender));
+        }
+    }
+}
+
+/// Plugin that sets up Vulkano without winit: no event loop, surfaces or swapchains are created.
+/// [`BevyVulkanoWindows`] stays empty and you render into your own offscreen images, reading them
+/// back via transfers. The schedule is run in a plain loop until [`AppExit`] is sent. Useful in CI
+/// and for offscreen image generation where no display server is available.
+#[derive(Default)]
+pub struct HeadlessVulkanoPlugin;
+
+impl Plugin for HeadlessVulkanoPlugin {
+    fn build(&self, app: &mut App) {
+        let vulkano_context = create_vulkano_context(app);
+
+        app.init_non_send_resource::<BevyVulkanoWindows>()
+            .init_resource::<PipelineSyncData>()
+            .add_event::<DeviceLost>()
+            .insert_resource(SharedResources::new(
+                vulkano_context.context.device().clone(),
+            ))
+            .insert_resource(vulkano_context)
+            .add_system(app_exit_system.in_base_set(CoreSet::Last))
+            .set_runner(headless_runner);
+    }
+}
+
+/// Runs the app schedule in a loop until [`AppExit`] is sent. Used by [`HeadlessVulkanoPlugin`].
+pub fn headless_runner(mut app: App) {
+    let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
+
+    trace!("Entering headless loop");
+
+    loop {
+        app.update();
+        if let Some(app_exit_events) = app.world.get_resource::<Events<AppExit>>() {
+            if app_exit_event_reader.iter(app_exit_events).next().is_some() {
+                break;
+            }
         }
     }
 }
@@ -170,25 +968,325 @@ fn update_on_resize_system(
 
     for id in changed_window_ids {
         #[cfg(not(feature = "gui"))]
-        if let Some(window_renderer) = windows.get_window_renderer_mut(id) {
+        let window_renderer = windows.get_window_renderer_mut(id);
+        #[cfg(feature = "gui")]
+        let window_renderer = windows.get_window_renderer_mut(id).map(|(r, _)| r);
+        if let Some(window_renderer) = window_renderer {
             // Swap chain will be resized at the beginning of next frame. But user should update pipeline frame data
             window_renderer.resize();
-            // Insert or update pipeline frame data
-            pipeline_data.add(SyncData {
-                window_entity: id,
-                before: None,
-                after: None,
-            });
+            // Insert or update pipeline frame data. Smooth resizes keep the frame fences
+            if !window_renderer.smooth_resize() || pipeline_data.get(id).is_none() {
+                pipeline_data.add(SyncData::new(id));
+            }
         }
+    }
+}
+
+/// Sent when the logical device was lost (e.g. on a driver reset or GPU hotplug) during acquire
+/// or present of [`pre_render_system`] & [`post_render_system`]. If you acquire & present
+/// yourself, check for [`VulkanoRendererError::DeviceLost`] instead.
+///
+/// Vulkano objects created from a lost device can't be used anymore, and the device isn't
+/// recreated automatically. Save state & restart (or exit) the app in response.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceLost {
+    /// Window whose frame failed
+    pub window: Entity,
+}
+
+/// Sent for each frame successfully presented to a window, whether by [`post_render_system`] or
+/// your own present calls. Events are sent at the end of the frame, so systems read them in the
+/// next frame.
+#[derive(Debug, Clone, Copy)]
+pub struct FramePresented {
+    pub window: Entity,
+    pub window_id: WindowId,
+    /// Index of the present since the window was created, counting successful presents only,
+    /// see [`VulkanoWindowRenderer::frames_presented`]
+    pub frame_index: u64,
+    /// When the present was queued. This is a best-effort CPU time: the image reaches the display
+    /// later. For actual display times, see
+    /// [`VulkanoWindowRenderer::past_presentation_timings`].
+    pub present_time: Instant,
+}
+
+/// Sent when a window's swapchain was recreated, e.g. after a resize, so that resources created
+/// from its images can be rebuilt. Events are sent at the end of the frame, after the frame
+/// rendered with the new swapchain. To rebuild before rendering, compare
+/// [`VulkanoWindowRenderer::swapchain_generation`] after acquiring instead.
+#[derive(Debug, Clone, Copy)]
+pub struct SwapchainRecreated {
+    pub window: Entity,
+    pub window_id: WindowId,
+    /// See [`VulkanoWindowRenderer::swapchain_generation`]. Generations in between are skipped if
+    /// the swapchain was recreated several times during a frame.
+    pub generation: u64,
+    pub image_count: usize,
+    pub image_size: [u32; 2],
+}
+
+/// A winit window event, forwarded as is for events bevy doesn't model, e.g. `WindowEvent::Ime` or
+/// `WindowEvent::TouchpadMagnify`. Sent in addition to the translated bevy events, for every
+/// window event except `ScaleFactorChanged` (which can't outlive the event loop callback) and
+/// those consumed by the gui.
+#[derive(Debug)]
+pub struct RawWinitWindowEvent {
+    pub window: Entity,
+    pub window_id: WindowId,
+    pub event: WindowEvent<'static>,
+}
+
+/// Custom event sent from any thread with [`UserEventSender`], e.g. by a decoder thread when a new
+/// frame is ready. It wakes the event loop and is read as a bevy event in the next update.
+/// Downcast it to the type that was sent.
+#[derive(Debug)]
+pub struct UserEvent(Box<dyn Any + Send + Sync>);
+
+impl UserEvent {
+    pub fn new<T: Any + Send + Sync>(value: T) -> UserEvent {
+        UserEvent(Box::new(value))
+    }
+
+    /// Whether the sent value is a `T`
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    /// The sent value, if it's a `T`
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+/// Sends [`UserEvent`]s to the winit event loop owned by [`VulkanoWinitPlugin`]. Clone it into
+/// background threads: it's `Send` & `Sync`, and sending only locks a proxy briefly, so it can be
+/// used from any number of threads. Sending fails once the event loop has exited.
+#[derive(Resource)]
+pub struct UserEventSender(Mutex<EventLoopProxy<UserEvent>>);
+
+impl UserEventSender {
+    fn new(proxy: EventLoopProxy<UserEvent>) -> UserEventSender {
+        UserEventSender(Mutex::new(proxy))
+    }
+
+    /// Sends `value` as a [`UserEvent`], waking the event loop.
+    pub fn send<T: Any + Send + Sync>(&self, value: T) -> Result<(), EventLoopClosed<UserEvent>> {
+        self.0.lock().unwrap().send_event(UserEvent::new(value))
+    }
+}
+
+impl Clone for UserEventSender {
+    fn clone(&self) -> Self {
+        UserEventSender::new(self.0.lock().unwrap().clone())
+    }
+}
+
+/// Sets in `CoreSet::PostUpdate` for rendering to windows without one big system doing all the
+/// work. With [`VulkanoWinitConfig::auto_present`], [`pre_render_system`] acquires each window's
+/// frame in `PreRender`, storing the future in [`SyncData::before`] of [`PipelineSyncData`]. Your
+/// systems in `Render` take that future, chain their work and store the result in
+/// [`SyncData::after`]. [`post_render_system`] presents `after` (or clears the frame if nothing was
+/// chained) in `PostRender`.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VulkanoRenderSet {
+    PreRender,
+    Render,
+    PostRender,
+}
+
+/// Acquires the next frame of each window, see [`VulkanoRenderSet`]. Minimized windows get no
+/// future, skip rendering to them.
+pub fn pre_render_system(
+    mut pipeline_data: ResMut<PipelineSyncData>,
+    mut windows: NonSendMut<BevyVulkanoWindows>,
+    mut device_lost_events: EventWriter<DeviceLost>,
+) {
+    for sync_data in pipeline_data.iter_mut() {
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = windows.get_window_renderer_mut(sync_data.window_entity);
         #[cfg(feature = "gui")]
-        if let Some((window_renderer, _)) = windows.get_window_renderer_mut(id) {
-            // Swap chain will be resized at the beginning of next frame. But user should update pipeline frame data
-            window_renderer.resize();
-            // Insert or update pipeline frame data
-            pipeline_data.add(SyncData {
-                window_id: id,
-                before: None,
-                after: None,
+        let window_renderer = windows
+            .get_window_renderer_mut(sync_data.window_entity)
+            .map(|(window_renderer, _)| window_renderer);
+        sync_data.after = None;
+        sync_data.content = FrameContent::Auto;
+        sync_data.before = match window_renderer.map(|r| (r.acquire(), r)) {
+            Some((Ok(future), window_renderer)) => {
+                sync_data.begin_frame(
+                    window_renderer.current_frame_index(),
+                    window_renderer.frames_in_flight(),
+                );
+                Some(future)
+            }
+            // Losing exclusive full-screen is logged by the renderer, which falls back to borderless.
+            // Timeouts skip the frame, see `VulkanoWinitConfig::acquire_timeout`
+            Some((Err(VulkanoRendererError::WindowMinimized), _))
+            | Some((Err(VulkanoRendererError::FullScreenExclusiveLost), _))
+            | Some((Err(VulkanoRendererError::Timeout), _))
+            | None => None,
+            Some((Err(VulkanoRendererError::DeviceLost), _)) => {
+                error!("Device lost when starting frame");
+                device_lost_events.send(DeviceLost {
+                    window: sync_data.window_entity,
+                });
+                None
+            }
+            Some((Err(e), _)) => {
+                error!("Failed to start frame: {}", e);
+                None
+            }
+        };
+    }
+}
+
+/// Presents the frame of each window acquired by [`pre_render_system`], see
+/// [`VulkanoRenderSet`]. Frames nothing was chained to, or whose [`SyncData::content`] is
+/// [`FrameContent::Clear`], are cleared with [`VulkanoWindowRenderer::clear_color`]. With the
+/// `gui` feature, gui is drawn over the frame before presenting. Each frame's fence is stored in
+/// its [`SyncData`], see [`SyncData::wait_for_frame`].
+pub fn post_render_system(
+    mut pipeline_data: ResMut<PipelineSyncData>,
+    mut windows: NonSendMut<BevyVulkanoWindows>,
+    frame_submissions: Option<Res<FrameSubmissionQueue>>,
+    mut device_lost_events: EventWriter<DeviceLost>,
+) {
+    for sync_data in pipeline_data.iter_mut() {
+        let window = sync_data.window_entity;
+        let before = sync_data.before.take();
+        let after = sync_data.after.take();
+        let content = sync_data.content;
+        let frame_submissions = frame_submissions.as_deref();
+        #[cfg(not(feature = "gui"))]
+        let result = windows
+            .get_window_renderer_mut(window)
+            .and_then(|window_renderer| {
+                let future = frame_future(
+                    window_renderer,
+                    window,
+                    before,
+                    after,
+                    content,
+                    frame_submissions,
+                )?;
+                Some(window_renderer.present_with_fence(future))
+            });
+        #[cfg(feature = "gui")]
+        let result = windows
+            .get_window_renderer_mut(window)
+            .and_then(|(window_renderer, gui)| {
+                let future = frame_future(
+                    window_renderer,
+                    window,
+                    before,
+                    after,
+                    content,
+                    frame_submissions,
+                )?;
+                let after_gui = gui.draw_on_image(future, window_renderer.swapchain_image_view());
+                Some(window_renderer.present_with_fence(after_gui))
+            });
+        if let Some(result) = &result {
+            sync_data.set_frame_fence(result.as_ref().ok().cloned());
+        }
+        match result {
+            Some(Err(VulkanoRendererError::DeviceLost)) => {
+                error!("Device lost when presenting frame");
+                device_lost_events.send(DeviceLost {
+                    window: sync_data.window_entity,
+                });
+            }
+            Some(Err(VulkanoRendererError::FullScreenExclusiveLost)) => {}
+            Some(Err(e)) => error!("Failed to present frame: {}", e),
+            _ => {}
+        }
+    }
+}
+
+/// Future of a window's frame to present: the frame rendered by the user if any, otherwise a
+/// cleared frame, with the latest [`FrameSubmissionQueue`] image blitted over it. `None` if the
+/// frame wasn't acquired.
+fn frame_future(
+    window_renderer: &VulkanoWindowRenderer,
+    window: Entity,
+    before: Option<Box<dyn GpuFuture>>,
+    after: Option<Box<dyn GpuFuture>>,
+    content: FrameContent,
+    frame_submissions: Option<&FrameSubmissionQueue>,
+) -> Option<Box<dyn GpuFuture>> {
+    let composite = |future| match frame_submissions {
+        Some(frame_submissions) => frame_submissions.composite(window, window_renderer, future),
+        None => Err(future),
+    };
+    let rendered = match content {
+        FrameContent::Auto => after.is_some(),
+        FrameContent::Provided => true,
+        FrameContent::Clear => false,
+    };
+    let future = match after {
+        Some(after) => after,
+        None => before?,
+    };
+    // A submitted frame is blitted over the whole image, so it needs no clear
+    Some(if rendered {
+        composite(future).unwrap_or_else(|future| future)
+    } else {
+        composite(future).unwrap_or_else(|future| window_renderer.clear_swapchain_image(future))
+    })
+}
+
+/// Sends [`FramePresented`] events for the presents of each window during this frame
+fn frame_presented_system(
+    mut windows: NonSendMut<BevyVulkanoWindows>,
+    mut frame_presented_events: EventWriter<FramePresented>,
+) {
+    let windows = &mut *windows;
+    #[cfg(not(feature = "gui"))]
+    let renderers = windows.windows.iter_mut();
+    #[cfg(feature = "gui")]
+    let renderers = windows
+        .windows
+        .iter_mut()
+        .map(|(window_id, (window_renderer, _))| (window_id, window_renderer));
+    for (window_id, window_renderer) in renderers {
+        let Some(&window) = windows.winit_to_entity.get(window_id) else {
+            continue;
+        };
+        for (frame_index, present_time) in window_renderer.take_unreported_presents() {
+            frame_presented_events.send(FramePresented {
+                window,
+                window_id: *window_id,
+                frame_index,
+                present_time,
+            });
+        }
+    }
+}
+
+/// Sends [`SwapchainRecreated`] events for the windows whose swapchain was recreated during this
+/// frame
+fn swapchain_recreated_system(
+    mut windows: NonSendMut<BevyVulkanoWindows>,
+    mut swapchain_recreated_events: EventWriter<SwapchainRecreated>,
+) {
+    let windows = &mut *windows;
+    #[cfg(not(feature = "gui"))]
+    let renderers = windows.windows.iter_mut();
+    #[cfg(feature = "gui")]
+    let renderers = windows
+        .windows
+        .iter_mut()
+        .map(|(window_id, (window_renderer, _))| (window_id, window_renderer));
+    for (window_id, window_renderer) in renderers {
+        let Some(&window) = windows.winit_to_entity.get(window_id) else {
+            continue;
+        };
+        if let Some(generation) = window_renderer.take_unreported_swapchain_generation() {
+            swapchain_recreated_events.send(SwapchainRecreated {
+                window,
+                window_id: *window_id,
+                generation,
+                image_count: window_renderer.swapchain_image_views().len(),
+                image_size: window_renderer.swapchain_image_size(),
             });
         }
     }
@@ -444,9 +1542,9 @@ fn change_window(world: &mut World) {
     }
 }
 
-fn run<F>(event_loop: EventLoop<()>, event_handler: F) -> !
+fn run<F>(event_loop: EventLoop<UserEvent>, event_handler: F) -> !
 where
-    F: 'static + FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: 'static + FnMut(Event<'_, UserEvent>, &EventLoopWindowTarget<UserEvent>, &mut ControlFlow),
 {
     event_loop.run(event_handler)
 }
@@ -460,9 +1558,9 @@ where
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-fn run_return<F>(event_loop: &mut EventLoop<()>, event_handler: F) -> i32
+fn run_return<F>(event_loop: &mut EventLoop<UserEvent>, event_handler: F) -> i32
 where
-    F: FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: FnMut(Event<'_, UserEvent>, &EventLoopWindowTarget<UserEvent>, &mut ControlFlow),
 {
     use winit::platform::run_return::EventLoopExtRunReturn;
     event_loop.run_return(event_handler)
@@ -477,25 +1575,21 @@ where
     target_os = "netbsd",
     target_os = "openbsd"
 )))]
-fn run_return<F>(_event_loop: &mut EventLoop<()>, _event_handler: F)
+fn run_return<F>(_event_loop: &mut EventLoop<UserEvent>, _event_handler: F)
 where
-    F: FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: FnMut(Event<'_, UserEvent>, &EventLoopWindowTarget<UserEvent>, &mut ControlFlow),
 {
     panic!("Run return is not supported on this platform!")
 }
 
+/// Runs the app in the winit event loop created by [`VulkanoWinitPlugin`]
 pub fn winit_runner(app: App) {
     winit_runner_with(app);
 }
 
 pub fn winit_runner_with(mut app: App) {
-    let mut event_loop = app
-        .world
-        .remove_non_send_resource::<EventLoop<()>>()
-        .unwrap();
-    let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
-    app.world
-        .insert_non_send_resource(event_loop.create_proxy());
+    let mut event_loop = take_event_loop(&mut app);
+    let mut event_handler = WinitEventHandler::new(&mut app);
 
     trace!("Entering winit event loop");
 
@@ -504,15 +1598,84 @@ pub fn winit_runner_with(mut app: App) {
         .get_non_send_resource::<VulkanoWinitConfig>()
         .map_or(false, |config| config.return_from_run);
 
-    let mut active = true;
-
-    let event_handler = move |event: Event<()>,
-                              event_loop: &EventLoopWindowTarget<()>,
+    let event_handler = move |event: Event<UserEvent>,
+                              event_loop: &EventLoopWindowTarget<UserEvent>,
                               control_flow: &mut ControlFlow| {
+        event_handler.handle_event(&mut app, event, event_loop, control_flow);
+    };
+    if should_return_from_run {
+        let _exit_code = run_return(&mut event_loop, event_handler);
+    } else {
+        run(event_loop, event_handler);
+    }
+}
+
+/// Removes the event loop windows & renderers were created with from the app, to run it yourself
+/// with [`VulkanoSetupPlugin`]. This is the host's event loop if it inserted one before adding the
+/// plugin.
+///
+/// # Panics
+///
+/// - Panics if the plugin wasn't added or the event loop was already taken
+pub fn take_event_loop(app: &mut App) -> EventLoop<UserEvent> {
+    app.world
+        .remove_non_send_resource::<EventLoop<UserEvent>>()
+        .expect("No event loop, add VulkanoSetupPlugin before taking it")
+}
+
+/// Translates winit events into bevy events & windows, creates windows spawned at runtime and
+/// updates the app once per `MainEventsCleared`. [`winit_runner`] calls it from the event loop.
+/// When embedding in a loop you run yourself with [`VulkanoSetupPlugin`], pass it every event of
+/// the event loop from [`take_event_loop`]:
+///
+/// ```ignore
+/// let event_loop = take_event_loop(&mut app);
+/// let mut handler = WinitEventHandler::new(&mut app);
+/// event_loop.run(move |event, event_loop, control_flow| {
+///     // Host code handles the event here too
+///     handler.handle_event(&mut app, event, event_loop, control_flow);
+/// });
+/// ```
+pub struct WinitEventHandler {
+    app_exit_event_reader: ManualEventReader<AppExit>,
+    active: bool,
+    drive_schedule_on_present: bool,
+    create_window_state: SystemState<CreateWindowParams<'static, 'static>>,
+}
+
+impl WinitEventHandler {
+    pub fn new(app: &mut App) -> WinitEventHandler {
+        let drive_schedule_on_present = app
+            .world
+            .get_non_send_resource::<VulkanoWinitConfig>()
+            .is_some_and(|config| config.drive_schedule_on_present);
+        WinitEventHandler {
+            app_exit_event_reader: ManualEventReader::default(),
+            active: true,
+            drive_schedule_on_present,
+            create_window_state: SystemState::from_world(&mut app.world),
+        }
+    }
+
+    /// Whether the app is updated on `MainEventsCleared`, false between `Suspended` & `Resumed`
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Handles `event`. Sets `control_flow` to `Poll`, or `Exit` once [`AppExit`] was sent, which
+    /// host loops may override afterwards.
+    pub fn handle_event(
+        &mut self,
+        app: &mut App,
+        event: Event<UserEvent>,
+        event_loop: &EventLoopWindowTarget<UserEvent>,
+        control_flow: &mut ControlFlow,
+    ) {
         *control_flow = ControlFlow::Poll;
 
         if let Some(app_exit_events) = app.world.get_resource_mut::<Events<AppExit>>() {
-            if app_exit_event_reader
+            if self
+                .app_exit_event_reader
                 .iter(&app_exit_events)
                 .next()
                 .is_some()
@@ -534,18 +1697,19 @@ pub fn winit_runner_with(mut app: App) {
                     window_id: winit_window_id,
                     ..
                 } => {
-                    let world = app.world.cell();
-                    let mut vulkano_winit_windows = world
+                    let mut vulkano_winit_windows = app
+                        .world
                         .get_non_send_resource_mut::<BevyVulkanoWindows>()
                         .unwrap();
-                    let window_id = if let Some(window_id) =
-                        vulkano_winit_windows.get_window_id(*winit_window_id)
+                    let window_entity = if let Some(window_entity) =
+                        vulkano_winit_windows.get_window_entity(*winit_window_id)
                     {
-                        window_id
+                        window_entity
                     } else {
                         return;
                     };
-                    if let Some((_, gui)) = vulkano_winit_windows.get_window_renderer_mut(window_id)
+                    if let Some((_, gui)) =
+                        vulkano_winit_windows.get_window_renderer_mut(window_entity)
                     {
                         // Update egui with the window event. If false, we should skip the event in bevy
                         skip_window_event = gui.update(window_event);
@@ -620,7 +1784,7 @@ pub fn winit_runner_with(mut app: App) {
             // Main events...
             match event {
                 event::Event::WindowEvent {
-                    event,
+                    mut event,
                     window_id: winit_window_id,
                     ..
                 } => {
@@ -644,7 +1808,7 @@ pub fn winit_runner_with(mut app: App) {
                     )> = SystemState::from_world(&mut app.world);
 
                     let (
-                        vulkano_winit_windows,
+                        mut vulkano_winit_windows,
                         mut windows,
                         mut resize_events,
                         mut focused_events,
@@ -774,7 +1938,7 @@ pub fn winit_runner_with(mut app: App) {
                         }
                         WindowEvent::ScaleFactorChanged {
                             scale_factor,
-                            new_inner_size,
+                            ref mut new_inner_size,
                         } => {
                             window_backend_scale_factor_changed_events.send(
                                 WindowBackendScaleFactorChanged {
@@ -792,11 +1956,9 @@ pub fn winit_runner_with(mut app: App) {
                                 // Otherwise, use the OS suggested size
                                 // We have already told the OS about our resize constraints, so
                                 // the new_inner_size should take those into account
-                                // *new_inner_size = winit::dpi::LogicalSize::new(
-                                //     window.requested_width(),
-                                //     window.requested_height(),
-                                // )
-                                // .to_physical::<u32>(forced_factor);
+                                **new_inner_size =
+                                    winit::dpi::LogicalSize::new(window.width(), window.height())
+                                        .to_physical::<u32>(forced_factor);
                             } else if approx::relative_ne!(new_factor, prior_factor) {
                                 window_scale_factor_changed_events.send(WindowScaleFactorChanged {
                                     window: window_entity,
@@ -819,7 +1981,20 @@ pub fn winit_runner_with(mut app: App) {
                             window.resolution.set_physical_resolution(
                                 new_inner_size.width,
                                 new_inner_size.height,
-                            )
+                            );
+
+                            // The physical size changes even if the logical size (and so
+                            // `WindowResized`) doesn't, so recreate the swapchain at the new size
+                            #[cfg(not(feature = "gui"))]
+                            let window_renderer =
+                                vulkano_winit_windows.get_window_renderer_mut(window_entity);
+                            #[cfg(feature = "gui")]
+                            let window_renderer = vulkano_winit_windows
+                                .get_window_renderer_mut(window_entity)
+                                .map(|(window_renderer, _)| window_renderer);
+                            if let Some(window_renderer) = window_renderer {
+                                window_renderer.resize();
+                            }
                         }
                         WindowEvent::Focused(focused) => {
                             window.focused = focused;
@@ -828,16 +2003,16 @@ pub fn winit_runner_with(mut app: App) {
                                 focused,
                             });
                         }
-                        WindowEvent::DroppedFile(path_buf) => {
+                        WindowEvent::DroppedFile(ref path_buf) => {
                             file_drag_and_drop_events.send(FileDragAndDrop::DroppedFile {
                                 window: window_entity,
-                                path_buf,
+                                path_buf: path_buf.clone(),
                             });
                         }
-                        WindowEvent::HoveredFile(path_buf) => {
+                        WindowEvent::HoveredFile(ref path_buf) => {
                             file_drag_and_drop_events.send(FileDragAndDrop::HoveredFile {
                                 window: window_entity,
-                                path_buf,
+                                path_buf: path_buf.clone(),
                             });
                         }
                         WindowEvent::HoveredFileCancelled => {
@@ -856,6 +2031,16 @@ pub fn winit_runner_with(mut app: App) {
                         }
                         _ => {}
                     }
+
+                    // Forward the event as is too, for what isn't translated above (IME, file
+                    // hover, touchpad gestures...)
+                    if let Some(event) = event.to_static() {
+                        app.world.send_event(RawWinitWindowEvent {
+                            window: window_entity,
+                            window_id: winit_window_id,
+                            event,
+                        });
+                    }
                 }
                 event::Event::DeviceEvent {
                     event:
@@ -870,39 +2055,73 @@ pub fn winit_runner_with(mut app: App) {
                         delta: Vec2::new(delta.0 as f32, delta.1 as f32),
                     });
                 }
+                event::Event::UserEvent(user_event) => {
+                    app.world.send_event(user_event);
+                }
                 event::Event::Suspended => {
-                    active = false;
+                    self.active = false;
                 }
                 event::Event::Resumed => {
-                    active = true;
+                    self.active = true;
                 }
                 event::Event::MainEventsCleared => {
-                    handle_create_window_events(&mut app.world, event_loop);
-                    if active {
+                    handle_create_window_events(
+                        &mut app.world,
+                        event_loop,
+                        &mut self.create_window_state,
+                    );
+                    if self.active {
+                        if self.drive_schedule_on_present {
+                            wait_for_last_presents(&mut app.world);
+                        }
                         app.update();
                     }
                 }
                 _ => (),
             }
         }
-    };
-    if should_return_from_run {
-        let _exit_code = run_return(&mut event_loop, event_handler);
-    } else {
-        run(event_loop, event_handler);
     }
 }
 
-fn handle_create_window_events(world: &mut World, event_loop: &EventLoopWindowTarget<()>) {
-    let mut handle_create_window_events_state: SystemState<(
-        Commands,
-        Res<BevyVulkanoContext>,
-        NonSend<VulkanoWinitConfig>,
-        NonSendMut<BevyVulkanoWindows>,
-        Query<(Entity, &mut Window), Added<Window>>,
-        EventWriter<WindowCreated>,
-    )> = SystemState::from_world(world);
+/// Waits for the latest present of each window, see
+/// [`VulkanoWinitConfig::drive_schedule_on_present`]
+fn wait_for_last_presents(world: &mut World) {
+    let mut windows = world.non_send_resource_mut::<BevyVulkanoWindows>();
+    #[cfg(not(feature = "gui"))]
+    for (_, window_renderer) in windows.iter_mut() {
+        window_renderer.wait_for_last_present();
+    }
+    #[cfg(feature = "gui")]
+    for (_, (window_renderer, _)) in windows.iter_mut() {
+        window_renderer.wait_for_last_present();
+    }
+}
 
+type CreateWindowParams<'w, 's> = (
+    Commands<'w, 's>,
+    Res<'w, BevyVulkanoContext>,
+    NonSend<'w, VulkanoWinitConfig>,
+    NonSendMut<'w, BevyVulkanoWindows>,
+    Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Window,
+            Option<&'static PreferredSurfaceFormats>,
+        ),
+        Added<Window>,
+    >,
+    EventWriter<'w, WindowCreated>,
+);
+
+/// Creates winit windows & renderers for [`Window`] entities spawned at runtime. The system state
+/// must persist between calls for `Added<Window>` to only see new windows.
+fn handle_create_window_events(
+    world: &mut World,
+    event_loop: &EventLoopWindowTarget<UserEvent>,
+    create_window_state: &mut SystemState<CreateWindowParams>,
+) {
     let (
         mut commands,
         vulkano_context,
@@ -910,40 +2129,40 @@ fn handle_create_window_events(world: &mut World, event_loop: &EventLoopWindowTa
         mut vulkano_winit_windows,
         mut new_windows,
         mut event_writer,
-    ) = handle_create_window_events_state.get_mut(world);
+    ) = create_window_state.get_mut(world);
 
-    //TODO: Query<(Entity, &mut Window), Added<Window>> is suppose to react to only created windows, but it keeps
-    // triggering each frame causing a window to be created constantly
-
-    for (entity, create_window) in new_windows.iter_mut() {
-        println!("Creating window: {:?}", create_window);
-
-        // let window = vulkano_winit_windows.create_window(
-        //     &mut commands,
-        //     event_loop,
-        //     entity,
-        //     create_window,
-        //     &vulkano_context.context,
-        //     &vulkano_config,
-        // );
+    for (entity, mut window, preferred_surface_formats) in new_windows.iter_mut() {
+        // Initial windows are already created at plugin build
+        if vulkano_winit_windows.get_winit_window(entity).is_some() {
+            continue;
+        }
+        info!("Creating window {:?}: {}", entity, window.title);
 
-        // commands.spawn(window);
+        *window = vulkano_winit_windows.create_window(
+            &mut commands,
+            event_loop,
+            entity,
+            &window,
+            preferred_surface_formats,
+            &vulkano_context.context,
+            &vulkano_config,
+        );
 
-        // event_writer.send(WindowCreated {
-        //     window: entity,
-        // });
+        event_writer.send(WindowCreated {
+            window: entity,
+        });
     }
 
-    handle_create_window_events_state.apply(world);
+    create_window_state.apply(world);
 }
 
-fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<()>) {
+fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<UserEvent>) {
     let mut handle_initial_window_events_state: SystemState<(
         Commands,
         Res<BevyVulkanoContext>,
         NonSend<VulkanoWinitConfig>,
         NonSendMut<BevyVulkanoWindows>,
-        Query<(Entity, &Window)>,
+        Query<(Entity, &Window, Option<&PreferredSurfaceFormats>)>,
         EventWriter<WindowCreated>,
     )> = SystemState::from_world(world);
 
@@ -956,17 +2175,18 @@ fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<()>) {
         mut event_writer,
     ) = handle_initial_window_events_state.get_mut(world);
 
-    for (entity, window) in new_windows.iter() {
+    for (entity, window, preferred_surface_formats) in new_windows.iter() {
         let window = vulkano_winit_windows.create_window(
             &mut commands,
             event_loop,
             entity,
             window,
+            preferred_surface_formats,
             &vulkano_context.context,
             &vulkano_config,
         );
 
-        commands.spawn(window);
+        commands.entity(entity).insert(window);
 
         event_writer.send(WindowCreated {
             window: entity,
@@ -983,10 +2203,17 @@ pub fn exit_on_window_close_system(
     mut window_close_events: EventWriter<WindowClosed>,
     primary_window_entity: Query<Entity, With<PrimaryWindow>>,
     mut window_close_requested_events: EventReader<WindowCloseRequested>,
+    mut removed_windows: RemovedComponents<Window>,
 ) {
-    for event in window_close_requested_events.iter() {
+    // Windows are closed either by request, or by despawning their entity
+    let closed_windows: Vec<Entity> = window_close_requested_events
+        .iter()
+        .map(|event| event.window)
+        .chain(removed_windows.iter())
+        .collect();
+    for window in closed_windows {
         let (app_close, window_close) = close_window(
-            event.window,
+            window,
             &mut windows,
             primary_window_entity.get_single(),
             &mut pipeline_data,
@@ -996,7 +2223,7 @@ pub fn exit_on_window_close_system(
             app_exit_events.send(AppExit);
         } else if window_close {
             window_close_events.send(WindowClosed {
-                window: event.window,
+                window,
             })
         }
     }
@@ -1026,14 +2253,22 @@ fn close_window(
         return (false, false);
     };
 
+    // Drop pending futures first, they may hold swapchain images
     pipeline_data.remove(window_entity);
-    windows.windows.remove(&winit_id);
-    (false, true)
-}
-
-#[cfg(feature = "gui")]
-pub fn begin_egui_frame_system(mut vulkano_windows: NonSendMut<BevyVulkanoWindows>) {
-    for (_, (_, g)) in vulkano_windows.windows.iter_mut() {
-        g.begin_frame();
+    if let Some(window) = windows.windows.remove(&winit_id) {
+        #[cfg(not(feature = "gui"))]
+        let mut window_renderer = window;
+        #[cfg(feature = "gui")]
+        let (mut window_renderer, gui) = window;
+        // Nothing may be freed while the GPU is still using it
+        window_renderer.wait_for_frames();
+        #[cfg(feature = "gui")]
+        drop(gui);
+        // Swapchain is dropped before the surface and winit window it references
+        drop(window_renderer);
     }
+    windows.entity_to_winit.remove(&window_entity);
+    windows.winit_to_entity.remove(&winit_id);
+    windows.cursor_states.remove(&window_entity);
+    (false, true)
 }
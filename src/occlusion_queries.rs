@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder},
+    query::{
+        QueryControlFlags, QueryPool, QueryPoolCreateInfo, QueryPoolCreationError,
+        QueryResultFlags, QueryType,
+    },
+};
+
+use crate::BevyVulkanoContext;
+
+/// Counts samples passing depth & stencil tests for draws, e.g. to skip drawing objects that were
+/// hidden last frame.
+///
+/// The query pool holds a region of `queries_per_frame` queries per frame in flight, so that the
+/// results of previous frames stay readable while the current frame is recorded. Call
+/// [`OcclusionQueries::begin_frame`] with
+/// [`VulkanoWindowRenderer::current_frame_index`](crate::VulkanoWindowRenderer::current_frame_index)
+/// before any query of the frame, record draws between [`OcclusionQueries::begin_query`] and
+/// [`OcclusionQueries::end_query`], and read results of earlier frames with
+/// [`OcclusionQueries::result`].
+pub struct OcclusionQueries {
+    query_pool: Arc<QueryPool>,
+    queries_per_frame: u32,
+    /// Whether each frame's region has been reset, so that its queries can be read
+    reset_frames: Vec<bool>,
+    current_frame: u32,
+    /// Whether `occlusion_query_precise` is enabled
+    precise: bool,
+}
+
+impl OcclusionQueries {
+    /// Creates a query pool with `queries_per_frame` occlusion queries for each of
+    /// `frames_in_flight` frames.
+    pub fn new(
+        context: &BevyVulkanoContext,
+        queries_per_frame: u32,
+        frames_in_flight: usize,
+    ) -> Result<OcclusionQueries, QueryPoolCreationError> {
+        let device = context.context.device();
+        let frames = frames_in_flight.max(1);
+        let query_pool = QueryPool::new(device.clone(), QueryPoolCreateInfo {
+            query_count: queries_per_frame * frames as u32,
+            ..QueryPoolCreateInfo::query_type(QueryType::Occlusion)
+        })?;
+        Ok(OcclusionQueries {
+            query_pool,
+            queries_per_frame,
+            reset_frames: vec![false; frames],
+            current_frame: 0,
+            precise: device.enabled_features().occlusion_query_precise,
+        })
+    }
+
+    /// Starts recording queries of `frame_index`, discarding the frame's previous results. This
+    /// resets the frame's queries, so it must be recorded outside a render pass, and the frame
+    /// that previously used the region must have finished on the GPU.
+    pub fn begin_frame<L, A: CommandBufferAllocator>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        frame_index: usize,
+    ) {
+        let frame = frame_index % self.reset_frames.len();
+        self.current_frame = frame as u32;
+        unsafe {
+            builder
+                .reset_query_pool(self.query_pool.clone(), self.frame_range(frame))
+                .unwrap();
+        }
+        self.reset_frames[frame] = true;
+    }
+
+    /// Starts counting samples for `query` of the current frame. With `precise`, the exact count
+    /// is returned if the `occlusion_query_precise` feature is enabled, otherwise any non-zero
+    /// count may be returned as 1.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `query` isn't below `queries_per_frame`, or was already used this frame.
+    pub fn begin_query<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        query: u32,
+        precise: bool,
+    ) {
+        assert!(query < self.queries_per_frame, "Query index out of range");
+        let flags = if precise && self.precise {
+            QueryControlFlags::PRECISE
+        } else {
+            QueryControlFlags::empty()
+        };
+        unsafe {
+            builder
+                .begin_query(self.query_pool.clone(), self.query_index(query), flags)
+                .unwrap();
+        }
+    }
+
+    /// Stops counting samples for `query` of the current frame.
+    pub fn end_query<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        query: u32,
+    ) {
+        builder
+            .end_query(self.query_pool.clone(), self.query_index(query))
+            .unwrap();
+    }
+
+    /// Samples counted by `query` of `frame_index`, or `None` if the result isn't available, e.g.
+    /// because the frame is still executing or the query wasn't recorded.
+    pub fn result(&self, frame_index: usize, query: u32) -> Option<u64> {
+        let frame = frame_index % self.reset_frames.len();
+        if query >= self.queries_per_frame || !self.reset_frames[frame] {
+            return None;
+        }
+        let index = frame as u32 * self.queries_per_frame + query;
+        let mut samples = [0u64];
+        let available = self
+            .query_pool
+            .queries_range(index..index + 1)?
+            .get_results(&mut samples, QueryResultFlags::empty())
+            .ok()?;
+        available.then_some(samples[0])
+    }
+
+    /// Number of queries available each frame
+    pub fn queries_per_frame(&self) -> u32 {
+        self.queries_per_frame
+    }
+
+    fn frame_range(&self, frame: usize) -> std::ops::Range<u32> {
+        let start = frame as u32 * self.queries_per_frame;
+        start..start + self.queries_per_frame
+    }
+
+    fn query_index(&self, query: u32) -> u32 {
+        self.current_frame * self.queries_per_frame + query
+    }
+}
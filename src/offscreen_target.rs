@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use bevy::log::warn;
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    format::Format,
+    image::{view::ImageView, AttachmentImage, ImageAccess, ImageUsage, SampleCount},
+    sampler::Filter,
+    sync::GpuFuture,
+};
+
+use crate::{
+    util::blit_image, BevyVulkanoContext, DepthImageView, SharedMemoryAllocator,
+    TransientAttachmentImage, VulkanoWindowRenderer,
+};
+
+/// Color image view of an [`OffscreenTarget`]
+pub type OffscreenImageView = Arc<ImageView<AttachmentImage>>;
+
+/// An offscreen color target (and optional depth attachment) to render into instead of the
+/// swapchain. The color image can be sampled afterwards, or blitted to a window with
+/// [`OffscreenTarget::blit_to_swapchain`].
+pub struct OffscreenTarget {
+    memory_allocator: Arc<SharedMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    format: Format,
+    depth_format: Option<Format>,
+    color_view: OffscreenImageView,
+    depth_view: Option<DepthImageView>,
+}
+
+impl OffscreenTarget {
+    /// Creates a target of `dimensions` with a color image of `format`. A depth attachment is
+    /// created too if `depth_format` is set.
+    pub fn new(
+        context: &BevyVulkanoContext,
+        dimensions: [u32; 2],
+        format: Format,
+        depth_format: Option<Format>,
+    ) -> OffscreenTarget {
+        let memory_allocator = context.context.memory_allocator().clone();
+        let color_view = create_color_view(&*memory_allocator, dimensions, format);
+        let depth_view = create_depth_view(&*memory_allocator, dimensions, depth_format);
+        OffscreenTarget {
+            memory_allocator,
+            command_buffer_allocator: context.command_buffer_allocator(),
+            format,
+            depth_format,
+            color_view,
+            depth_view,
+        }
+    }
+
+    /// Return the color image view. The view is recreated on [`OffscreenTarget::resize`], so fetch
+    /// it each frame instead of holding on to it.
+    #[inline]
+    pub fn image_view(&self) -> OffscreenImageView {
+        self.color_view.clone()
+    }
+
+    /// Return the depth attachment view, if the target was created with a depth format.
+    #[inline]
+    pub fn depth_image_view(&self) -> Option<DepthImageView> {
+        self.depth_view.clone()
+    }
+
+    /// Format of the color image.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Size of the color (and depth) image.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.color_view.image().dimensions().width_height()
+    }
+
+    /// Recreates the images at `dimensions`. Does nothing if the size doesn't change. Previous
+    /// contents are discarded.
+    pub fn resize(&mut self, dimensions: [u32; 2]) {
+        if dimensions == self.dimensions() {
+            return;
+        }
+        self.color_view = create_color_view(&*self.memory_allocator, dimensions, self.format);
+        self.depth_view = create_depth_view(&*self.memory_allocator, dimensions, self.depth_format);
+    }
+
+    /// Blits the color image over the current swapchain image of `renderer`, scaling it to fit.
+    /// Call this between [`VulkanoWindowRenderer::acquire`] and
+    /// [`VulkanoWindowRenderer::present`], and pass the returned future to `present`.
+    ///
+    /// The swapchain images must support `TRANSFER_DST` usage, see
+    /// [`VulkanoWinitConfig::swapchain_image_usage`](crate::VulkanoWinitConfig::swapchain_image_usage).
+    /// Otherwise nothing is recorded and `before_future` is returned as is.
+    pub fn blit_to_swapchain(
+        &self,
+        renderer: &VulkanoWindowRenderer,
+        before_future: Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        let dst_image = renderer.swapchain_image_view().image().clone();
+        if !dst_image.usage().intersects(ImageUsage::TRANSFER_DST) {
+            warn!("Swapchain images don't support TRANSFER_DST usage, skipping offscreen blit");
+            return before_future;
+        }
+        let queue = renderer.graphics_queue();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        blit_image(
+            &mut builder,
+            self.color_view.image().clone(),
+            dst_image,
+            Filter::Linear,
+        )
+        .unwrap();
+        let command_buffer = builder.build().unwrap();
+        before_future
+            .then_execute(queue, command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+fn create_color_view(
+    memory_allocator: &SharedMemoryAllocator,
+    dimensions: [u32; 2],
+    format: Format,
+) -> OffscreenImageView {
+    ImageView::new_default(
+        AttachmentImage::with_usage(
+            memory_allocator,
+            dimensions,
+            format,
+            ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+fn create_depth_view(
+    memory_allocator: &SharedMemoryAllocator,
+    dimensions: [u32; 2],
+    depth_format: Option<Format>,
+) -> Option<DepthImageView> {
+    depth_format.map(|format| {
+        ImageView::new_default(
+            TransientAttachmentImage::new(
+                memory_allocator,
+                dimensions,
+                format,
+                SampleCount::Sample1,
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    })
+}
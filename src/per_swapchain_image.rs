@@ -0,0 +1,83 @@
+use std::any::Any;
+
+/// One `T` per swapchain image, e.g. uniform buffers or descriptor sets written by the frame
+/// rendering to that image. Add it with
+/// [`VulkanoWindowRenderer::add_per_image`](crate::VulkanoWindowRenderer::add_per_image) and get
+/// the current image's slot with
+/// [`VulkanoWindowRenderer::per_image_mut`](crate::VulkanoWindowRenderer::per_image_mut).
+///
+/// Slots are created by the closure given the image index. When the swapchain is recreated with a
+/// different image count, slots of removed images are dropped and the closure is run for the new
+/// ones. Slots of kept indices are reused as is.
+pub struct PerSwapchainImage<T> {
+    items: Vec<T>,
+    create: Box<dyn FnMut(usize) -> T>,
+}
+
+impl<T> PerSwapchainImage<T> {
+    pub fn new(image_count: usize, mut create: impl FnMut(usize) -> T + 'static) -> Self {
+        let items = (0..image_count).map(&mut create).collect();
+        PerSwapchainImage {
+            items,
+            create: Box::new(create),
+        }
+    }
+
+    /// Creates or drops slots to match `image_count`
+    pub fn resize(&mut self, image_count: usize) {
+        self.items.truncate(image_count);
+        while self.items.len() < image_count {
+            let item = (self.create)(self.items.len());
+            self.items.push(item);
+        }
+    }
+
+    /// Slot of image `index`
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index)
+    }
+
+    /// Slot of image `index`
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.items.get_mut(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.items.iter_mut()
+    }
+}
+
+/// Type erased [`PerSwapchainImage`], so that the renderer can hold containers of any type
+pub(crate) trait AnyPerSwapchainImage {
+    fn resize(&mut self, image_count: usize);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyPerSwapchainImage for PerSwapchainImage<T> {
+    fn resize(&mut self, image_count: usize) {
+        PerSwapchainImage::resize(self, image_count);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
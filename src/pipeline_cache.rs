@@ -0,0 +1,56 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use bevy::log::{info, warn};
+use vulkano::{device::Device, pipeline::cache::PipelineCache};
+
+/// Size of the pipeline cache header version one, see `VkPipelineCacheHeaderVersionOne`
+const HEADER_SIZE: usize = 32;
+const HEADER_VERSION_ONE: u32 = 1;
+
+/// Whether the cache blob was created by a compatible device & driver. Vulkan implementations are
+/// expected to check this too, but not all of them survive corrupt data.
+fn is_compatible(device: &Device, data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let properties = device.physical_device().properties();
+    read_u32(0) as usize >= HEADER_SIZE
+        && read_u32(4) == HEADER_VERSION_ONE
+        && read_u32(8) == properties.vendor_id
+        && read_u32(12) == properties.device_id
+        && data[16..32] == properties.pipeline_cache_uuid
+}
+
+/// Creates a pipeline cache from the data at `path`. Missing, corrupt or incompatible data is
+/// discarded and an empty cache is created instead.
+pub(crate) fn load_pipeline_cache(device: &Arc<Device>, path: &Path) -> Arc<PipelineCache> {
+    match fs::read(path) {
+        Ok(data) if is_compatible(device, &data) => {
+            // Safety: Header was validated to match this device
+            match unsafe { PipelineCache::with_data(device.clone(), &data) } {
+                Ok(cache) => {
+                    info!("Loaded pipeline cache from {}", path.display());
+                    return cache;
+                }
+                Err(e) => warn!("Failed to load pipeline cache {}: {}", path.display(), e),
+            }
+        }
+        Ok(_) => warn!(
+            "Discarding corrupt or incompatible pipeline cache {}",
+            path.display()
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to read pipeline cache {}: {}", path.display(), e),
+    }
+    PipelineCache::empty(device.clone()).expect("Failed to create pipeline cache")
+}
+
+/// Writes pipeline cache data to `path`
+pub(crate) fn save_pipeline_cache(cache: &PipelineCache, path: &Path) -> io::Result<()> {
+    let data = cache.get_data().map_err(io::Error::other)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)
+}
@@ -1,7 +1,9 @@
 use bevy::prelude::Entity;
 #[allow(unused)]
 use bevy::{ecs::system::Resource, utils::HashMap};
-use vulkano::sync::GpuFuture;
+use vulkano::sync::{FlushError, GpuFuture};
+
+use crate::FrameFence;
 
 /// Contains gpu future data per window to be used in Vulkano pipeline synchronization
 #[derive(Default, Resource)]
@@ -40,6 +42,81 @@ pub struct SyncData {
     pub window_entity: Entity,
     pub before: Option<Box<dyn GpuFuture>>,
     pub after: Option<Box<dyn GpuFuture>>,
+    /// Whether systems rendered this frame's content themselves, deciding if
+    /// [`post_render_system`](crate::post_render_system) clears it. Reset to
+    /// [`FrameContent::Auto`] when the frame is acquired.
+    pub content: FrameContent,
+    /// Frame slot of the frame being rendered, see
+    /// [`VulkanoWindowRenderer::current_frame_index`](crate::VulkanoWindowRenderer::current_frame_index).
+    /// Set when the frame is acquired.
+    pub frame_index: usize,
+    /// Fence of the latest frame presented from each frame slot
+    frame_fences: Vec<Option<FrameFence>>,
+}
+
+impl SyncData {
+    pub fn new(window_entity: Entity) -> SyncData {
+        SyncData {
+            window_entity,
+            before: None,
+            after: None,
+            content: FrameContent::Auto,
+            frame_index: 0,
+            frame_fences: vec![],
+        }
+    }
+
+    /// Number of frame slots tracked, i.e. the window's frames in flight. 0 until the first frame
+    /// is acquired.
+    pub fn frame_in_flight_count(&self) -> usize {
+        self.frame_fences.len()
+    }
+
+    /// Fence signaled when the GPU work of the latest frame presented from slot `frame_index` has
+    /// completed, if any.
+    pub fn frame_fence(&self, frame_index: usize) -> Option<&FrameFence> {
+        self.frame_fences.get(frame_index)?.as_ref()
+    }
+
+    /// Blocks until the latest frame presented from slot `frame_index` has finished on the GPU,
+    /// so that resources it used (e.g. staging buffers or descriptor sets) can be reused, without
+    /// waiting on other frames.
+    pub fn wait_for_frame(&self, frame_index: usize) -> Result<(), FlushError> {
+        match self.frame_fence(frame_index) {
+            Some(fence) => fence.wait(None),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts a frame of slot `frame_index` out of `frames_in_flight`
+    pub(crate) fn begin_frame(&mut self, frame_index: usize, frames_in_flight: usize) {
+        self.frame_index = frame_index;
+        self.frame_fences.resize(frames_in_flight, None);
+    }
+
+    /// Stores the fence of the frame presented from the current slot
+    pub(crate) fn set_frame_fence(&mut self, fence: Option<FrameFence>) {
+        if let Some(slot) = self.frame_fences.get_mut(self.frame_index) {
+            *slot = fence;
+        }
+    }
+}
+
+/// What [`post_render_system`](crate::post_render_system) presents for a window's frame, see
+/// [`SyncData::content`]. Frames it clears are filled with
+/// [`VulkanoWindowRenderer::clear_color`](crate::VulkanoWindowRenderer::clear_color), so that
+/// uninitialized swapchain images (e.g. on the first frame) are never presented.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum FrameContent {
+    /// The frame was rendered if anything was chained to [`SyncData::after`], otherwise it's
+    /// cleared
+    #[default]
+    Auto,
+    /// Systems rendered the whole frame, it's presented as is
+    Provided,
+    /// Systems didn't render to the frame, e.g. [`SyncData::after`] only holds offscreen work or
+    /// the rendering system skipped this frame. It's cleared after [`SyncData::after`].
+    Clear,
 }
 
 unsafe impl Send for SyncData {}
@@ -0,0 +1,1931 @@
+// Copyright (c) 2022 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+/*
+Adapted from vulkano_util's renderer, so that the window renderer can be extended with features
+this crate needs (e.g. depth attachment) without waiting on upstream.
+ */
+use std::{
+    any::{Any, TypeId},
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    num::NonZeroU64,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    math::{IVec2, Mat4, Vec2, Vec3},
+    utils::HashMap,
+};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, ClearColorImageInfo,
+        CommandBufferUsage, CopyImageToBufferInfo, SemaphoreSubmitInfo, SubmitInfo,
+    },
+    device::{Device, DeviceOwned, Queue},
+    format::{ClearColorValue, Format, FormatFeatures, NumericType},
+    image::{
+        view::ImageView, ImageAccess, ImageUsage, ImageViewAbstract, SampleCount, StorageImage,
+        SwapchainImage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryUsage},
+    swapchain::{
+        self, AcquireError, ColorSpace, FullScreenExclusive, FullScreenExclusiveError, PresentMode,
+        RectangleLayer, Surface, SurfaceCapabilities, SurfaceTransform, Swapchain,
+        SwapchainCreateInfo, SwapchainCreationError, SwapchainPresentInfo,
+    },
+    sync::{
+        self,
+        future::FenceSignalFuture,
+        semaphore::{Semaphore, SemaphoreError},
+        FlushError, GpuFuture,
+    },
+    DeviceSize, VulkanError, VulkanObject,
+};
+use vulkano_util::window::WindowDescriptor;
+use vulkano_win::create_surface_from_winit;
+use winit::window::{Fullscreen, Window};
+
+use crate::{
+    per_swapchain_image::AnyPerSwapchainImage, util, PerSwapchainImage, SharedMemoryAllocator,
+    TransientAttachmentImage, VulkanoContext, VulkanoWinitConfig,
+};
+
+/// Swapchain Image View. Your final render target typically.
+pub type SwapchainImageView = Arc<ImageView<SwapchainImage>>;
+/// Multipurpose image view
+pub type DeviceImageView = Arc<ImageView<StorageImage>>;
+/// Depth (stencil) attachment image view which follows the swapchain size, see
+/// [`TransientAttachmentImage`]
+pub type DepthImageView = Arc<ImageView<TransientAttachmentImage>>;
+/// Fence signaled when a presented frame's GPU work has completed
+pub type FrameFence = Arc<FenceSignalFuture<Box<dyn GpuFuture>>>;
+
+/// How long [`VulkanoWindowRenderer::acquire`] waits on a present before giving up on it, so that
+/// a present the platform never completes can't hang the app
+const PRESENT_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Most common image format
+pub const DEFAULT_IMAGE_FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+/// A window renderer struct holding the winit window surface and functionality for organizing your
+/// render between frames.
+///
+/// Begin rendering with [`VulkanoWindowRenderer::acquire`] and finish with
+/// [`VulkanoWindowRenderer::present`]. Between those, you should execute your command buffers.
+///
+/// The intended usage of this struct is through [`crate::BevyVulkanoWindows`].
+pub struct VulkanoWindowRenderer {
+    surface: Arc<Surface>,
+    graphics_queue: Arc<Queue>,
+    compute_queue: Arc<Queue>,
+    swapchain: Arc<Swapchain>,
+    final_views: Vec<SwapchainImageView>,
+    memory_allocator: Arc<SharedMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    /// Additional image views that you can add which are resized with the window.
+    /// Use associated functions to get access to these.
+    additional_image_views: HashMap<usize, DeviceImageView>,
+    /// Per swapchain image resources, keyed by their type. Resized with the swapchain
+    per_image_resources: HashMap<TypeId, Box<dyn AnyPerSwapchainImage>>,
+    /// Your data attached to the window, keyed by its type
+    user_data: HashMap<TypeId, Box<dyn Any>>,
+    /// Optional depth attachment, recreated along with the swapchain
+    depth_format: Option<Format>,
+    depth_view: Option<DepthImageView>,
+    /// Requested minimum swapchain image count, clamped to surface capabilities
+    min_image_count: Option<u32>,
+    recreate_swapchain: bool,
+    /// Whether the swapchain was recreated since the last present, see
+    /// [`VulkanoWindowRenderer::swapchain_recreated`]
+    swapchain_recreated: bool,
+    /// Number of times the swapchain was recreated, see
+    /// [`VulkanoWindowRenderer::swapchain_generation`]
+    swapchain_generation: u64,
+    /// Generation last reported as a [`crate::SwapchainRecreated`] event
+    reported_swapchain_generation: u64,
+    /// Whether the image of the current frame was acquired from a suboptimal swapchain
+    suboptimal: bool,
+    /// Fences of frames in flight, indexed by frame index. The CPU waits on a frame's fence only
+    /// when its slot comes around again.
+    frame_fences: Vec<Option<FrameFence>>,
+    /// Whether to wait on frame fences. If not, frames are only limited by swapchain acquire
+    wait_frame_fences: bool,
+    current_frame: usize,
+    previous_frame: usize,
+    image_index: u32,
+    present_mode: PresentMode,
+    /// Color used by [`VulkanoWindowRenderer::clear_swapchain_image`]
+    clear_color: [f32; 4],
+    /// Minimum time between presents, see [`VulkanoWindowRenderer::set_frame_rate_limit`]
+    min_frame_time: Option<Duration>,
+    /// How long [`VulkanoWindowRenderer::acquire`] waits for a swapchain image
+    acquire_timeout: Option<Duration>,
+    /// Whether swapchain recreation skips waiting for frames in flight, see
+    /// [`VulkanoWinitConfig::smooth_resize`]
+    smooth_resize: bool,
+    /// See [`VulkanoWinitConfig::pre_transform`]
+    requested_pre_transform: Option<SurfaceTransform>,
+    last_present: Option<Instant>,
+    /// Presents allowed to be queued when acquiring, if present wait is supported. See
+    /// [`VulkanoWinitConfig::max_frame_latency`]
+    max_frame_latency: Option<u32>,
+    /// Whether presents get ids that can be waited on with `VK_KHR_present_wait`
+    present_wait: bool,
+    /// Id of the latest present
+    present_id: u64,
+    /// Id of the latest present to the current swapchain, see
+    /// [`VulkanoWindowRenderer::wait_for_last_present`]
+    last_present_id: Option<u64>,
+    /// Ids & submission times of presents not waited on yet
+    pending_presents: VecDeque<(u64, Instant)>,
+    present_latency: Option<Duration>,
+    /// Whether `VK_GOOGLE_display_timing` is enabled, see [`VulkanoWinitConfig::display_timing`]
+    display_timing: bool,
+    /// Whether [`VulkanoWindowRenderer::present_at`] warned about missing display timing
+    warned_display_timing: bool,
+    /// Semaphores waited on by the latest [`VulkanoWindowRenderer::present_at`] of each swapchain
+    /// image
+    present_semaphores: Vec<Option<Arc<Semaphore>>>,
+    /// Whether `VK_KHR_incremental_present` is enabled, see
+    /// [`VulkanoWindowRenderer::present_with_regions`]
+    incremental_present: bool,
+    /// Number of frames successfully presented
+    frames_presented: u64,
+    /// Presents not yet reported as [`crate::FramePresented`] events
+    unreported_presents: Vec<(u64, Instant)>,
+}
+
+/// Timing of a past present, reported by `VK_GOOGLE_display_timing`. Times are in nanoseconds of
+/// the platform's presentation clock (`CLOCK_MONOTONIC` on Linux & Android).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentTiming {
+    /// Application id of the present: the low 32 bits of
+    /// [`VulkanoWindowRenderer::frames_presented`] before presents of
+    /// [`VulkanoWindowRenderer::present_at`], 0 for other presents
+    pub present_id: u32,
+    /// Requested present time, 0 if none was given
+    pub desired_present_time: u64,
+    /// When the image was actually presented
+    pub actual_present_time: u64,
+    /// Earliest time the image could have been presented, earlier than `actual_present_time` if
+    /// the present was held back to honor `desired_present_time`
+    pub earliest_present_time: u64,
+    /// How early the GPU work finished relative to `earliest_present_time`
+    pub present_margin: u64,
+}
+
+impl VulkanoWindowRenderer {
+    /// Creates a new [`VulkanoWindowRenderer`] which is used to orchestrate your rendering with
+    /// Vulkano. Pass [`WindowDescriptor`] and optionally a function modifying the
+    /// [`SwapchainCreateInfo`](vulkano::swapchain::SwapchainCreateInfo) parameters.
+    /// `preferred_surface_formats` take precedence over
+    /// [`VulkanoWinitConfig::preferred_surface_formats`] if the surface supports any of them.
+    pub fn new(
+        vulkano_context: &VulkanoContext,
+        window: winit::window::Window,
+        descriptor: &WindowDescriptor,
+        config: &VulkanoWinitConfig,
+        preferred_surface_formats: &[(Format, ColorSpace)],
+        swapchain_create_info_modify: fn(&mut SwapchainCreateInfo),
+    ) -> VulkanoWindowRenderer {
+        // Create rendering surface from window
+        let surface =
+            create_surface_from_winit(Arc::new(window), vulkano_context.instance().clone())
+                .unwrap();
+
+        // Create swap chain & frame(s) to which we'll render
+        let (swap_chain, final_views) = Self::create_swapchain(
+            vulkano_context.device().clone(),
+            surface.clone(),
+            descriptor,
+            config,
+            preferred_surface_formats,
+            swapchain_create_info_modify,
+        );
+
+        // Without present wait, frame latency is limited by waiting on frame fences instead
+        let features = vulkano_context.device().enabled_features();
+        let present_wait = features.present_id && features.present_wait;
+        let frames_in_flight = match config.max_frame_latency {
+            Some(latency) if !present_wait => {
+                let latency = latency.max(1) as usize;
+                if config.frames_in_flight == 0 {
+                    latency
+                } else {
+                    config.frames_in_flight.min(latency)
+                }
+            }
+            _ => config.frames_in_flight,
+        };
+
+        let present_mode = swap_chain.present_mode();
+        let mut renderer = VulkanoWindowRenderer {
+            surface,
+            graphics_queue: vulkano_context.graphics_queue().clone(),
+            compute_queue: vulkano_context
+                .compute_queue()
+                .unwrap_or_else(|| vulkano_context.graphics_queue().clone()),
+            swapchain: swap_chain,
+            final_views,
+            memory_allocator: vulkano_context.memory_allocator().clone(),
+            command_buffer_allocator: vulkano_context.command_buffer_allocator().clone(),
+            additional_image_views: HashMap::default(),
+            per_image_resources: HashMap::default(),
+            user_data: HashMap::default(),
+            depth_format: config.depth_format,
+            depth_view: None,
+            min_image_count: config.min_image_count,
+            recreate_swapchain: false,
+            swapchain_recreated: false,
+            swapchain_generation: 0,
+            reported_swapchain_generation: 0,
+            suboptimal: false,
+            frame_fences: (0..frames_in_flight.max(1)).map(|_| None).collect(),
+            wait_frame_fences: frames_in_flight > 0,
+            current_frame: 0,
+            previous_frame: 0,
+            image_index: 0,
+            present_mode,
+            clear_color: [0.0; 4],
+            min_frame_time: None,
+            acquire_timeout: config.acquire_timeout,
+            smooth_resize: config.smooth_resize,
+            requested_pre_transform: config.pre_transform,
+            last_present: None,
+            max_frame_latency: config.max_frame_latency.filter(|_| present_wait),
+            present_wait,
+            present_id: 0,
+            last_present_id: None,
+            pending_presents: VecDeque::new(),
+            present_latency: None,
+            display_timing: vulkano_context
+                .device()
+                .enabled_extensions()
+                .google_display_timing,
+            warned_display_timing: false,
+            present_semaphores: vec![],
+            incremental_present: vulkano_context
+                .device()
+                .enabled_extensions()
+                .khr_incremental_present,
+            frames_presented: 0,
+            unreported_presents: vec![],
+        };
+        renderer.set_frame_rate_limit(config.frame_rate_limit);
+        renderer.create_depth_view();
+        renderer
+    }
+
+    /// Creates the swapchain and its images based on [`WindowDescriptor`]. The swapchain creation
+    /// can be modified with the `swapchain_create_info_modify` function passed as an input.
+    /// [`VulkanoWinitConfig::preferred_surface_formats`] take precedence over the modification
+    /// if the surface supports any of them.
+    fn create_swapchain(
+        device: Arc<Device>,
+        surface: Arc<Surface>,
+        window_descriptor: &WindowDescriptor,
+        config: &VulkanoWinitConfig,
+        preferred_surface_formats: &[(Format, ColorSpace)],
+        swapchain_create_info_modify: fn(&mut SwapchainCreateInfo),
+    ) -> (Arc<Swapchain>, Vec<SwapchainImageView>) {
+        let surface_capabilities = device
+            .physical_device()
+            .surface_capabilities(&surface, Default::default())
+            .unwrap();
+        let surface_formats = device
+            .physical_device()
+            .surface_formats(&surface, Default::default())
+            .unwrap();
+        let image_format = Some(surface_formats[0].0);
+        let preferred_format = preferred_surface_formats
+            .iter()
+            .chain(&config.preferred_surface_formats)
+            .find(|format| surface_formats.contains(format));
+        let unsupported_usage =
+            config.swapchain_image_usage - surface_capabilities.supported_usage_flags;
+        if !unsupported_usage.is_empty() {
+            bevy::log::warn!(
+                "Swapchain image usage {:?} is not supported by the surface, ignoring it",
+                unsupported_usage
+            );
+        }
+        let supported_present_modes: Vec<PresentMode> = device
+            .physical_device()
+            .surface_present_modes(&surface)
+            .map(|modes| modes.collect())
+            .unwrap_or_default();
+        let present_mode =
+            select_present_mode(window_descriptor.present_mode, &supported_present_modes);
+        if present_mode != window_descriptor.present_mode {
+            bevy::log::warn!(
+                "Present mode {:?} is not supported by the surface, using {:?}",
+                window_descriptor.present_mode,
+                present_mode
+            );
+        }
+        let pre_transform = match config.pre_transform {
+            Some(transform)
+                if !surface_capabilities
+                    .supported_transforms
+                    .contains_enum(transform) =>
+            {
+                bevy::log::warn!(
+                    "Surface transform {:?} is not supported, using {:?}",
+                    transform,
+                    surface_capabilities.current_transform
+                );
+                surface_capabilities.current_transform
+            }
+            Some(transform) => transform,
+            None => surface_capabilities.current_transform,
+        };
+        let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
+        let image_extent = clamp_image_extent(
+            window.inner_size().into(),
+            &surface_capabilities,
+            pre_transform,
+        );
+        let (swapchain, images) = Swapchain::new(device.clone(), surface, {
+            let mut create_info = SwapchainCreateInfo {
+                min_image_count: clamp_image_count(config.min_image_count, &surface_capabilities),
+                image_format,
+                image_extent,
+                // Transfer source allows reading back frames (screenshots) and transfer destination
+                // allows clears & blits when supported
+                image_usage: ImageUsage::COLOR_ATTACHMENT
+                    | (surface_capabilities.supported_usage_flags
+                        & (ImageUsage::TRANSFER_SRC
+                            | ImageUsage::TRANSFER_DST
+                            | config.swapchain_image_usage)),
+                composite_alpha: surface_capabilities
+                    .supported_composite_alpha
+                    .into_iter()
+                    .next()
+                    .unwrap(),
+                pre_transform,
+                ..Default::default()
+            };
+            // Get present mode from window descriptor, falling back to supported ones
+            create_info.present_mode = present_mode;
+            // Exclusive access is only taken when requested, see `acquire_full_screen_exclusive`
+            #[cfg(target_os = "windows")]
+            if device.enabled_extensions().ext_full_screen_exclusive {
+                if let Some(monitor) = window.current_monitor() {
+                    create_info.full_screen_exclusive = FullScreenExclusive::ApplicationControlled;
+                    create_info.win32_monitor =
+                        Some(vulkano_win::create_win32_monitor_from_winit(&monitor));
+                }
+            }
+            swapchain_create_info_modify(&mut create_info);
+            if let Some((format, color_space)) = preferred_format {
+                create_info.image_format = Some(*format);
+                create_info.image_color_space = *color_space;
+            }
+            create_info
+        })
+        .unwrap();
+        let images = images
+            .into_iter()
+            .map(|image| ImageView::new_default(image).unwrap())
+            .collect::<Vec<_>>();
+        (swapchain, images)
+    }
+
+    /// (Re)creates the depth attachment at swapchain image size if a depth format was requested.
+    fn create_depth_view(&mut self) {
+        self.depth_view = self.depth_format.map(|format| {
+            ImageView::new_default(
+                TransientAttachmentImage::new(
+                    &*self.memory_allocator,
+                    self.swapchain_image_size(),
+                    format,
+                    SampleCount::Sample1,
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        });
+    }
+
+    /// Set window renderer present mode. This triggers a swapchain recreation on next
+    /// [`VulkanoWindowRenderer::acquire`]. If the surface doesn't support the mode, an error is
+    /// returned and the current mode is kept.
+    #[inline]
+    pub fn set_present_mode(
+        &mut self,
+        present_mode: PresentMode,
+    ) -> Result<(), PresentModeNotSupported> {
+        if !self.supported_present_modes().contains(&present_mode) {
+            return Err(PresentModeNotSupported(present_mode));
+        }
+        if self.present_mode != present_mode {
+            self.present_mode = present_mode;
+            self.recreate_swapchain = true;
+        }
+        Ok(())
+    }
+
+    /// Current present mode.
+    #[inline]
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// Present modes supported by the surface of this window.
+    pub fn supported_present_modes(&self) -> Vec<PresentMode> {
+        self.graphics_queue
+            .device()
+            .physical_device()
+            .surface_present_modes(&self.surface)
+            .map(|modes| modes.collect())
+            .unwrap_or_default()
+    }
+
+    /// Capabilities of the surface of this window on the current physical device, e.g. supported
+    /// image counts, usage flags and transforms.
+    pub fn surface_capabilities(&self) -> SurfaceCapabilities {
+        self.graphics_queue
+            .device()
+            .physical_device()
+            .surface_capabilities(&self.surface, Default::default())
+            .unwrap()
+    }
+
+    /// Formats and color spaces supported by the surface of this window on the current physical
+    /// device.
+    pub fn supported_surface_formats(&self) -> Vec<(Format, ColorSpace)> {
+        self.graphics_queue
+            .device()
+            .physical_device()
+            .surface_formats(&self.surface, Default::default())
+            .unwrap_or_default()
+    }
+
+    /// Return swapchain image format.
+    #[inline]
+    pub fn swapchain_format(&self) -> Format {
+        self.final_views[self.image_index as usize]
+            .format()
+            .unwrap()
+    }
+
+    /// Return swapchain color space, e.g. `ColorSpace::Hdr10St2084` if an HDR format was selected
+    /// through [`VulkanoWinitConfig::preferred_surface_formats`]. Use this to decide which
+    /// transfer function to apply when writing to the swapchain image.
+    #[inline]
+    pub fn swapchain_color_space(&self) -> ColorSpace {
+        self.swapchain.image_color_space()
+    }
+
+    /// Number of swapchain images actually obtained. Drivers may round the requested
+    /// [`VulkanoWinitConfig::min_image_count`] up.
+    #[inline]
+    pub fn image_count(&self) -> u32 {
+        self.final_views.len() as u32
+    }
+
+    /// Returns the index of the swapchain image acquired by the latest
+    /// [`VulkanoWindowRenderer::acquire`], which is the current render target. Use it to index
+    /// per swapchain image resources, sized by [`VulkanoWindowRenderer::image_count`]. The count
+    /// may change when the swapchain is recreated.
+    #[inline]
+    pub fn image_index(&self) -> u32 {
+        self.image_index
+    }
+
+    /// Graphics queue of this window. You also can access this through [`VulkanoContext`].
+    #[inline]
+    pub fn graphics_queue(&self) -> Arc<Queue> {
+        self.graphics_queue.clone()
+    }
+
+    /// Compute queue of this window. This is the separate async compute queue if the device has
+    /// one, otherwise the graphics queue. See [`VulkanoContext::compute_queue`].
+    #[inline]
+    pub fn compute_queue(&self) -> Arc<Queue> {
+        self.compute_queue.clone()
+    }
+
+    /// Render target surface.
+    #[inline]
+    pub fn surface(&self) -> Arc<Surface> {
+        self.surface.clone()
+    }
+
+    /// Winit window (you can manipulate window through this).
+    #[inline]
+    pub fn window(&self) -> &Window {
+        self.surface.object().unwrap().downcast_ref().unwrap()
+    }
+
+    /// Size of the physical window.
+    #[inline]
+    pub fn window_size(&self) -> [f32; 2] {
+        let size = self.window().inner_size();
+        [size.width as f32, size.height as f32]
+    }
+
+    /// Size of the final swapchain image (surface).
+    #[inline]
+    pub fn swapchain_image_size(&self) -> [u32; 2] {
+        self.final_views[0].image().dimensions().width_height()
+    }
+
+    /// Transform the presentation engine applies to swapchain images, see
+    /// [`VulkanoWinitConfig::pre_transform`]. When it rotates by a quarter turn, the swapchain
+    /// images have the window's width & height swapped, and rendering must be rotated to match,
+    /// see [`VulkanoWindowRenderer::pre_transform_matrix`].
+    #[inline]
+    pub fn pre_transform(&self) -> SurfaceTransform {
+        self.swapchain.pre_transform()
+    }
+
+    /// Rotation (and mirroring) of clip space matching [`VulkanoWindowRenderer::pre_transform`].
+    /// Multiply it on the left of the projection matrix, which keeps using the window's aspect
+    /// ratio. The identity unless the surface is rotated, e.g. on desktop.
+    pub fn pre_transform_matrix(&self) -> Mat4 {
+        let quarter_turns = |n: f32| Mat4::from_rotation_z(n * std::f32::consts::FRAC_PI_2);
+        let mirror = Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0));
+        match self.pre_transform() {
+            SurfaceTransform::Rotate90 => quarter_turns(1.0),
+            SurfaceTransform::Rotate180 => quarter_turns(2.0),
+            SurfaceTransform::Rotate270 => quarter_turns(3.0),
+            SurfaceTransform::HorizontalMirror => mirror,
+            SurfaceTransform::HorizontalMirrorRotate90 => quarter_turns(1.0) * mirror,
+            SurfaceTransform::HorizontalMirrorRotate180 => quarter_turns(2.0) * mirror,
+            SurfaceTransform::HorizontalMirrorRotate270 => quarter_turns(3.0) * mirror,
+            _ => Mat4::IDENTITY,
+        }
+    }
+
+    /// Return the current swapchain image view.
+    #[inline]
+    pub fn swapchain_image_view(&self) -> SwapchainImageView {
+        self.final_views[self.image_index as usize].clone()
+    }
+
+    /// Return the views of all swapchain images, indexed by [`VulkanoWindowRenderer::image_index`],
+    /// e.g. to create a framebuffer per image once instead of each frame. The views are replaced
+    /// when the swapchain is recreated, check [`VulkanoWindowRenderer::swapchain_recreated`] after
+    /// acquiring to rebuild what was created from them.
+    #[inline]
+    pub fn swapchain_image_views(&self) -> &[SwapchainImageView] {
+        &self.final_views
+    }
+
+    /// Whether the swapchain was recreated since the last present, e.g. by
+    /// [`VulkanoWindowRenderer::acquire`] after a resize. If so, framebuffers & descriptor sets
+    /// created from [`VulkanoWindowRenderer::swapchain_image_views`] are stale and must be
+    /// rebuilt before rendering the frame. To track recreations across frames, compare
+    /// [`VulkanoWindowRenderer::swapchain_generation`] instead.
+    #[inline]
+    pub fn swapchain_recreated(&self) -> bool {
+        self.swapchain_recreated
+    }
+
+    /// Number of times the swapchain was recreated, starting at 0. Store it along with resources
+    /// created from the swapchain images, e.g. framebuffers, and rebuild them when it differs.
+    /// Each recreation is also reported with a [`crate::SwapchainRecreated`] event.
+    #[inline]
+    pub fn swapchain_generation(&self) -> u64 {
+        self.swapchain_generation
+    }
+
+    /// Takes the swapchain generation if it changed since the last call
+    pub(crate) fn take_unreported_swapchain_generation(&mut self) -> Option<u64> {
+        if self.reported_swapchain_generation == self.swapchain_generation {
+            return None;
+        }
+        self.reported_swapchain_generation = self.swapchain_generation;
+        Some(self.swapchain_generation)
+    }
+
+    /// Return the current swapchain image view for binding as a storage image, e.g. to write the
+    /// frame directly from a compute shader with `WriteDescriptorSet::image_view`.
+    ///
+    /// Returns `None` unless the swapchain images have `STORAGE` usage (see
+    /// [`VulkanoWinitConfig::swapchain_image_usage`]) and their format supports storage images.
+    /// sRGB formats usually don't, so prefer e.g. `B8G8R8A8_UNORM` with
+    /// [`VulkanoWinitConfig::preferred_surface_formats`] and write sRGB encoded values.
+    ///
+    /// No manual layout transitions are needed: the command buffer transitions the image to
+    /// `General` for the dispatch, and it's transitioned to `PresentSrc` when passing the future
+    /// to [`VulkanoWindowRenderer::present`]. Record the dispatch for
+    /// [`VulkanoWindowRenderer::graphics_queue`], as swapchain images aren't shared with other
+    /// queue families.
+    pub fn swapchain_storage_image_view(&self) -> Option<SwapchainImageView> {
+        let image_view = self.swapchain_image_view();
+        let supports_storage = self
+            .swapchain
+            .device()
+            .physical_device()
+            .format_properties(self.swapchain_format())
+            .is_ok_and(|properties| {
+                properties
+                    .optimal_tiling_features
+                    .intersects(FormatFeatures::STORAGE_IMAGE)
+            });
+        (image_view.usage().intersects(ImageUsage::STORAGE) && supports_storage)
+            .then_some(image_view)
+    }
+
+    /// Return the depth attachment view. This is `None` unless
+    /// [`VulkanoWinitConfig::depth_format`] was set. The view is recreated along with the
+    /// swapchain, so fetch it each frame instead of holding on to it.
+    #[inline]
+    pub fn depth_image_view(&self) -> Option<DepthImageView> {
+        self.depth_view.clone()
+    }
+
+    /// Return the current frame's target for recording commands on another thread. Call this after
+    /// [`VulkanoWindowRenderer::acquire`].
+    #[inline]
+    pub fn frame_target(&self) -> WindowFrameTarget {
+        WindowFrameTarget {
+            image_view: self.swapchain_image_view(),
+            depth_view: self.depth_image_view(),
+            graphics_queue: self.graphics_queue(),
+            image_size: self.swapchain_image_size(),
+        }
+    }
+
+    /// Whether this renderer owns a depth attachment.
+    #[inline]
+    pub fn has_depth(&self) -> bool {
+        self.depth_view.is_some()
+    }
+
+    /// Whether the depth attachment was placed in lazily allocated memory, or `None` without a
+    /// depth attachment. Devices without lazily allocated memory types fall back to device local
+    /// memory, so this is mostly `false` on desktop GPUs.
+    #[inline]
+    pub fn depth_lazily_allocated(&self) -> Option<bool> {
+        self.depth_view
+            .as_ref()
+            .map(|view| view.image().is_lazily_allocated())
+    }
+
+    /// Return scale factor accounted window size.
+    #[inline]
+    pub fn resolution(&self) -> [f32; 2] {
+        let size = self.window().inner_size();
+        let scale_factor = self.window().scale_factor();
+        [
+            (size.width as f64 / scale_factor) as f32,
+            (size.height as f64 / scale_factor) as f32,
+        ]
+    }
+
+    /// Maps a cursor position to pixel coordinates of an image stretched over the whole window.
+    ///
+    /// `cursor_position` is in logical pixels with the origin at the bottom left, as reported by
+    /// bevy's `Window::cursor_position`. The result has its origin at the top left like Vulkan
+    /// images and is clamped to `image_dimensions`, see [`util::window_to_image_coords`]. Returns
+    /// `None` if the window is minimized or the image is empty.
+    pub fn cursor_to_image_coords(
+        &self,
+        cursor_position: Vec2,
+        image_dimensions: [u32; 2],
+    ) -> Option<IVec2> {
+        let size = self.window().inner_size();
+        util::window_to_image_coords(
+            cursor_position * self.window().scale_factor() as f32,
+            [size.width, size.height],
+            image_dimensions,
+        )
+    }
+
+    /// Whether the window is minimized (its inner size is zero), meaning nothing can be rendered.
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        let size = self.window().inner_size();
+        size.width == 0 || size.height == 0
+    }
+
+    #[inline]
+    pub fn aspect_ratio(&self) -> f32 {
+        let dims = self.window_size();
+        dims[0] / dims[1]
+    }
+
+    /// Resize swapchain and camera view images at the beginning of next frame based on window
+    /// dimensions.
+    #[inline]
+    pub fn resize(&mut self) {
+        self.recreate_swapchain = true;
+    }
+
+    /// Recreates the swapchain now, at the current surface extent, instead of on next
+    /// [`VulkanoWindowRenderer::acquire`]. Waits for all frames in flight first, so that no GPU
+    /// work uses the old images, unless [`VulkanoWinitConfig::smooth_resize`] is set, in which
+    /// case those frames keep the old swapchain alive until they complete. The depth attachment
+    /// and additional image views are recreated too. Call it between
+    /// [`VulkanoWindowRenderer::present`] and the next acquire; calling it again without changes
+    /// rebuilds an identical swapchain. While the window is minimized, the recreation is deferred
+    /// to the acquire after it's restored.
+    pub fn recreate_swapchain(&mut self) {
+        if self.is_minimized() {
+            self.recreate_swapchain = true;
+            return;
+        }
+        if !self.smooth_resize {
+            self.wait_for_frames();
+        }
+        self.recreate_swapchain_and_views();
+        self.image_index = 0;
+        self.suboptimal = false;
+    }
+
+    /// Add interim image view that resizes with window.
+    #[inline]
+    pub fn add_additional_image_view(&mut self, key: usize, format: Format, usage: ImageUsage) {
+        let size = self.swapchain_image_size();
+        let image = StorageImage::general_purpose_image_view(
+            &*self.memory_allocator,
+            self.graphics_queue.clone(),
+            size,
+            format,
+            usage,
+        )
+        .unwrap();
+        self.additional_image_views.insert(key, image);
+    }
+
+    /// Get additional image view by key.
+    #[inline]
+    pub fn get_additional_image_view(&mut self, key: usize) -> DeviceImageView {
+        self.additional_image_views.get(&key).unwrap().clone()
+    }
+
+    /// Remove additional image by key.
+    #[inline]
+    pub fn remove_additional_image_view(&mut self, key: usize) {
+        self.additional_image_views.remove(&key);
+    }
+
+    /// Add a resource of type `T` for each swapchain image, created by `create` given the image
+    /// index, replacing previous resources of that type. See [`PerSwapchainImage`].
+    pub fn add_per_image<T: 'static>(&mut self, create: impl FnMut(usize) -> T + 'static) {
+        self.per_image_resources.insert(
+            TypeId::of::<T>(),
+            Box::new(PerSwapchainImage::new(self.final_views.len(), create)),
+        );
+    }
+
+    /// Get the resource of type `T` for the current swapchain image, i.e.
+    /// [`VulkanoWindowRenderer::image_index`]. `None` unless added with
+    /// [`VulkanoWindowRenderer::add_per_image`].
+    #[inline]
+    pub fn per_image<T: 'static>(&self) -> Option<&T> {
+        self.per_swapchain_image::<T>()?
+            .get(self.image_index as usize)
+    }
+
+    /// Get the resource of type `T` for the current swapchain image, i.e.
+    /// [`VulkanoWindowRenderer::image_index`]. `None` unless added with
+    /// [`VulkanoWindowRenderer::add_per_image`].
+    #[inline]
+    pub fn per_image_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        let image_index = self.image_index as usize;
+        self.per_swapchain_image_mut::<T>()?.get_mut(image_index)
+    }
+
+    /// Get the resources of type `T` of all swapchain images.
+    pub fn per_swapchain_image<T: 'static>(&self) -> Option<&PerSwapchainImage<T>> {
+        self.per_image_resources
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref()
+    }
+
+    /// Get the resources of type `T` of all swapchain images.
+    pub fn per_swapchain_image_mut<T: 'static>(&mut self) -> Option<&mut PerSwapchainImage<T>> {
+        self.per_image_resources
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// Remove the resources of type `T`.
+    #[inline]
+    pub fn remove_per_image<T: 'static>(&mut self) {
+        self.per_image_resources.remove(&TypeId::of::<T>());
+    }
+
+    /// Attach `data` of type `T` to this window, e.g. its role or render settings, so that systems
+    /// can dispatch per window without bookkeeping of their own. One value is kept per type and
+    /// it lives as long as the renderer, across swapchain recreations. Returns the previous value
+    /// of that type.
+    pub fn set_user_data<T: Any>(&mut self, data: T) -> Option<T> {
+        self.user_data
+            .insert(TypeId::of::<T>(), Box::new(data))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Get the data of type `T` attached with [`VulkanoWindowRenderer::set_user_data`]
+    #[inline]
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        self.user_data.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Get the data of type `T` attached with [`VulkanoWindowRenderer::set_user_data`]
+    #[inline]
+    pub fn user_data_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.user_data.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Detach and return the data of type `T`
+    pub fn remove_user_data<T: Any>(&mut self) -> Option<T> {
+        self.user_data
+            .remove(&TypeId::of::<T>())
+            .and_then(|data| data.downcast().ok())
+            .map(|data| *data)
+    }
+
+    /// Color the swapchain image is cleared with when nothing else was rendered to it.
+    #[inline]
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Set the color the swapchain image is cleared with when nothing else was rendered to it, see
+    /// [`VulkanoWindowRenderer::clear_swapchain_image`]. Default is transparent black.
+    #[inline]
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    /// Records a clear of the current swapchain image with
+    /// [`VulkanoWindowRenderer::clear_color`]. This is done automatically for frames nothing was
+    /// chained to when [`VulkanoWinitConfig::auto_present`] is set.
+    ///
+    /// Returns the `before_future` as is if the swapchain images don't support `TRANSFER_DST`
+    /// usage.
+    pub fn clear_swapchain_image(&self, before_future: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        if !self
+            .swapchain
+            .image_usage()
+            .intersects(ImageUsage::TRANSFER_DST)
+        {
+            return before_future;
+        }
+        let image = self.final_views[self.image_index as usize].image().clone();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.graphics_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .clear_color_image(ClearColorImageInfo {
+                clear_value: ClearColorValue::Float(self.clear_color),
+                ..ClearColorImageInfo::image(image)
+            })
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        before_future
+            .then_execute(self.graphics_queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    /// Records a copy of the current swapchain image into a host visible buffer, e.g. for
+    /// screenshots. Call this after your rendering, right before
+    /// [`VulkanoWindowRenderer::present`], and pass the returned future to `present`. The buffer
+    /// can be read once that future has been waited on (`present(future, true)`).
+    ///
+    /// Returns `None` (and the `before_future` as is) if the swapchain images don't support
+    /// `TRANSFER_SRC` usage.
+    pub fn copy_swapchain_to_cpu(
+        &self,
+        before_future: Box<dyn GpuFuture>,
+    ) -> (Option<SwapchainReadback>, Box<dyn GpuFuture>) {
+        if !self
+            .swapchain
+            .image_usage()
+            .intersects(ImageUsage::TRANSFER_SRC)
+        {
+            return (None, before_future);
+        }
+        let image = self.final_views[self.image_index as usize].image().clone();
+        let format = self.swapchain.image_format();
+        let extent = self.swapchain.image_extent();
+        let texel_size = format.block_size().unwrap();
+        let buffer = Buffer::new_slice::<u8>(
+            &*self.memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Download,
+                ..Default::default()
+            },
+            extent[0] as DeviceSize * extent[1] as DeviceSize * texel_size,
+        )
+        .unwrap();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.graphics_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        // Layout transitions of the swapchain image are handled by vulkano's synchronization
+        builder
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image, buffer.clone()))
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        let after_future = before_future
+            .then_execute(self.graphics_queue.clone(), command_buffer)
+            .unwrap();
+        (
+            Some(SwapchainReadback {
+                buffer,
+                format,
+                extent,
+                row_stride: extent[0] * texel_size as u32,
+            }),
+            after_future.boxed(),
+        )
+    }
+
+    /// Begin your rendering by calling `acquire`.
+    /// Returns a [`GpuFuture`](vulkano::sync::GpuFuture) representing the time after which the
+    /// swapchain image has been acquired and previous frame ended.
+    /// Execute your command buffers after calling this function and finish rendering by calling
+    /// [`VulkanoWindowRenderer::present`].
+    ///
+    /// Returns [`VulkanoRendererError::WindowMinimized`] while the window has a zero extent, in
+    /// which case you should skip rendering the frame. Returns [`VulkanoRendererError::Timeout`]
+    /// if no image became available within [`VulkanoWindowRenderer::acquire_timeout`].
+    #[inline]
+    pub fn acquire(&mut self) -> std::result::Result<Box<dyn GpuFuture>, VulkanoRendererError> {
+        // A zero sized swapchain can't be created. Recreate it once the window is restored
+        if self.is_minimized() {
+            self.recreate_swapchain = true;
+            return Err(VulkanoRendererError::WindowMinimized);
+        }
+        // Recreate swap chain if needed (when resizing of window occurs or swapchain is outdated)
+        // Also resize render views if needed
+        if self.recreate_swapchain {
+            self.recreate_swapchain_and_views();
+        }
+
+        self.wait_for_present_latency();
+
+        // Acquire next image in the swapchain
+        let (image_index, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(self.swapchain.clone(), self.acquire_timeout) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    self.recreate_swapchain = true;
+                    return Err(VulkanoRendererError::OutOfDate);
+                }
+                Err(AcquireError::FullScreenExclusiveModeLost) => {
+                    self.on_full_screen_exclusive_lost();
+                    return Err(VulkanoRendererError::FullScreenExclusiveLost);
+                }
+                Err(e) => return Err(e.into()),
+            };
+        // A suboptimal image can still be presented. Recreate lazily on next acquire instead of
+        // rebuilding the swapchain mid-frame
+        self.suboptimal = suboptimal;
+        if suboptimal {
+            self.recreate_swapchain = true;
+        }
+        // Update our image index
+        self.image_index = image_index;
+
+        // Wait for the frame which last used this frame slot
+        if self.wait_frame_fences {
+            if let Some(fence) = &self.frame_fences[self.current_frame] {
+                fence.wait(None)?;
+            }
+        }
+        let previous_frame_end = match self.frame_fences[self.previous_frame].clone() {
+            Some(mut fence) => {
+                fence.cleanup_finished();
+                fence.boxed()
+            }
+            None => sync::now(self.graphics_queue.device().clone()).boxed(),
+        };
+        let future = previous_frame_end.join(acquire_future);
+
+        Ok(future.boxed())
+    }
+
+    /// Finishes rendering by presenting the swapchain. Pass your last future as an input to this
+    /// function.
+    ///
+    /// Depending on your implementation, you may want to wait on your future. For example, a
+    /// compute shader dispatch using an image that's being later drawn should probably be waited
+    /// on.
+    ///
+    /// On error, the frame is dropped and the next frame starts from a clean state. An
+    /// [`VulkanoRendererError::OutOfDate`] swapchain is recreated on next acquire.
+    #[inline]
+    pub fn present(
+        &mut self,
+        after_future: Box<dyn GpuFuture>,
+        wait_future: bool,
+    ) -> std::result::Result<(), VulkanoRendererError> {
+        let fence = self.present_with_fence(after_future)?;
+        // wait allows you to organize resource waiting yourself.
+        if wait_future {
+            fence.wait(None)?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`VulkanoWindowRenderer::present`] without waiting, but returns the fence signaled
+    /// when the frame's GPU work has completed. Poll it with `is_signaled` or wait on it with a
+    /// timeout, e.g. to measure latency or to know when resources used by the frame can be
+    /// reused.
+    pub fn present_with_fence(
+        &mut self,
+        after_future: Box<dyn GpuFuture>,
+    ) -> std::result::Result<FrameFence, VulkanoRendererError> {
+        self.present_with_regions(after_future, &[])
+    }
+
+    /// Same as [`VulkanoWindowRenderer::present_with_fence`], but hints that only `regions` of the
+    /// image changed since the previous present, so the presentation engine may update just those
+    /// (e.g. mostly static UI). Regions are clipped to the image. They're ignored if
+    /// `VK_KHR_incremental_present` isn't supported, see
+    /// [`VulkanoWindowRenderer::incremental_present_enabled`]. Empty `regions` update the whole
+    /// image.
+    pub fn present_with_regions(
+        &mut self,
+        after_future: Box<dyn GpuFuture>,
+        regions: &[RectangleLayer],
+    ) -> std::result::Result<FrameFence, VulkanoRendererError> {
+        self.limit_frame_rate();
+        let present_regions = if self.incremental_present {
+            let extent = self.swapchain.image_extent();
+            regions
+                .iter()
+                .filter_map(|region| clip_region(region, extent))
+                .collect()
+        } else {
+            vec![]
+        };
+        let present_id = self.next_present_id();
+        let future = after_future
+            .then_swapchain_present(self.graphics_queue.clone(), SwapchainPresentInfo {
+                present_id,
+                present_regions,
+                ..SwapchainPresentInfo::swapchain_image_index(
+                    self.swapchain.clone(),
+                    self.image_index,
+                )
+            })
+            .boxed()
+            .then_signal_fence_and_flush();
+        self.finish_present(future, present_id)
+    }
+
+    /// Id of the next present, if presents can be waited on
+    fn next_present_id(&mut self) -> Option<NonZeroU64> {
+        if self.present_wait {
+            self.present_id += 1;
+            NonZeroU64::new(self.present_id)
+        } else {
+            None
+        }
+    }
+
+    /// Records the result of a present and moves on to the next frame slot
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn finish_present(
+        &mut self,
+        future: std::result::Result<FenceSignalFuture<Box<dyn GpuFuture>>, FlushError>,
+        present_id: Option<NonZeroU64>,
+    ) -> std::result::Result<FrameFence, VulkanoRendererError> {
+        let result = match future {
+            Ok(mut future) => {
+                if let Some(present_id) = present_id {
+                    if self.max_frame_latency.is_some() {
+                        self.pending_presents
+                            .push_back((present_id.get(), Instant::now()));
+                    }
+                    self.last_present_id = Some(present_id.get());
+                }
+                future.cleanup_finished();
+                self.unreported_presents
+                    .push((self.frames_presented, Instant::now()));
+                self.frames_presented += 1;
+                let fence = Arc::new(future);
+                self.frame_fences[self.current_frame] = Some(fence.clone());
+                Ok(fence)
+            }
+            Err(e) => {
+                match e {
+                    FlushError::OutOfDate => self.recreate_swapchain = true,
+                    FlushError::FullScreenExclusiveModeLost => self.on_full_screen_exclusive_lost(),
+                    _ => {}
+                }
+                self.frame_fences[self.current_frame] = None;
+                Err(e.into())
+            }
+        };
+        self.previous_frame = self.current_frame;
+        self.current_frame = (self.current_frame + 1) % self.frame_fences.len();
+        self.swapchain_recreated = false;
+        result
+    }
+
+    /// Same as [`VulkanoWindowRenderer::present_with_fence`], but asks the presentation engine
+    /// not to display the frame before `desired_present_time`, e.g. to pace media playback without
+    /// judder. The time is in nanoseconds of the presentation clock, like the times of
+    /// [`VulkanoWindowRenderer::past_presentation_timings`]: target e.g. the latest
+    /// `actual_present_time` plus a multiple of
+    /// [`VulkanoWindowRenderer::refresh_cycle_duration`]. The frame is shown at the first vblank at
+    /// or after that time, which works best with FIFO present modes.
+    ///
+    /// The time is passed with `VK_GOOGLE_display_timing`. Without it (see
+    /// [`VulkanoWinitConfig::display_timing`]) this warns once and presents immediately.
+    pub fn present_at(
+        &mut self,
+        after_future: Box<dyn GpuFuture>,
+        desired_present_time: u64,
+    ) -> std::result::Result<FrameFence, VulkanoRendererError> {
+        if !self.display_timing {
+            if !self.warned_display_timing {
+                bevy::log::warn!(
+                    "VK_GOOGLE_display_timing isn't enabled, presenting without target time"
+                );
+                self.warned_display_timing = true;
+            }
+            return self.present_with_fence(after_future);
+        }
+        self.limit_frame_rate();
+        let present_id = self.next_present_id();
+        // vulkano can't chain `VkPresentTimesInfoGOOGLE` into its presents, so the frame is
+        // flushed first and presented directly
+        let future = after_future
+            .then_signal_fence_and_flush()
+            .and_then(|future| {
+                self.queue_present_at(desired_present_time, present_id)?;
+                Ok(future)
+            });
+        self.finish_present(future, present_id)
+    }
+
+    /// Queues the present of the current image with a desired present time, after all work
+    /// submitted to the graphics queue so far, which includes the frame
+    fn queue_present_at(
+        &mut self,
+        desired_present_time: u64,
+        present_id: Option<NonZeroU64>,
+    ) -> std::result::Result<(), FlushError> {
+        let device = self.graphics_queue.device().clone();
+        // Default semaphores fail to create only when out of memory
+        let semaphore = Arc::new(Semaphore::from_pool(device.clone()).map_err(|e| match e {
+            SemaphoreError::OomError(e) => FlushError::OomError(e),
+            e => unreachable!("{}", e),
+        })?);
+        let present_time = ash::vk::PresentTimeGOOGLE {
+            present_id: self.frames_presented as u32,
+            desired_present_time,
+        };
+        let mut present_times_info = ash::vk::PresentTimesInfoGOOGLE {
+            swapchain_count: 1,
+            p_times: &present_time,
+            ..Default::default()
+        };
+        let present_id_value = present_id.map_or(0, NonZeroU64::get);
+        let present_id_info = ash::vk::PresentIdKHR {
+            swapchain_count: 1,
+            p_present_ids: &present_id_value,
+            ..Default::default()
+        };
+        if present_id.is_some() {
+            present_times_info.p_next = &present_id_info as *const _ as *const _;
+        }
+        let semaphore_handle = semaphore.handle();
+        let swapchain_handle = self.swapchain.handle();
+        let present_info = ash::vk::PresentInfoKHR {
+            p_next: &present_times_info as *const _ as *const _,
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &semaphore_handle,
+            swapchain_count: 1,
+            p_swapchains: &swapchain_handle,
+            p_image_indices: &self.image_index,
+            ..Default::default()
+        };
+        let queue_handle = self.graphics_queue.handle();
+        let result = self.graphics_queue.with(|mut queue| unsafe {
+            // A signal operation waits for all work submitted before it on the queue
+            queue.submit_unchecked(
+                [SubmitInfo {
+                    signal_semaphores: vec![SemaphoreSubmitInfo::semaphore(semaphore.clone())],
+                    ..Default::default()
+                }],
+                None,
+            )?;
+            Ok::<_, VulkanError>((device.fns().khr_swapchain.queue_present_khr)(
+                queue_handle,
+                &present_info,
+            ))
+        })?;
+        // Keep the semaphore until the image is presented again, which means the presentation
+        // engine has waited on it
+        let image_index = self.image_index as usize;
+        if self.present_semaphores.len() <= image_index {
+            self.present_semaphores.resize(image_index + 1, None);
+        }
+        self.present_semaphores[image_index] = Some(semaphore);
+        match result {
+            ash::vk::Result::SUCCESS => Ok(()),
+            ash::vk::Result::SUBOPTIMAL_KHR => {
+                self.recreate_swapchain = true;
+                Ok(())
+            }
+            e => Err(VulkanError::from(e).into()),
+        }
+    }
+
+    /// Duration of a display refresh cycle of the window's display, if
+    /// [`VulkanoWinitConfig::display_timing`] is enabled and supported.
+    pub fn refresh_cycle_duration(&self) -> Option<Duration> {
+        if !self.display_timing {
+            return None;
+        }
+        let device = self.swapchain.device();
+        let mut properties = ash::vk::RefreshCycleDurationGOOGLE::default();
+        let result = unsafe {
+            (device
+                .fns()
+                .google_display_timing
+                .get_refresh_cycle_duration_google)(
+                device.handle(),
+                self.swapchain.handle(),
+                &mut properties,
+            )
+        };
+        (result == ash::vk::Result::SUCCESS)
+            .then(|| Duration::from_nanos(properties.refresh_duration))
+    }
+
+    /// Timings of presents completed since the last call, oldest first, for pacing later
+    /// presents. Empty if [`VulkanoWinitConfig::display_timing`] isn't enabled or supported.
+    pub fn past_presentation_timings(&self) -> Vec<PresentTiming> {
+        if !self.display_timing {
+            return vec![];
+        }
+        let device = self.swapchain.device();
+        let get_past_presentation_timing = device
+            .fns()
+            .google_display_timing
+            .get_past_presentation_timing_google;
+        let mut timings = vec![];
+        unsafe {
+            let mut count = 0;
+            let result = get_past_presentation_timing(
+                device.handle(),
+                self.swapchain.handle(),
+                &mut count,
+                std::ptr::null_mut(),
+            );
+            if result != ash::vk::Result::SUCCESS {
+                bevy::log::debug!("Failed to get past presentation timings: {:?}", result);
+                return vec![];
+            }
+            timings.resize(
+                count as usize,
+                ash::vk::PastPresentationTimingGOOGLE::default(),
+            );
+            let result = get_past_presentation_timing(
+                device.handle(),
+                self.swapchain.handle(),
+                &mut count,
+                timings.as_mut_ptr(),
+            );
+            // Timings completed between the calls are reported next time
+            if result != ash::vk::Result::SUCCESS && result != ash::vk::Result::INCOMPLETE {
+                bevy::log::debug!("Failed to get past presentation timings: {:?}", result);
+                return vec![];
+            }
+            timings.truncate(count as usize);
+        }
+        timings
+            .into_iter()
+            .map(|timing| PresentTiming {
+                present_id: timing.present_id,
+                desired_present_time: timing.desired_present_time,
+                actual_present_time: timing.actual_present_time,
+                earliest_present_time: timing.earliest_present_time,
+                present_margin: timing.present_margin,
+            })
+            .collect()
+    }
+
+    /// Whether `VK_KHR_incremental_present` is enabled, so that regions passed to
+    /// [`VulkanoWindowRenderer::present_with_regions`] are used.
+    #[inline]
+    pub fn incremental_present_enabled(&self) -> bool {
+        self.incremental_present
+    }
+
+    /// Whether the swapchain was created for application controlled exclusive full-screen, i.e.
+    /// [`VulkanoWinitConfig::full_screen_exclusive`] is set, `VK_EXT_full_screen_exclusive` is
+    /// supported and the app runs on Windows.
+    #[inline]
+    pub fn full_screen_exclusive_supported(&self) -> bool {
+        self.swapchain.full_screen_exclusive() == FullScreenExclusive::ApplicationControlled
+    }
+
+    /// Takes exclusive access of the window's monitor, bypassing the compositor for lower latency
+    /// and better frame pacing. Make the window fullscreen first, e.g. with
+    /// `Fullscreen::Borderless`. Exclusive access is kept across swapchain recreations until
+    /// released, or lost e.g. when the user switches to another window, in which case acquire or
+    /// present return [`VulkanoRendererError::FullScreenExclusiveLost`] and the window falls back to
+    /// borderless fullscreen. Call this again to take it back.
+    ///
+    /// Fails unless [`VulkanoWindowRenderer::full_screen_exclusive_supported`], or if exclusive
+    /// access is already held.
+    pub fn acquire_full_screen_exclusive(&self) -> Result<(), FullScreenExclusiveError> {
+        self.swapchain.acquire_full_screen_exclusive()
+    }
+
+    /// Releases exclusive access taken with
+    /// [`VulkanoWindowRenderer::acquire_full_screen_exclusive`], e.g. before leaving fullscreen.
+    /// Fails if it isn't held.
+    pub fn release_full_screen_exclusive(&self) -> Result<(), FullScreenExclusiveError> {
+        self.swapchain.release_full_screen_exclusive()
+    }
+
+    /// Whether exclusive full-screen access is currently held.
+    #[inline]
+    pub fn is_full_screen_exclusive(&self) -> bool {
+        self.swapchain.is_full_screen_exclusive()
+    }
+
+    /// Falls back to borderless fullscreen on the current monitor after exclusive access was lost,
+    /// recreating the swapchain on next acquire. Windowed windows are left as is.
+    fn on_full_screen_exclusive_lost(&mut self) {
+        bevy::log::warn!("Lost exclusive full-screen access, falling back to borderless");
+        let window = self.window();
+        if matches!(window.fullscreen(), Some(Fullscreen::Exclusive(_))) {
+            window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+        }
+        self.recreate_swapchain = true;
+    }
+
+    /// Number of frames successfully presented so far. The next present has this frame index in
+    /// [`crate::FramePresented`].
+    #[inline]
+    pub fn frames_presented(&self) -> u64 {
+        self.frames_presented
+    }
+
+    /// Takes the frame indices & times of presents since the last call
+    pub(crate) fn take_unreported_presents(&mut self) -> Vec<(u64, Instant)> {
+        std::mem::take(&mut self.unreported_presents)
+    }
+
+    /// Maximum frames per second presented, if limited.
+    #[inline]
+    pub fn frame_rate_limit(&self) -> Option<f64> {
+        self.min_frame_time.map(|t| 1.0 / t.as_secs_f64())
+    }
+
+    /// Limit the frames per second presented, independent of the present mode. `None` or a
+    /// non-positive value removes the limit.
+    pub fn set_frame_rate_limit(&mut self, frame_rate_limit: Option<f64>) {
+        self.min_frame_time = frame_rate_limit
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps));
+    }
+
+    /// How long [`VulkanoWindowRenderer::acquire`] waits for a swapchain image before returning
+    /// [`VulkanoRendererError::Timeout`]. `None` waits indefinitely.
+    pub fn acquire_timeout(&self) -> Option<Duration> {
+        self.acquire_timeout
+    }
+
+    /// Sets how long [`VulkanoWindowRenderer::acquire`] waits for a swapchain image, see
+    /// [`VulkanoWinitConfig::acquire_timeout`]
+    pub fn set_acquire_timeout(&mut self, acquire_timeout: Option<Duration>) {
+        self.acquire_timeout = acquire_timeout;
+    }
+
+    /// Whether swapchain recreation skips waiting for frames in flight, see
+    /// [`VulkanoWinitConfig::smooth_resize`]
+    #[inline]
+    pub fn smooth_resize(&self) -> bool {
+        self.smooth_resize
+    }
+
+    /// Sets whether swapchain recreation skips waiting for frames in flight
+    #[inline]
+    pub fn set_smooth_resize(&mut self, smooth_resize: bool) {
+        self.smooth_resize = smooth_resize;
+    }
+
+    /// Waits until the minimum frame time since the previous present has passed. Time spent
+    /// rendering since then counts towards it.
+    fn limit_frame_rate(&mut self) {
+        if let (Some(min_frame_time), Some(last_present)) = (self.min_frame_time, self.last_present)
+        {
+            wait_until(last_present + min_frame_time);
+        }
+        self.last_present = Some(Instant::now());
+    }
+
+    /// Waits until fewer than `max_frame_latency` presents are queued, so that the frame about to
+    /// be acquired reflects the latest input. Records the latency of the last present waited on.
+    fn wait_for_present_latency(&mut self) {
+        let Some(max_frame_latency) = self.max_frame_latency else {
+            return;
+        };
+        while self.pending_presents.len() >= max_frame_latency.max(1) as usize {
+            let (present_id, presented_at) = self.pending_presents.pop_front().unwrap();
+            match swapchain::wait_for_present(
+                self.swapchain.clone(),
+                present_id,
+                Some(PRESENT_WAIT_TIMEOUT),
+            ) {
+                Ok(_) => self.present_latency = Some(presented_at.elapsed()),
+                Err(e) => {
+                    bevy::log::debug!("Failed to wait for present {}: {}", present_id, e);
+                    self.pending_presents.clear();
+                }
+            }
+        }
+    }
+
+    /// Blocks until the latest frame presented to this window is shown, if the device supports
+    /// present wait (see [`VulkanoWinitConfig::drive_schedule_on_present`]). Otherwise blocks
+    /// until the frame's GPU work has finished, which with FIFO present modes is paced by the
+    /// display through image acquisition. Does nothing if no frame was presented since the
+    /// swapchain was created.
+    pub fn wait_for_last_present(&mut self) {
+        if let Some(present_id) = self.last_present_id {
+            if let Err(e) = swapchain::wait_for_present(
+                self.swapchain.clone(),
+                present_id,
+                Some(PRESENT_WAIT_TIMEOUT),
+            ) {
+                bevy::log::debug!("Failed to wait for present {}: {}", present_id, e);
+                self.last_present_id = None;
+            }
+        } else if let Some(fence) = &self.frame_fences[self.previous_frame] {
+            if let Err(e) = fence.wait(None) {
+                bevy::log::warn!("Failed to wait for frame: {}", e);
+            }
+        }
+    }
+
+    /// Time from submitting the last present waited on until it was reported presented. Only
+    /// measured if [`VulkanoWinitConfig::max_frame_latency`] is set and the device supports
+    /// present wait, `None` otherwise.
+    #[inline]
+    pub fn present_latency(&self) -> Option<Duration> {
+        self.present_latency
+    }
+
+    /// Whether the current frame's image was acquired from a swapchain that no longer matches the
+    /// surface exactly (e.g. after a DPI change). The frame can still be rendered and presented,
+    /// the swapchain is recreated on next [`VulkanoWindowRenderer::acquire`].
+    #[inline]
+    pub fn is_suboptimal(&self) -> bool {
+        self.suboptimal
+    }
+
+    /// Blocks until all frames in flight of this window have finished on the GPU. Call this before
+    /// freeing resources used by those frames.
+    pub fn wait_for_frames(&mut self) {
+        for fence in self.frame_fences.iter_mut() {
+            if let Some(fence) = fence.take() {
+                if let Err(e) = fence.wait(None) {
+                    bevy::log::warn!("Failed to wait for frame: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Number of frame slots, see [`VulkanoWindowRenderer::current_frame_index`]. At least 1.
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frame_fences.len()
+    }
+
+    /// Index of the frame slot currently being rendered, in `0..frames_in_flight`. Use it to index
+    /// per-frame resources such as uniform buffers, which are then safe to write when the frame
+    /// is acquired. Always 0 if [`VulkanoWinitConfig::frames_in_flight`] is 0.
+    #[inline]
+    pub fn current_frame_index(&self) -> usize {
+        self.current_frame
+    }
+
+    /// Recreates swapchain images and image views which follow the window size.
+    fn recreate_swapchain_and_views(&mut self) {
+        let dimensions: [u32; 2] = self.window().inner_size().into();
+        let surface_capabilities = self
+            .swapchain
+            .device()
+            .physical_device()
+            .surface_capabilities(&self.surface, Default::default())
+            .unwrap();
+        // Follow the display rotation unless a supported transform was configured
+        let pre_transform = match self.requested_pre_transform {
+            Some(transform)
+                if surface_capabilities
+                    .supported_transforms
+                    .contains_enum(transform) =>
+            {
+                transform
+            }
+            _ => surface_capabilities.current_transform,
+        };
+        let dimensions = clamp_image_extent(dimensions, &surface_capabilities, pre_transform);
+        #[allow(unused_mut)]
+        let mut create_info = SwapchainCreateInfo {
+            image_extent: dimensions,
+            pre_transform,
+            min_image_count: clamp_image_count(self.min_image_count, &surface_capabilities),
+            // Use present mode from current state
+            present_mode: self.present_mode,
+            ..self.swapchain.create_info()
+        };
+        // The window may have moved to another monitor
+        #[cfg(target_os = "windows")]
+        if create_info.full_screen_exclusive == FullScreenExclusive::ApplicationControlled {
+            if let Some(monitor) = self.window().current_monitor() {
+                create_info.win32_monitor =
+                    Some(vulkano_win::create_win32_monitor_from_winit(&monitor));
+            }
+        }
+        let (new_swapchain, new_images) = match self.swapchain.recreate(create_info) {
+            Ok(r) => r,
+            // Capabilities may change again between the query & recreation, retry next frame
+            Err(SwapchainCreationError::ImageExtentNotSupported {
+                ..
+            }) => return,
+            Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
+        };
+
+        self.swapchain = new_swapchain;
+        let new_images = new_images
+            .into_iter()
+            .map(|image| ImageView::new_default(image).unwrap())
+            .collect::<Vec<_>>();
+        self.final_views = new_images;
+        self.swapchain_recreated = true;
+        self.swapchain_generation += 1;
+        // Presents to the old swapchain can't be waited on
+        self.pending_presents.clear();
+        self.last_present_id = None;
+        // Depth must always match the swapchain extent
+        self.create_depth_view();
+        // Resize images that follow swapchain size
+        let resizable_views = self
+            .additional_image_views
+            .iter()
+            .map(|c| *c.0)
+            .collect::<Vec<usize>>();
+        for i in resizable_views {
+            let format = self.get_additional_image_view(i).format().unwrap();
+            let usage = self.get_additional_image_view(i).usage();
+            self.remove_additional_image_view(i);
+            self.add_additional_image_view(i, format, usage);
+        }
+        let image_count = self.final_views.len();
+        for resources in self.per_image_resources.values_mut() {
+            resources.resize(image_count);
+        }
+        #[cfg(target_os = "ios")]
+        unsafe {
+            self.surface.update_ios_sublayer_on_resize();
+        }
+        self.recreate_swapchain = false;
+    }
+}
+
+/// What's needed to record commands targeting a window's current frame. Unlike
+/// [`VulkanoWindowRenderer`] this is `Send` & `Sync`, so windows' command buffers can be recorded
+/// on worker threads, see [`crate::BevyVulkanoWindows::frame_targets`].
+#[derive(Clone)]
+pub struct WindowFrameTarget {
+    pub image_view: SwapchainImageView,
+    pub depth_view: Option<DepthImageView>,
+    pub graphics_queue: Arc<Queue>,
+    pub image_size: [u32; 2],
+}
+
+/// Swapchain image contents copied to host visible memory by
+/// [`VulkanoWindowRenderer::copy_swapchain_to_cpu`].
+pub struct SwapchainReadback {
+    /// Tightly packed texels of the swapchain image in `format`
+    pub buffer: Subbuffer<[u8]>,
+    pub format: Format,
+    pub extent: [u32; 2],
+    /// Bytes per row
+    pub row_stride: u32,
+}
+
+impl SwapchainReadback {
+    /// Whether the swapchain format is sRGB encoded. Note that texels are stored as they're
+    /// presented in both cases, so no conversion is needed when saving them to e.g. PNG.
+    pub fn is_srgb(&self) -> bool {
+        self.format.type_color() == Some(NumericType::SRGB)
+    }
+
+    /// Returns the texels as RGBA8, swizzling BGRA formats. Returns `None` for formats other than
+    /// 8 bit RGBA / BGRA. The buffer must not be in use by the GPU anymore.
+    pub fn to_rgba8(&self) -> Option<Vec<u8>> {
+        let texels = self.buffer.read().ok()?;
+        match self.format {
+            Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => Some(texels.to_vec()),
+            Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => Some(
+                texels
+                    .chunks_exact(4)
+                    .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Clamps requested image count to what the surface supports. `None` uses surface minimum.
+fn clamp_image_count(requested: Option<u32>, surface_capabilities: &SurfaceCapabilities) -> u32 {
+    match requested {
+        Some(count) => {
+            let count = count.max(surface_capabilities.min_image_count);
+            surface_capabilities
+                .max_image_count
+                .map_or(count, |max| count.min(max))
+        }
+        None => surface_capabilities.min_image_count,
+    }
+}
+
+/// First of `requested` and its fallbacks which the surface supports. Falls back to `Fifo`, which
+/// all surfaces support:
+/// - `Immediate` (bevy's `AutoNoVsync`): `Immediate`, `Mailbox`, `Fifo`
+/// - `FifoRelaxed` (bevy's `AutoVsync`): `FifoRelaxed`, `Fifo`
+/// - `Mailbox`: `Mailbox`, `Fifo`, so that it doesn't tear
+/// - Others: `requested`, `Fifo`
+fn select_present_mode(requested: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    let fallbacks: &[PresentMode] = match requested {
+        PresentMode::Immediate => &[PresentMode::Mailbox],
+        _ => &[],
+    };
+    std::iter::once(requested)
+        .chain(fallbacks.iter().copied())
+        .find(|present_mode| supported.contains(present_mode))
+        .unwrap_or(PresentMode::Fifo)
+}
+
+/// Clips `region` to an image of `extent`, or `None` if nothing of it is left. Only single layer
+/// swapchains are created, so the layer is always 0.
+fn clip_region(region: &RectangleLayer, extent: [u32; 2]) -> Option<RectangleLayer> {
+    let offset = [
+        region.offset[0].min(extent[0]),
+        region.offset[1].min(extent[1]),
+    ];
+    let clipped_extent = [
+        region.extent[0].min(extent[0] - offset[0]),
+        region.extent[1].min(extent[1] - offset[1]),
+    ];
+    (clipped_extent[0] > 0 && clipped_extent[1] > 0).then_some(RectangleLayer {
+        offset,
+        extent: clipped_extent,
+        layer: 0,
+    })
+}
+
+/// Swapchain image extent for a window of `window_size`. Surface extent is authoritative when
+/// known, as window size may lag behind e.g. fullscreen transitions. Otherwise (current extent
+/// `0xFFFFFFFF`) the window size is used. Either is clamped to the supported extent range, which
+/// some compositors transiently violate during rapid resizes.
+fn clamp_image_extent(
+    window_size: [u32; 2],
+    surface_capabilities: &SurfaceCapabilities,
+    pre_transform: SurfaceTransform,
+) -> [u32; 2] {
+    let extent = surface_capabilities.current_extent.unwrap_or(window_size);
+    // Rotated by a quarter turn, images are in the display's native orientation while the extent
+    // follows the current orientation
+    let extent = if is_quarter_turn(pre_transform) {
+        [extent[1], extent[0]]
+    } else {
+        extent
+    };
+    let [min_width, min_height] = surface_capabilities.min_image_extent;
+    let [max_width, max_height] = surface_capabilities.max_image_extent;
+    [
+        extent[0].clamp(min_width, max_width.max(min_width)),
+        extent[1].clamp(min_height, max_height.max(min_height)),
+    ]
+}
+
+fn is_quarter_turn(transform: SurfaceTransform) -> bool {
+    matches!(
+        transform,
+        SurfaceTransform::Rotate90
+            | SurfaceTransform::Rotate270
+            | SurfaceTransform::HorizontalMirrorRotate90
+            | SurfaceTransform::HorizontalMirrorRotate270
+    )
+}
+
+/// Error returned by [`VulkanoWindowRenderer::set_present_mode`] when the surface doesn't support
+/// the requested present mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentModeNotSupported(pub PresentMode);
+
+impl fmt::Display for PresentModeNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "present mode {:?} is not supported by the surface",
+            self.0
+        )
+    }
+}
+
+impl Error for PresentModeNotSupported {}
+
+/// Errors that can happen when acquiring or presenting a frame with [`VulkanoWindowRenderer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VulkanoRendererError {
+    /// The swapchain no longer matches the surface and will be recreated on next acquire.
+    OutOfDate,
+    /// The operation didn't complete in time.
+    Timeout,
+    /// The logical device was lost (e.g. driver reset).
+    DeviceLost,
+    /// The surface is no longer available.
+    SurfaceLost,
+    /// The window is minimized and has a zero extent. Skip rendering this frame.
+    WindowMinimized,
+    /// Exclusive full-screen access was lost, see
+    /// [`VulkanoWindowRenderer::acquire_full_screen_exclusive`]. The window falls back to
+    /// borderless fullscreen and the swapchain is recreated on next acquire.
+    FullScreenExclusiveLost,
+    /// Any other error when acquiring the next swapchain image.
+    Acquire(AcquireError),
+    /// Any other error when flushing the frame for presentation.
+    Flush(FlushError),
+}
+
+impl fmt::Display for VulkanoRendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VulkanoRendererError::OutOfDate => write!(f, "swapchain is out of date"),
+            VulkanoRendererError::Timeout => write!(f, "timed out"),
+            VulkanoRendererError::DeviceLost => write!(f, "device was lost"),
+            VulkanoRendererError::SurfaceLost => write!(f, "surface was lost"),
+            VulkanoRendererError::WindowMinimized => write!(f, "window is minimized"),
+            VulkanoRendererError::FullScreenExclusiveLost => {
+                write!(f, "exclusive full-screen access was lost")
+            }
+            VulkanoRendererError::Acquire(e) => write!(f, "failed to acquire next image: {}", e),
+            VulkanoRendererError::Flush(e) => write!(f, "failed to flush future: {}", e),
+        }
+    }
+}
+
+impl Error for VulkanoRendererError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VulkanoRendererError::Acquire(e) => Some(e),
+            VulkanoRendererError::Flush(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<AcquireError> for VulkanoRendererError {
+    fn from(err: AcquireError) -> Self {
+        match err {
+            AcquireError::OutOfDate => VulkanoRendererError::OutOfDate,
+            AcquireError::Timeout => VulkanoRendererError::Timeout,
+            AcquireError::DeviceLost => VulkanoRendererError::DeviceLost,
+            AcquireError::SurfaceLost => VulkanoRendererError::SurfaceLost,
+            AcquireError::FullScreenExclusiveModeLost => {
+                VulkanoRendererError::FullScreenExclusiveLost
+            }
+            e => VulkanoRendererError::Acquire(e),
+        }
+    }
+}
+
+impl From<FlushError> for VulkanoRendererError {
+    fn from(err: FlushError) -> Self {
+        match err {
+            FlushError::OutOfDate => VulkanoRendererError::OutOfDate,
+            FlushError::Timeout => VulkanoRendererError::Timeout,
+            FlushError::DeviceLost => VulkanoRendererError::DeviceLost,
+            FlushError::SurfaceLost => VulkanoRendererError::SurfaceLost,
+            FlushError::FullScreenExclusiveModeLost => {
+                VulkanoRendererError::FullScreenExclusiveLost
+            }
+            e => VulkanoRendererError::Flush(e),
+        }
+    }
+}
+
+/// Sleeps for most of the time until `deadline` and spins for the rest, because sleeps overshoot
+/// by up to a scheduler tick.
+fn wait_until(deadline: Instant) {
+    const SPIN_TIME: Duration = Duration::from_millis(1);
+    let now = Instant::now();
+    if deadline > now + SPIN_TIME {
+        std::thread::sleep(deadline - now - SPIN_TIME);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::swapchain::PresentMode;
+
+    use super::select_present_mode;
+
+    const FIFO_ONLY: &[PresentMode] = &[PresentMode::Fifo];
+    const ALL: &[PresentMode] = &[
+        PresentMode::Immediate,
+        PresentMode::Mailbox,
+        PresentMode::Fifo,
+        PresentMode::FifoRelaxed,
+    ];
+
+    #[test]
+    fn auto_vsync_prefers_fifo_relaxed() {
+        assert_eq!(
+            select_present_mode(PresentMode::FifoRelaxed, ALL),
+            PresentMode::FifoRelaxed
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::FifoRelaxed, &[
+                PresentMode::Mailbox,
+                PresentMode::Fifo
+            ]),
+            PresentMode::Fifo
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::FifoRelaxed, FIFO_ONLY),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn auto_no_vsync_falls_back_to_mailbox_then_fifo() {
+        assert_eq!(
+            select_present_mode(PresentMode::Immediate, ALL),
+            PresentMode::Immediate
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::Immediate, &[
+                PresentMode::Mailbox,
+                PresentMode::Fifo,
+                PresentMode::FifoRelaxed
+            ]),
+            PresentMode::Mailbox
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::Immediate, &[
+                PresentMode::Fifo,
+                PresentMode::FifoRelaxed
+            ]),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn unsupported_explicit_modes_fall_back_to_fifo() {
+        assert_eq!(
+            select_present_mode(PresentMode::Mailbox, ALL),
+            PresentMode::Mailbox
+        );
+        // Mailbox doesn't tear, so it never falls back to Immediate
+        assert_eq!(
+            select_present_mode(PresentMode::Mailbox, &[
+                PresentMode::Immediate,
+                PresentMode::Fifo
+            ]),
+            PresentMode::Fifo
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::Fifo, FIFO_ONLY),
+            PresentMode::Fifo
+        );
+    }
+}
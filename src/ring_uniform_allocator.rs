@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    memory::allocator::{AllocationCreateInfo, MemoryUsage},
+    sync::FlushError,
+    DeviceSize,
+};
+
+use crate::{BevyVulkanoContext, SyncData};
+
+/// Hands out per-frame uniform data from one large host visible buffer instead of allocating a
+/// new buffer for each uniform.
+///
+/// The buffer is split into a region per frame in flight. Call
+/// [`RingUniformAllocator::begin_frame`] with the window's [`SyncData`] once its frame is
+/// acquired, e.g. in [`VulkanoRenderSet::Render`](crate::VulkanoRenderSet::Render). It waits for
+/// the frames that last used the region about to be reused. Regions follow the frame slots of one
+/// window, so use an allocator per window.
+pub struct RingUniformAllocator {
+    buffer: Subbuffer<[u8]>,
+    region_size: DeviceSize,
+    regions: usize,
+    /// Offset of the current frame's region
+    region_offset: DeviceSize,
+    /// Bytes allocated in the current frame's region
+    cursor: AtomicU64,
+    /// `minUniformBufferOffsetAlignment` of the device
+    alignment: DeviceSize,
+}
+
+impl RingUniformAllocator {
+    /// Creates a buffer with `frames_in_flight` regions of `region_size` bytes each, which should
+    /// match [`VulkanoWinitConfig::frames_in_flight`](crate::VulkanoWinitConfig::frames_in_flight)
+    /// and fit all uniforms allocated in a frame.
+    pub fn new(
+        context: &BevyVulkanoContext,
+        region_size: DeviceSize,
+        frames_in_flight: usize,
+    ) -> RingUniformAllocator {
+        let regions = frames_in_flight.max(1);
+        let buffer = Buffer::new_slice::<u8>(
+            &**context.context.memory_allocator(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            region_size * regions as DeviceSize,
+        )
+        .expect("Failed to create uniform ring buffer");
+        RingUniformAllocator {
+            buffer,
+            region_size,
+            regions,
+            region_offset: 0,
+            cursor: AtomicU64::new(0),
+            alignment: context
+                .context
+                .device()
+                .physical_device()
+                .properties()
+                .min_uniform_buffer_offset_alignment
+                .as_devicesize(),
+        }
+    }
+
+    /// Starts allocating from the region of the frame `sync_data` is rendering, discarding what
+    /// was allocated in it previously. Blocks until the GPU has finished the frames that last used
+    /// the region, which are usually done by the time their slot is acquired again.
+    pub fn begin_frame(&mut self, sync_data: &SyncData) -> Result<(), FlushError> {
+        let region = sync_data.frame_index % self.regions;
+        // Slots share regions when the window has more frames in flight than the allocator
+        for frame_index in (region..sync_data.frame_in_flight_count()).step_by(self.regions) {
+            sync_data.wait_for_frame(frame_index)?;
+        }
+        self.region_offset = region as DeviceSize * self.region_size;
+        *self.cursor.get_mut() = 0;
+        Ok(())
+    }
+
+    /// Writes `data` to the current frame's region. Returns the subbuffer holding it and its
+    /// offset in [`RingUniformAllocator::buffer`], e.g. for dynamic uniform buffer offsets.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the region is full, see [`RingUniformAllocator::new`]
+    pub fn allocate<T: BufferContents>(&self, data: T) -> (Subbuffer<T>, DeviceSize) {
+        let size = std::mem::size_of::<T>() as DeviceSize;
+        let alignment = self.alignment.max(std::mem::align_of::<T>() as DeviceSize);
+        let mut start = 0;
+        self.cursor
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |cursor| {
+                start = cursor.next_multiple_of(alignment);
+                (start + size <= self.region_size).then_some(start + size)
+            })
+            .unwrap_or_else(|_| {
+                panic!(
+                    "Uniform ring region of {} bytes is full, create the allocator with a larger \
+                     region size",
+                    self.region_size
+                )
+            });
+        let offset = self.region_offset + start;
+        let subbuffer =
+            Subbuffer::<T>::try_from_bytes(self.buffer.clone().slice(offset..offset + size))
+                .unwrap();
+        // The region isn't used by the GPU since begin_frame waited for its frames
+        *subbuffer.write().unwrap() = data;
+        (subbuffer, offset)
+    }
+
+    /// The whole ring buffer
+    pub fn buffer(&self) -> &Subbuffer<[u8]> {
+        &self.buffer
+    }
+
+    /// Size of a frame's region in bytes
+    pub fn region_size(&self) -> DeviceSize {
+        self.region_size
+    }
+}
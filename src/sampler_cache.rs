@@ -0,0 +1,95 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::utils::HashMap;
+use vulkano::{
+    device::Device,
+    pipeline::graphics::depth_stencil::CompareOp,
+    sampler::{
+        BorderColor, Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerCreationError,
+        SamplerMipmapMode, SamplerReductionMode,
+    },
+};
+
+/// Shares samplers between pipelines, creating each distinct sampler only once. Access it through
+/// [`BevyVulkanoContext::get_sampler`](crate::BevyVulkanoContext::get_sampler). Lookups lock
+/// internally, so it can be used from systems running in parallel.
+pub struct SamplerCache {
+    device: Arc<Device>,
+    samplers: Mutex<HashMap<SamplerKey, Arc<Sampler>>>,
+}
+
+impl SamplerCache {
+    pub fn new(device: Arc<Device>) -> SamplerCache {
+        SamplerCache {
+            device,
+            samplers: Mutex::new(HashMap::default()),
+        }
+    }
+
+    /// Returns the sampler created with `create_info`, creating it on first use.
+    pub fn get(
+        &self,
+        create_info: SamplerCreateInfo,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let key = SamplerKey::new(&create_info);
+        let mut samplers = self.samplers.lock().unwrap();
+        if let Some(sampler) = samplers.get(&key) {
+            return Ok(sampler.clone());
+        }
+        let sampler = Sampler::new(self.device.clone(), create_info)?;
+        samplers.insert(key, sampler.clone());
+        Ok(sampler)
+    }
+
+    /// Number of distinct samplers created so far
+    pub fn len(&self) -> usize {
+        self.samplers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Hashable copy of a [`SamplerCreateInfo`]. Floats are compared bitwise, and the YCbCr conversion
+/// by address, which stays unique as long as the cached sampler keeps the conversion alive.
+#[derive(PartialEq, Eq, Hash)]
+struct SamplerKey {
+    mag_filter: Filter,
+    min_filter: Filter,
+    mipmap_mode: SamplerMipmapMode,
+    address_mode: [SamplerAddressMode; 3],
+    mip_lod_bias: u32,
+    anisotropy: Option<u32>,
+    compare: Option<CompareOp>,
+    lod: [u32; 2],
+    border_color: BorderColor,
+    unnormalized_coordinates: bool,
+    reduction_mode: SamplerReductionMode,
+    sampler_ycbcr_conversion: Option<usize>,
+}
+
+impl SamplerKey {
+    fn new(create_info: &SamplerCreateInfo) -> SamplerKey {
+        SamplerKey {
+            mag_filter: create_info.mag_filter,
+            min_filter: create_info.min_filter,
+            mipmap_mode: create_info.mipmap_mode,
+            address_mode: create_info.address_mode,
+            mip_lod_bias: create_info.mip_lod_bias.to_bits(),
+            anisotropy: create_info.anisotropy.map(f32::to_bits),
+            compare: create_info.compare,
+            lod: [
+                create_info.lod.start().to_bits(),
+                create_info.lod.end().to_bits(),
+            ],
+            border_color: create_info.border_color,
+            unnormalized_coordinates: create_info.unnormalized_coordinates,
+            reduction_mode: create_info.reduction_mode,
+            sampler_ycbcr_conversion: create_info
+                .sampler_ycbcr_conversion
+                .as_ref()
+                .map(|conversion| Arc::as_ptr(conversion) as usize),
+        }
+    }
+}
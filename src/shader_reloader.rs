@@ -0,0 +1,236 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::*,
+};
+use vulkano::{
+    device::Device,
+    shader::{ShaderCreationError, ShaderModule},
+};
+
+use crate::BevyVulkanoContext;
+
+/// Adds [`ShaderReloader`] and [`ShaderReloaded`] events, polling watched SPIR-V files for changes
+/// every `poll_interval`. Requires [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) or
+/// [`HeadlessVulkanoPlugin`](crate::HeadlessVulkanoPlugin) to be added first.
+pub struct ShaderReloadPlugin {
+    pub poll_interval: Duration,
+}
+
+impl Default for ShaderReloadPlugin {
+    fn default() -> Self {
+        ShaderReloadPlugin {
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+impl Plugin for ShaderReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShaderReloader::new(self.poll_interval))
+            .add_event::<ShaderReloaded>()
+            .add_system(reload_shaders_system.in_base_set(CoreSet::First));
+    }
+}
+
+/// Identifies a shader watched by [`ShaderReloader`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchedShaderId(usize);
+
+/// Sent when a watched shader file changed and its new module was created. Rebuild the pipelines
+/// using the shader with [`ShaderReloaded::module`], keeping the old pipelines if that fails. The
+/// device has been waited idle, so the old pipelines can be dropped right away.
+pub struct ShaderReloaded {
+    pub id: WatchedShaderId,
+    pub path: PathBuf,
+    pub module: Arc<ShaderModule>,
+}
+
+/// Watches SPIR-V files during development and recreates their shader modules when they change,
+/// e.g. when compiled by `glslc` in watch mode. GLSL sources aren't compiled at runtime, point the
+/// reloader at the compiled `.spv` files instead.
+///
+/// Files are polled by modification time, see [`ShaderReloadPlugin`]. If a changed file can't be
+/// loaded (e.g. invalid SPIR-V, or read while still being written), the error is logged and the
+/// previous module is kept until the file changes again.
+#[derive(Resource)]
+pub struct ShaderReloader {
+    shaders: Vec<WatchedShader>,
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+struct WatchedShader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    module: Arc<ShaderModule>,
+}
+
+impl ShaderReloader {
+    pub fn new(poll_interval: Duration) -> ShaderReloader {
+        ShaderReloader {
+            shaders: vec![],
+            poll_interval,
+            last_poll: None,
+        }
+    }
+
+    /// Loads the SPIR-V file at `path` and starts watching it for changes. Returns the id that
+    /// [`ShaderReloaded`] events of this file carry, and the initial module.
+    ///
+    /// # Safety
+    ///
+    /// The file, and every version of it reloaded later, must be valid SPIR-V, see
+    /// [`ShaderModule::from_bytes`].
+    pub unsafe fn watch(
+        &mut self,
+        device: Arc<Device>,
+        path: impl Into<PathBuf>,
+    ) -> Result<(WatchedShaderId, Arc<ShaderModule>), ShaderReloadError> {
+        let path = path.into();
+        let modified = modified_time(&path);
+        let module = load_shader(device, &path)?;
+        self.shaders.push(WatchedShader {
+            path,
+            modified,
+            module: module.clone(),
+        });
+        Ok((WatchedShaderId(self.shaders.len() - 1), module))
+    }
+
+    /// Latest module successfully loaded for `id`
+    pub fn module(&self, id: WatchedShaderId) -> Arc<ShaderModule> {
+        self.shaders[id.0].module.clone()
+    }
+
+    /// File watched for `id`
+    pub fn path(&self, id: WatchedShaderId) -> &Path {
+        &self.shaders[id.0].path
+    }
+
+    /// Reloads shaders whose files changed since the last poll. Returns the ids of the shaders
+    /// whose modules were replaced, logging those that failed to load.
+    fn poll(&mut self, device: &Arc<Device>) -> Vec<WatchedShaderId> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last_poll| now - last_poll < self.poll_interval)
+        {
+            return vec![];
+        }
+        self.last_poll = Some(now);
+        let mut reloaded = vec![];
+        for (index, shader) in self.shaders.iter_mut().enumerate() {
+            let modified = modified_time(&shader.path);
+            if modified.is_none() || modified == shader.modified {
+                continue;
+            }
+            shader.modified = modified;
+            // Safety: upheld by the caller of `watch`
+            match unsafe { load_shader(device.clone(), &shader.path) } {
+                Ok(module) => {
+                    shader.module = module;
+                    reloaded.push(WatchedShaderId(index));
+                }
+                Err(e) => error!(
+                    "Failed to reload shader {}, keeping the previous one: {}",
+                    shader.path.display(),
+                    e
+                ),
+            }
+        }
+        reloaded
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+unsafe fn load_shader(
+    device: Arc<Device>,
+    path: &Path,
+) -> Result<Arc<ShaderModule>, ShaderReloadError> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() % 4 != 0 {
+        return Err(ShaderReloadError::InvalidLength(bytes.len()));
+    }
+    Ok(ShaderModule::from_bytes(device, &bytes)?)
+}
+
+/// Reloads changed shaders and notifies their users once the device is idle
+fn reload_shaders_system(
+    mut reloader: ResMut<ShaderReloader>,
+    context: Res<BevyVulkanoContext>,
+    mut reloaded_events: EventWriter<ShaderReloaded>,
+) {
+    let reloaded = reloader.poll(context.context.device());
+    if reloaded.is_empty() {
+        return;
+    }
+    if let Err(e) = context.wait_idle() {
+        warn!("Failed to wait for device idle: {}", e);
+    }
+    for id in reloaded {
+        info!("Reloaded shader {}", reloader.path(id).display());
+        reloaded_events.send(ShaderReloaded {
+            id,
+            path: reloader.path(id).to_path_buf(),
+            module: reloader.module(id),
+        });
+    }
+}
+
+/// Errors that can happen when loading a watched shader.
+#[derive(Debug)]
+pub enum ShaderReloadError {
+    /// Reading the file failed.
+    Io(io::Error),
+    /// The file size isn't a multiple of 4 bytes, so it can't be SPIR-V.
+    InvalidLength(usize),
+    /// Creating the shader module failed.
+    Creation(ShaderCreationError),
+}
+
+impl fmt::Display for ShaderReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderReloadError::Io(e) => write!(f, "failed to read shader: {}", e),
+            ShaderReloadError::InvalidLength(len) => {
+                write!(f, "shader is {} bytes, which isn't valid SPIR-V", len)
+            }
+            ShaderReloadError::Creation(e) => write!(f, "failed to create shader module: {}", e),
+        }
+    }
+}
+
+impl Error for ShaderReloadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShaderReloadError::Io(e) => Some(e),
+            ShaderReloadError::Creation(e) => Some(e),
+            ShaderReloadError::InvalidLength(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ShaderReloadError {
+    fn from(err: io::Error) -> Self {
+        ShaderReloadError::Io(err)
+    }
+}
+
+impl From<ShaderCreationError> for ShaderReloadError {
+    fn from(err: ShaderCreationError) -> Self {
+        ShaderReloadError::Creation(err)
+    }
+}
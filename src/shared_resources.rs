@@ -0,0 +1,219 @@
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    command_buffer::{allocator::CommandBufferAllocator, AutoCommandBufferBuilder, CopyError},
+    descriptor_set::{
+        layout::DescriptorSetLayout, DescriptorSetCreationError, PersistentDescriptorSet,
+        WriteDescriptorSet,
+    },
+    device::{Device, DeviceOwned},
+    image::ImageViewAbstract,
+    sampler::{Filter, Sampler},
+};
+
+use crate::{util::blit_image, BevyVulkanoContext, VulkanoWindowRenderer};
+
+/// Named images & buffers tied to the context rather than a window, so that a large texture can
+/// be created once and composited into several windows, e.g. in editor layouts.
+///
+/// Clone the resource to share it with other threads. Registered resources are held until
+/// removed, so they outlive windows being closed and swapchains being recreated. Resources in use
+/// by submitted frames are kept alive by those frames, so removing or replacing them is safe at
+/// any time. Everything registered must belong to the context's device, and images sampled from
+/// several queue families must be created with concurrent sharing.
+#[derive(Resource, Clone)]
+pub struct SharedResources {
+    device: Arc<Device>,
+    resources: Arc<RwLock<HashMap<String, SharedResource>>>,
+}
+
+/// A resource registered in [`SharedResources`]
+#[derive(Clone)]
+pub enum SharedResource {
+    Image(Arc<dyn ImageViewAbstract>),
+    /// Buffers are stored as bytes, use [`Subbuffer::reinterpret`] to get their type back
+    Buffer(Subbuffer<[u8]>),
+}
+
+#[allow(clippy::result_large_err)]
+impl SharedResources {
+    pub fn new(device: Arc<Device>) -> SharedResources {
+        SharedResources {
+            device,
+            resources: Arc::new(RwLock::new(HashMap::default())),
+        }
+    }
+
+    /// Registers `image_view` under `name`, returning the resource it replaced
+    pub fn insert_image(
+        &self,
+        name: impl Into<String>,
+        image_view: Arc<dyn ImageViewAbstract>,
+    ) -> Result<Option<SharedResource>, SharedResourceError> {
+        if image_view.device() != &self.device {
+            return Err(SharedResourceError::DeviceMismatch);
+        }
+        Ok(self.insert(name.into(), SharedResource::Image(image_view)))
+    }
+
+    /// Registers `buffer` under `name`, returning the resource it replaced
+    pub fn insert_buffer<T: BufferContents + ?Sized>(
+        &self,
+        name: impl Into<String>,
+        buffer: Subbuffer<T>,
+    ) -> Result<Option<SharedResource>, SharedResourceError> {
+        if buffer.device() != &self.device {
+            return Err(SharedResourceError::DeviceMismatch);
+        }
+        Ok(self.insert(name.into(), SharedResource::Buffer(buffer.into_bytes())))
+    }
+
+    fn insert(&self, name: String, resource: SharedResource) -> Option<SharedResource> {
+        self.resources.write().unwrap().insert(name, resource)
+    }
+
+    /// Unregisters the resource under `name`. It's freed once no frame uses it anymore.
+    pub fn remove(&self, name: &str) -> Option<SharedResource> {
+        self.resources.write().unwrap().remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<SharedResource> {
+        self.resources.read().unwrap().get(name).cloned()
+    }
+
+    /// Image registered under `name`, `None` if missing or a buffer
+    pub fn image(&self, name: &str) -> Option<Arc<dyn ImageViewAbstract>> {
+        match self.get(name) {
+            Some(SharedResource::Image(image_view)) => Some(image_view),
+            _ => None,
+        }
+    }
+
+    /// Buffer registered under `name`, `None` if missing or an image
+    pub fn buffer(&self, name: &str) -> Option<Subbuffer<[u8]>> {
+        match self.get(name) {
+            Some(SharedResource::Buffer(buffer)) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.resources.read().unwrap().contains_key(name)
+    }
+
+    /// Names of all registered resources
+    pub fn names(&self) -> Vec<String> {
+        self.resources.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.resources.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a descriptor set of `layout` with the image under `name` and `sampler` bound to
+    /// `binding`, for sampling it in a window's composite pass. The set is cached by the context,
+    /// so every window drawing the image with the same layout shares it. See
+    /// [`BevyVulkanoContext::get_descriptor_set`].
+    pub fn image_sampler_descriptor_set(
+        &self,
+        context: &BevyVulkanoContext,
+        name: &str,
+        layout: Arc<DescriptorSetLayout>,
+        binding: u32,
+        sampler: Arc<Sampler>,
+    ) -> Result<Arc<PersistentDescriptorSet>, SharedResourceError> {
+        let image_view = self
+            .image(name)
+            .ok_or_else(|| SharedResourceError::NotFound(name.to_string()))?;
+        Ok(
+            context.get_descriptor_set(layout, [WriteDescriptorSet::image_view_sampler(
+                binding, image_view, sampler,
+            )])?,
+        )
+    }
+
+    /// Records a blit of the image under `name` over `renderer`'s current swapchain image, scaled
+    /// to fit. The image needs `TRANSFER_SRC` usage and the swapchain images `TRANSFER_DST`, see
+    /// [`VulkanoWinitConfig::swapchain_image_usage`](crate::VulkanoWinitConfig::swapchain_image_usage).
+    pub fn blit_to_window<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        name: &str,
+        renderer: &VulkanoWindowRenderer,
+        filter: Filter,
+    ) -> Result<(), SharedResourceError> {
+        let image_view = self
+            .image(name)
+            .ok_or_else(|| SharedResourceError::NotFound(name.to_string()))?;
+        blit_image(
+            builder,
+            image_view.image().clone(),
+            renderer.swapchain_image_view().image().clone(),
+            filter,
+        )?;
+        Ok(())
+    }
+}
+
+/// Error returned by [`SharedResources`]
+#[derive(Debug)]
+pub enum SharedResourceError {
+    /// The resource belongs to another device than the context's.
+    DeviceMismatch,
+    /// No image is registered under the name.
+    NotFound(String),
+    DescriptorSet(DescriptorSetCreationError),
+    Blit(CopyError),
+}
+
+impl fmt::Display for SharedResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharedResourceError::DeviceMismatch => {
+                write!(
+                    f,
+                    "shared resource belongs to another device than the context"
+                )
+            }
+            SharedResourceError::NotFound(name) => {
+                write!(f, "no shared image is registered as {:?}", name)
+            }
+            SharedResourceError::DescriptorSet(e) => {
+                write!(f, "failed to create descriptor set: {}", e)
+            }
+            SharedResourceError::Blit(e) => write!(f, "failed to blit shared image: {}", e),
+        }
+    }
+}
+
+impl Error for SharedResourceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SharedResourceError::DescriptorSet(e) => Some(e),
+            SharedResourceError::Blit(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DescriptorSetCreationError> for SharedResourceError {
+    fn from(e: DescriptorSetCreationError) -> Self {
+        SharedResourceError::DescriptorSet(e)
+    }
+}
+
+impl From<CopyError> for SharedResourceError {
+    fn from(e: CopyError) -> Self {
+        SharedResourceError::Blit(e)
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use vulkano::{
+    device::{Device, DeviceOwned},
+    format::Format,
+    image::{
+        sys::{Image, ImageCreateInfo, RawImage},
+        ImageAccess, ImageAspects, ImageDescriptorLayouts, ImageDimensions, ImageError, ImageInner,
+        ImageLayout, ImageUsage, SampleCount,
+    },
+    memory::{
+        allocator::{
+            AllocationCreateInfo, AllocationType, MemoryAlloc, MemoryUsage, SuballocationCreateInfo,
+        },
+        DedicatedAllocation, MemoryPropertyFlags,
+    },
+};
+
+use crate::SharedMemoryAllocator;
+
+/// Depth or multisampled color attachment whose contents only live within a render pass, such as
+/// the depth attachment of window renderers. It's created with `TRANSIENT_ATTACHMENT` usage and
+/// placed in lazily allocated memory if the device has such a memory type (typically tiled
+/// mobile GPUs), so that it may take no memory at all. Otherwise it's placed in device local
+/// memory, see [`TransientAttachmentImage::is_lazily_allocated`].
+///
+/// The image can only be used as an attachment: it can't be sampled, copied or kept across render
+/// passes, so use store op `DontCare` for it.
+#[derive(Debug)]
+pub struct TransientAttachmentImage {
+    inner: Arc<Image>,
+    layout: ImageLayout,
+    layout_initialized: AtomicBool,
+    lazily_allocated: bool,
+}
+
+impl TransientAttachmentImage {
+    pub fn new(
+        allocator: &SharedMemoryAllocator,
+        dimensions: [u32; 2],
+        format: Format,
+        samples: SampleCount,
+    ) -> Result<Arc<TransientAttachmentImage>, ImageError> {
+        let is_depth_stencil = format
+            .aspects()
+            .intersects(ImageAspects::DEPTH | ImageAspects::STENCIL);
+        let (usage, layout) = if is_depth_stencil {
+            (
+                ImageUsage::DEPTH_STENCIL_ATTACHMENT,
+                ImageLayout::DepthStencilAttachmentOptimal,
+            )
+        } else {
+            (
+                ImageUsage::COLOR_ATTACHMENT,
+                ImageLayout::ColorAttachmentOptimal,
+            )
+        };
+        let raw_image = RawImage::new(allocator.device().clone(), ImageCreateInfo {
+            dimensions: ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
+            format: Some(format),
+            samples,
+            usage: usage | ImageUsage::TRANSIENT_ATTACHMENT,
+            ..Default::default()
+        })?;
+        let (allocation, lazily_allocated) = allocate(allocator, &raw_image)?;
+        let inner = Arc::new(
+            raw_image
+                .bind_memory([allocation])
+                .map_err(|(err, _, _)| err)?,
+        );
+        Ok(Arc::new(TransientAttachmentImage {
+            inner,
+            layout,
+            layout_initialized: AtomicBool::new(false),
+            lazily_allocated,
+        }))
+    }
+
+    /// Whether the image was placed in lazily allocated memory
+    pub fn is_lazily_allocated(&self) -> bool {
+        self.lazily_allocated
+    }
+
+    pub fn dimensions(&self) -> ImageDimensions {
+        self.inner.dimensions()
+    }
+}
+
+/// Allocates from a lazily allocated memory type if there's one the image can use, falling back to
+/// device local memory. Returns whether the allocation is lazy.
+fn allocate(
+    allocator: &SharedMemoryAllocator,
+    raw_image: &RawImage,
+) -> Result<(MemoryAlloc, bool), ImageError> {
+    let requirements = raw_image.memory_requirements()[0];
+    let memory_types = &allocator
+        .device()
+        .physical_device()
+        .memory_properties()
+        .memory_types;
+    let lazy_type = (0..memory_types.len() as u32).find(|&index| {
+        requirements.memory_type_bits & (1 << index) != 0
+            && memory_types[index as usize]
+                .property_flags
+                .intersects(MemoryPropertyFlags::LAZILY_ALLOCATED)
+    });
+    if let Some(memory_type_index) = lazy_type {
+        // Custom allocators may not support lazily allocated memory, fall back if so
+        if let Ok(allocation) =
+            allocator.allocate_from_type(memory_type_index, SuballocationCreateInfo {
+                layout: requirements.layout,
+                allocation_type: AllocationType::NonLinear,
+                ..Default::default()
+            })
+        {
+            return Ok((allocation, true));
+        }
+    }
+    let allocation = allocator.allocate(
+        requirements,
+        AllocationType::NonLinear,
+        AllocationCreateInfo {
+            usage: MemoryUsage::DeviceOnly,
+            ..Default::default()
+        },
+        Some(DedicatedAllocation::Image(raw_image)),
+    )?;
+    Ok((allocation, false))
+}
+
+unsafe impl ImageAccess for TransientAttachmentImage {
+    fn inner(&self) -> ImageInner<'_> {
+        ImageInner {
+            image: &self.inner,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: 1,
+        }
+    }
+
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        self.layout
+    }
+
+    fn final_layout_requirement(&self) -> ImageLayout {
+        self.layout
+    }
+
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        // Transient attachments can only be read as input attachments
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::ShaderReadOnlyOptimal,
+            sampled_image: ImageLayout::ShaderReadOnlyOptimal,
+            input_attachment: ImageLayout::ShaderReadOnlyOptimal,
+        })
+    }
+
+    unsafe fn layout_initialized(&self) {
+        self.layout_initialized.store(true, Ordering::SeqCst);
+    }
+
+    fn is_layout_initialized(&self) -> bool {
+        self.layout_initialized.load(Ordering::SeqCst)
+    }
+}
+
+unsafe impl DeviceOwned for TransientAttachmentImage {
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+impl PartialEq for TransientAttachmentImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for TransientAttachmentImage {}
+
+impl Hash for TransientAttachmentImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
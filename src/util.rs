@@ -0,0 +1,475 @@
+//! Helpers for commands every pipeline ends up needing.
+//!
+//! vulkano's [`AutoCommandBufferBuilder`] records the barriers & layout transitions for every image
+//! used in a command buffer, including swapchain images, so [`transition_image_layout`] is only
+//! needed when recording with an [`UnsafeCommandBufferBuilder`].
+use std::{error::Error, fmt, sync::Arc};
+
+use bevy::math::{IVec2, Vec2};
+use vulkano::{
+    command_buffer::{
+        allocator::CommandBufferAllocator, sys::UnsafeCommandBufferBuilder,
+        AutoCommandBufferBuilder, BlitImageInfo, CommandBufferBeginError,
+        CommandBufferInheritanceInfo, CommandBufferInheritanceRenderPassType,
+        CommandBufferInheritanceRenderingInfo, CommandBufferUsage, CopyError, ExecuteCommandsError,
+        ImageBlit, SecondaryAutoCommandBuffer, SecondaryCommandBufferAbstract,
+    },
+    device::{Device, DeviceOwned},
+    format::{Format, FormatFeatures},
+    image::{ImageAccess, ImageLayout, ImageSubresourceLayers, ImageUsage, SampleCount},
+    pipeline::graphics::render_pass::PipelineRenderPassType,
+    sampler::Filter,
+    sync::{AccessFlags, DependencyInfo, ImageMemoryBarrier, PipelineStages},
+};
+
+/// Records a blit of the whole `src` image over the whole `dst` image, scaling if their sizes
+/// differ. Works with swapchain images and your own images, as long as `src` was created with
+/// `TRANSFER_SRC` and `dst` with `TRANSFER_DST` usage.
+///
+/// `Filter::Linear` falls back to `Filter::Nearest` if the source format doesn't support linear
+/// filtering (e.g. depth formats).
+#[allow(clippy::result_large_err)]
+pub fn blit_image<L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    src: Arc<dyn ImageAccess>,
+    dst: Arc<dyn ImageAccess>,
+    filter: Filter,
+) -> Result<(), CopyError> {
+    let filter = if filter == Filter::Linear && !supports_linear_filter(src.device(), src.format())
+    {
+        Filter::Nearest
+    } else {
+        filter
+    };
+    builder.blit_image(BlitImageInfo {
+        filter,
+        ..BlitImageInfo::images(src, dst)
+    })?;
+    Ok(())
+}
+
+/// Records a barrier transitioning all mip levels & array layers of `image` from `old_layout` to
+/// `new_layout`, with the aspects of its format. Works with swapchain images and your own images.
+/// Commands using the image in `old_layout` before the barrier complete before those using it in
+/// `new_layout` after it, see [`layout_stages_access`].
+///
+/// # Safety
+///
+/// - `image` must be in `old_layout` (or `old_layout` must be `ImageLayout::Undefined`, discarding
+///   its contents) when the barrier executes.
+/// - `new_layout` must not be `ImageLayout::Undefined` or `ImageLayout::Preinitialized`.
+/// - `image` must not be used concurrently by other queues when the barrier executes.
+pub unsafe fn transition_image_layout(
+    builder: &mut UnsafeCommandBufferBuilder,
+    image: Arc<dyn ImageAccess>,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+) {
+    let (src_stages, src_access) = layout_stages_access(old_layout);
+    let (dst_stages, dst_access) = layout_stages_access(new_layout);
+    builder.pipeline_barrier(&DependencyInfo {
+        image_memory_barriers: [ImageMemoryBarrier {
+            src_stages,
+            src_access,
+            dst_stages,
+            dst_access,
+            old_layout,
+            new_layout,
+            subresource_range: image.subresource_range(),
+            ..ImageMemoryBarrier::image(image.inner().image.clone())
+        }]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    });
+}
+
+/// Pipeline stages & accesses of the commands using an image in `layout`, which the barriers of
+/// [`transition_image_layout`] wait for (when leaving `layout`) or block (when entering it).
+/// Layouts without a specific use wait for or block all commands.
+pub fn layout_stages_access(layout: ImageLayout) -> (PipelineStages, AccessFlags) {
+    match layout {
+        ImageLayout::Undefined => (PipelineStages::TOP_OF_PIPE, AccessFlags::empty()),
+        ImageLayout::Preinitialized => (PipelineStages::HOST, AccessFlags::HOST_WRITE),
+        ImageLayout::ColorAttachmentOptimal => (
+            PipelineStages::COLOR_ATTACHMENT_OUTPUT,
+            AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE,
+        ),
+        ImageLayout::DepthStencilAttachmentOptimal => (
+            PipelineStages::EARLY_FRAGMENT_TESTS | PipelineStages::LATE_FRAGMENT_TESTS,
+            AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        ),
+        ImageLayout::DepthStencilReadOnlyOptimal => (
+            PipelineStages::EARLY_FRAGMENT_TESTS
+                | PipelineStages::LATE_FRAGMENT_TESTS
+                | PipelineStages::FRAGMENT_SHADER,
+            AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | AccessFlags::SHADER_READ,
+        ),
+        ImageLayout::ShaderReadOnlyOptimal => (
+            PipelineStages::VERTEX_SHADER
+                | PipelineStages::FRAGMENT_SHADER
+                | PipelineStages::COMPUTE_SHADER,
+            AccessFlags::SHADER_READ,
+        ),
+        ImageLayout::TransferSrcOptimal => {
+            (PipelineStages::ALL_TRANSFER, AccessFlags::TRANSFER_READ)
+        }
+        ImageLayout::TransferDstOptimal => {
+            (PipelineStages::ALL_TRANSFER, AccessFlags::TRANSFER_WRITE)
+        }
+        // Presentation waits on semaphores, which make the writes available
+        ImageLayout::PresentSrc => (PipelineStages::BOTTOM_OF_PIPE, AccessFlags::empty()),
+        _ => (
+            PipelineStages::ALL_COMMANDS,
+            AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+        ),
+    }
+}
+
+/// Whether images of `format` with optimal tiling can be sampled with linear filtering
+pub fn supports_linear_filter(device: &Arc<Device>, format: Format) -> bool {
+    device
+        .physical_device()
+        .format_properties(format)
+        .is_ok_and(|properties| {
+            properties
+                .optimal_tiling_features
+                .intersects(FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR)
+        })
+}
+
+/// Records blits generating mip levels `1..` of `image` from level 0, each level downsampled from
+/// the previous one with linear filtering. Level 0 must already hold the image data, e.g. record
+/// this after the upload copy. vulkano transitions each level between transfer source &
+/// destination layouts as needed. The command buffer must be for a graphics queue.
+///
+/// Returns an error if `image` wasn't created with `TRANSFER_SRC` & `TRANSFER_DST` usage, or if
+/// its format doesn't support linear blits, in which case upload precomputed mip levels instead.
+#[allow(clippy::result_large_err)]
+pub fn generate_mipmaps<L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    image: Arc<dyn ImageAccess>,
+) -> Result<(), MipmapError> {
+    let required_usage = ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST;
+    if !image.usage().contains(required_usage) {
+        return Err(MipmapError::MissingUsage(required_usage - image.usage()));
+    }
+    let required_features = FormatFeatures::BLIT_SRC
+        | FormatFeatures::BLIT_DST
+        | FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR;
+    let supports_linear_blit = image
+        .device()
+        .physical_device()
+        .format_properties(image.format())
+        .is_ok_and(|properties| {
+            properties
+                .optimal_tiling_features
+                .contains(required_features)
+        });
+    if !supports_linear_blit {
+        return Err(MipmapError::LinearBlitUnsupported(image.format()));
+    }
+    let dimensions = image.dimensions();
+    for level in 1..image.mip_levels() {
+        let src_size = dimensions
+            .mip_level_dimensions(level - 1)
+            .unwrap()
+            .width_height_depth();
+        let dst_size = dimensions
+            .mip_level_dimensions(level)
+            .unwrap()
+            .width_height_depth();
+        builder.blit_image(BlitImageInfo {
+            regions: [ImageBlit {
+                src_subresource: ImageSubresourceLayers {
+                    mip_level: level - 1,
+                    ..image.subresource_layers()
+                },
+                src_offsets: [[0; 3], src_size],
+                dst_subresource: ImageSubresourceLayers {
+                    mip_level: level,
+                    ..image.subresource_layers()
+                },
+                dst_offsets: [[0; 3], dst_size],
+                ..Default::default()
+            }]
+            .into(),
+            filter: Filter::Linear,
+            ..BlitImageInfo::images(image.clone(), image.clone())
+        })?;
+    }
+    Ok(())
+}
+
+/// Errors that can happen in [`generate_mipmaps`].
+#[derive(Debug)]
+pub enum MipmapError {
+    /// The image lacks these usages, which are needed to blit between its mip levels.
+    MissingUsage(ImageUsage),
+    /// The image format doesn't support blits with linear filtering.
+    LinearBlitUnsupported(Format),
+    /// Recording a blit failed.
+    Blit(CopyError),
+}
+
+impl fmt::Display for MipmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MipmapError::MissingUsage(usage) => {
+                write!(
+                    f,
+                    "image is missing {:?} usage for mipmap generation",
+                    usage
+                )
+            }
+            MipmapError::LinearBlitUnsupported(format) => write!(
+                f,
+                "format {:?} doesn't support linear blits, upload precomputed mip levels instead",
+                format
+            ),
+            MipmapError::Blit(e) => write!(f, "failed to blit mip level: {}", e),
+        }
+    }
+}
+
+impl Error for MipmapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MipmapError::Blit(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CopyError> for MipmapError {
+    fn from(err: CopyError) -> Self {
+        MipmapError::Blit(err)
+    }
+}
+
+/// Maps a position in physical pixels with the origin at the bottom left of a window of
+/// `window_size`, like bevy's cursor positions, to pixel coordinates of an image stretched over
+/// the whole window. Image coordinates have their origin at the top left, so for a 100x100 window
+/// and a 10x10 image the corners map as:
+///
+/// ```
+/// use bevy::math::{IVec2, Vec2};
+/// use bevy_vulkano::util::window_to_image_coords;
+///
+/// let corner = |x, y| window_to_image_coords(Vec2::new(x, y), [100, 100], [10, 10]);
+/// // Bottom left
+/// assert_eq!(corner(0.0, 0.0), Some(IVec2::new(0, 9)));
+/// // Top left
+/// assert_eq!(corner(0.0, 100.0), Some(IVec2::new(0, 0)));
+/// // Top right
+/// assert_eq!(corner(100.0, 100.0), Some(IVec2::new(9, 0)));
+/// // Bottom right
+/// assert_eq!(corner(100.0, 0.0), Some(IVec2::new(9, 9)));
+/// ```
+///
+/// Results are clamped to the image. Returns `None` if the window or image is empty.
+pub fn window_to_image_coords(
+    position: Vec2,
+    window_size: [u32; 2],
+    image_dimensions: [u32; 2],
+) -> Option<IVec2> {
+    if window_size.contains(&0) || image_dimensions.contains(&0) {
+        return None;
+    }
+    let normalized = Vec2::new(
+        position.x / window_size[0] as f32,
+        1.0 - position.y / window_size[1] as f32,
+    );
+    let max = IVec2::new(
+        image_dimensions[0] as i32 - 1,
+        image_dimensions[1] as i32 - 1,
+    );
+    let coords = (normalized * Vec2::new(image_dimensions[0] as f32, image_dimensions[1] as f32))
+        .floor()
+        .as_ivec2();
+    Some(coords.clamp(IVec2::ZERO, max))
+}
+
+/// Derives the inheritance info of secondary command buffers drawing with a graphics pipeline
+/// created for `render_pass`. For dynamic rendering, `samples` must match the sample count of the
+/// attachments passed to `begin_rendering`. It's ignored for render passes, where the subpass
+/// defines it.
+pub fn render_pass_inheritance(
+    render_pass: &PipelineRenderPassType,
+    samples: SampleCount,
+) -> CommandBufferInheritanceRenderPassType {
+    match render_pass {
+        PipelineRenderPassType::BeginRenderPass(subpass) => subpass.clone().into(),
+        PipelineRenderPassType::BeginRendering(rendering_info) => {
+            CommandBufferInheritanceRenderingInfo {
+                view_mask: rendering_info.view_mask,
+                color_attachment_formats: rendering_info.color_attachment_formats.clone(),
+                depth_attachment_format: rendering_info.depth_attachment_format,
+                stencil_attachment_format: rendering_info.stencil_attachment_format,
+                rasterization_samples: samples,
+            }
+            .into()
+        }
+    }
+}
+
+/// Begins a one time submit secondary command buffer to be executed within `render_pass`, e.g.
+/// from [`render_pass_inheritance`]. Each recording thread should create its own builder, which
+/// the [`StandardCommandBufferAllocator`](vulkano::command_buffer::allocator::StandardCommandBufferAllocator)
+/// shared through the context supports with a pool per thread. Execute the results with
+/// [`execute_secondaries`].
+#[allow(clippy::result_large_err)]
+pub fn secondary_command_buffer_builder<A: CommandBufferAllocator>(
+    allocator: &A,
+    queue_family_index: u32,
+    render_pass: impl Into<CommandBufferInheritanceRenderPassType>,
+) -> Result<
+    AutoCommandBufferBuilder<SecondaryAutoCommandBuffer<A::Alloc>, A>,
+    CommandBufferBeginError,
+> {
+    AutoCommandBufferBuilder::secondary(
+        allocator,
+        queue_family_index,
+        CommandBufferUsage::OneTimeSubmit,
+        CommandBufferInheritanceInfo {
+            render_pass: Some(render_pass.into()),
+            ..Default::default()
+        },
+    )
+}
+
+/// Records the execution of `command_buffers` in order. The render pass or dynamic rendering
+/// must have been begun with `SubpassContents::SecondaryCommandBuffers`.
+#[allow(clippy::result_large_err)]
+pub fn execute_secondaries<L, A, C>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    command_buffers: Vec<C>,
+) -> Result<(), ExecuteCommandsError>
+where
+    A: CommandBufferAllocator,
+    C: SecondaryCommandBufferAbstract + 'static,
+{
+    builder.execute_commands_from_vec(command_buffers)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::{
+        command_buffer::{
+            allocator::{
+                CommandBufferAllocator, CommandBufferBuilderAlloc, StandardCommandBufferAllocator,
+            },
+            sys::CommandBufferBeginInfo,
+            CommandBufferLevel,
+        },
+        device::{DeviceCreateInfo, Queue, QueueCreateInfo, QueueFlags},
+        image::AttachmentImage,
+        instance::{Instance, InstanceCreateInfo},
+        memory::allocator::StandardMemoryAllocator,
+        VulkanLibrary,
+    };
+
+    use super::*;
+
+    /// Device & graphics queue of the first physical device, `None` on machines without a Vulkan
+    /// driver, where the tests below pass without recording anything
+    fn device_and_queue() -> Option<(Arc<Device>, Arc<Queue>)> {
+        let library = VulkanLibrary::new().ok()?;
+        let instance = Instance::new(library, InstanceCreateInfo {
+            enumerate_portability: true,
+            ..Default::default()
+        })
+        .ok()?;
+        let physical_device = instance.enumerate_physical_devices().ok()?.next()?;
+        let queue_family_index = physical_device
+            .queue_family_properties()
+            .iter()
+            .position(|properties| properties.queue_flags.intersects(QueueFlags::GRAPHICS))?
+            as u32;
+        let (device, mut queues) = Device::new(physical_device, DeviceCreateInfo {
+            queue_create_infos: vec![QueueCreateInfo {
+                queue_family_index,
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .ok()?;
+        Some((device, queues.next()?))
+    }
+
+    fn transfer_image(
+        allocator: &StandardMemoryAllocator,
+        dimensions: [u32; 2],
+    ) -> Arc<AttachmentImage> {
+        AttachmentImage::with_usage(
+            allocator,
+            dimensions,
+            Format::R8G8B8A8_UNORM,
+            ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn blit_image_builds() {
+        let Some((device, queue)) = device_and_queue() else {
+            return;
+        };
+        let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
+        let command_buffer_allocator =
+            StandardCommandBufferAllocator::new(device, Default::default());
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        blit_image(
+            &mut builder,
+            transfer_image(&memory_allocator, [64, 32]),
+            transfer_image(&memory_allocator, [16, 16]),
+            Filter::Linear,
+        )
+        .unwrap();
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn transition_image_layout_builds() {
+        let Some((device, queue)) = device_and_queue() else {
+            return;
+        };
+        let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
+        let command_buffer_allocator =
+            StandardCommandBufferAllocator::new(device, Default::default());
+        let alloc = command_buffer_allocator
+            .allocate(queue.queue_family_index(), CommandBufferLevel::Primary, 1)
+            .unwrap()
+            .next()
+            .unwrap();
+        let image = transfer_image(&memory_allocator, [16, 16]);
+        unsafe {
+            let mut builder =
+                UnsafeCommandBufferBuilder::new(alloc.inner(), CommandBufferBeginInfo {
+                    usage: CommandBufferUsage::OneTimeSubmit,
+                    ..Default::default()
+                })
+                .unwrap();
+            transition_image_layout(
+                &mut builder,
+                image.clone(),
+                ImageLayout::Undefined,
+                ImageLayout::TransferDstOptimal,
+            );
+            transition_image_layout(
+                &mut builder,
+                image,
+                ImageLayout::TransferDstOptimal,
+                ImageLayout::TransferSrcOptimal,
+            );
+            assert!(builder.build().is_ok());
+        }
+    }
+}
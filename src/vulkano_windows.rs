@@ -2,32 +2,35 @@
 
 use bevy::{
     math::IVec2,
-    prelude::{Commands, Entity},
+    prelude::{Commands, Component, Entity},
     utils::{
         hashbrown::hash_map::{Iter, IterMut},
         HashMap,
     },
     window::{
-        MonitorSelection, PresentMode, RawHandleWrapper, Window, WindowMode, WindowResolution,
+        CursorGrabMode, MonitorSelection, RawHandleWrapper, Window, WindowMode, WindowResolution,
     },
 };
 #[cfg(feature = "gui")]
 use egui_winit_vulkano::{Gui, GuiConfig};
-use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
-use vulkano_util::{
-    context::VulkanoContext,
-    renderer::VulkanoWindowRenderer,
-    window::{
-        WindowDescriptor as VulkanoWindowDescriptor,
-        WindowResizeConstraints as VulkanoWindowResizeConstraints,
-    },
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+use vulkano::{format::Format, swapchain::ColorSpace};
+use vulkano_util::window::{
+    WindowDescriptor as VulkanoWindowDescriptor,
+    WindowResizeConstraints as VulkanoWindowResizeConstraints,
 };
 use winit::{
     dpi::{LogicalPosition, LogicalSize, PhysicalPosition},
-    window::{CursorGrabMode, WindowId},
+    error::ExternalError,
+    window::{BadIcon, Icon, WindowId},
 };
 
-use crate::VulkanoWinitConfig;
+use crate::{
+    converters, UserEvent, VulkanoContext, VulkanoWindowRenderer, VulkanoWinitConfig,
+    WindowFrameTarget,
+};
 
 fn window_descriptor_to_vulkano_window_descriptor(
     wd: &Window,
@@ -45,18 +48,12 @@ fn window_descriptor_to_vulkano_window_descriptor(
     };
     window_descriptor.scale_factor_override = wd.resolution.scale_factor_override();
     window_descriptor.title = wd.title.clone();
-    window_descriptor.present_mode = match wd.present_mode {
-        PresentMode::Fifo => vulkano::swapchain::PresentMode::Fifo,
-        PresentMode::Immediate => vulkano::swapchain::PresentMode::Immediate,
-        PresentMode::Mailbox => vulkano::swapchain::PresentMode::Mailbox,
-        PresentMode::AutoNoVsync => vulkano::swapchain::PresentMode::Immediate,
-        PresentMode::AutoVsync => vulkano::swapchain::PresentMode::FifoRelaxed,
-    };
+    window_descriptor.present_mode = converters::convert_present_mode(wd.present_mode);
     window_descriptor.resizable = wd.resizable;
     window_descriptor.decorations = wd.decorations;
     window_descriptor.cursor_visible = wd.cursor.visible;
     window_descriptor.cursor_locked = match wd.cursor.grab_mode {
-        bevy::window::CursorGrabMode::Locked => true,
+        CursorGrabMode::Locked => true,
         _ => false,
     };
     window_descriptor.mode = match wd.mode {
@@ -69,6 +66,31 @@ fn window_descriptor_to_vulkano_window_descriptor(
     window_descriptor
 }
 
+/// Swapchain formats & color spaces to try first for a window, before
+/// [`VulkanoWinitConfig::preferred_surface_formats`]. Insert it along with the [`Window`] when
+/// spawning it, e.g. to render one window in HDR and another in SDR. Each window's present mode is
+/// taken from its [`Window::present_mode`].
+#[derive(Component, Debug, Clone, Default)]
+pub struct PreferredSurfaceFormats(pub Vec<(Format, ColorSpace)>);
+
+/// Window mode for [`BevyVulkanoWindows::set_fullscreen`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FullscreenMode {
+    Windowed,
+    /// Borderless fullscreen on the current monitor
+    BorderlessFullscreen,
+    /// Exclusive fullscreen with the given video mode, see [`get_best_videomode`] and
+    /// [`get_fitting_videomode`]
+    SizedFullscreen(winit::monitor::VideoMode),
+}
+
+/// Cursor state of a window, see [`BevyVulkanoWindows::cursor_state`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorState {
+    pub grab_mode: CursorGrabMode,
+    pub visible: bool,
+}
+
 #[derive(Default)]
 pub struct BevyVulkanoWindows {
     #[cfg(not(feature = "gui"))]
@@ -79,17 +101,20 @@ pub struct BevyVulkanoWindows {
     pub(crate) entity_to_winit: HashMap<Entity, winit::window::WindowId>,
     /// Maps `winit` window identifiers to entities.
     pub(crate) winit_to_entity: HashMap<winit::window::WindowId, Entity>,
+    /// Cursor state applied to each window, as winit can't query it
+    pub(crate) cursor_states: HashMap<Entity, CursorState>,
 }
 
 impl BevyVulkanoWindows {
     pub fn create_window(
         &mut self,
         commands: &mut Commands,
-        event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<UserEvent>,
         window_entity: Entity,
         window: &Window,
+        preferred_surface_formats: Option<&PreferredSurfaceFormats>,
         vulkano_context: &VulkanoContext,
-        _config: &VulkanoWinitConfig,
+        config: &VulkanoWinitConfig,
     ) -> Window {
         #[cfg(target_os = "windows")]
         let mut winit_window_builder = {
@@ -204,21 +229,25 @@ impl BevyVulkanoWindows {
             };
 
         #[allow(unused_mut)]
-        let mut winit_window_builder = winit_window_builder.with_title(&window.title);
+        let mut winit_window_builder = winit_window_builder
+            .with_title(&window.title)
+            .with_window_icon(config.window_icon.clone());
 
         let winit_window = winit_window_builder.build(event_loop).unwrap();
 
-        match winit_window.set_cursor_grab(match window.cursor.grab_mode {
-            bevy::window::CursorGrabMode::Locked => CursorGrabMode::Locked,
-            bevy::window::CursorGrabMode::Confined => CursorGrabMode::Confined,
-            bevy::window::CursorGrabMode::None => CursorGrabMode::None,
-        }) {
-            Ok(_) => {}
-            Err(winit::error::ExternalError::NotSupported(_)) => {}
+        let grab_mode = match winit_window.set_cursor_grab(converters::convert_cursor_grab_mode(
+            window.cursor.grab_mode,
+        )) {
+            Ok(_) => window.cursor.grab_mode,
+            Err(ExternalError::NotSupported(_)) => CursorGrabMode::None,
             Err(err) => Err(err).unwrap(),
-        }
+        };
 
         winit_window.set_cursor_visible(window.cursor.visible);
+        self.cursor_states.insert(window_entity, CursorState {
+            grab_mode,
+            visible: window.cursor.visible,
+        });
 
         let winit_id = winit_window.id();
         self.entity_to_winit
@@ -253,6 +282,8 @@ impl BevyVulkanoWindows {
                 window,
                 position.map(|p| [p.x as f32, p.y as f32]),
             ),
+            config,
+            preferred_surface_formats.map_or(&[], |formats| &formats.0),
             move |ci| {
                 ci.image_format = Some(vulkano::format::Format::B8G8R8A8_SRGB);
             },
@@ -265,7 +296,7 @@ impl BevyVulkanoWindows {
                 window_renderer.surface(),
                 window_renderer.graphics_queue(),
                 GuiConfig {
-                    is_overlay: _config.is_gui_overlay,
+                    is_overlay: config.is_gui_overlay,
                     preferred_format: Some(window_renderer.swapchain_format()),
                     ..Default::default()
                 },
@@ -314,24 +345,24 @@ impl BevyVulkanoWindows {
     #[cfg(feature = "gui")]
     pub fn get_window_renderer_mut(
         &mut self,
-        id: WindowId,
+        entity: Entity,
     ) -> Option<&mut (VulkanoWindowRenderer, Gui)> {
-        self.window_id_to_winit
-            .get(&id)
+        self.entity_to_winit
+            .get(&entity)
             .and_then(|id| self.windows.get_mut(id))
     }
 
     #[cfg(feature = "gui")]
-    pub fn get_window_renderer(&self, id: WindowId) -> Option<&(VulkanoWindowRenderer, Gui)> {
-        self.window_id_to_winit
-            .get(&id)
+    pub fn get_window_renderer(&self, entity: Entity) -> Option<&(VulkanoWindowRenderer, Gui)> {
+        self.entity_to_winit
+            .get(&entity)
             .and_then(|id| self.windows.get(id))
     }
 
     #[cfg(feature = "gui")]
-    pub fn get_winit_window(&self, id: WindowId) -> Option<&winit::window::Window> {
-        self.window_id_to_winit
-            .get(&id)
+    pub fn get_winit_window(&self, entity: Entity) -> Option<&winit::window::Window> {
+        self.entity_to_winit
+            .get(&entity)
             .and_then(|id| self.windows.get(id))
             .map(|(v_window, _)| v_window.window())
     }
@@ -344,10 +375,127 @@ impl BevyVulkanoWindows {
             .map(|r| r.window())
     }
 
+    /// Raw window handle of the window, for interop with libraries using `raw-window-handle`. This
+    /// is the same handle the window's surface was created from.
+    pub fn raw_window_handle(&self, entity: Entity) -> Option<RawWindowHandle> {
+        self.get_winit_window(entity).map(|w| w.raw_window_handle())
+    }
+
+    /// Raw display handle of the window, for interop with libraries using `raw-window-handle`.
+    pub fn raw_display_handle(&self, entity: Entity) -> Option<RawDisplayHandle> {
+        self.get_winit_window(entity)
+            .map(|w| w.raw_display_handle())
+    }
+
+    /// Sets the window icon from RGBA8 pixels, e.g. from a loaded image. Returns an error if
+    /// `rgba` isn't `width * height * 4` bytes. Does nothing if the window doesn't exist.
+    pub fn set_window_icon(
+        &self,
+        entity: Entity,
+        rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), BadIcon> {
+        let icon = Icon::from_rgba(rgba, width, height)?;
+        if let Some(window) = self.get_winit_window(entity) {
+            window.set_window_icon(Some(icon));
+        }
+        Ok(())
+    }
+
+    /// Switches the window between windowed and fullscreen modes. The swapchain is recreated at the
+    /// new window size on next [`VulkanoWindowRenderer::acquire`]. Does nothing if the window
+    /// doesn't exist.
+    pub fn set_fullscreen(&mut self, entity: Entity, mode: FullscreenMode) {
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = self.get_window_renderer_mut(entity);
+        #[cfg(feature = "gui")]
+        let window_renderer = self
+            .get_window_renderer_mut(entity)
+            .map(|(window_renderer, _)| window_renderer);
+        if let Some(window_renderer) = window_renderer {
+            let window = window_renderer.window();
+            window.set_fullscreen(match mode {
+                FullscreenMode::Windowed => None,
+                FullscreenMode::BorderlessFullscreen => Some(
+                    winit::window::Fullscreen::Borderless(window.current_monitor()),
+                ),
+                FullscreenMode::SizedFullscreen(video_mode) => {
+                    Some(winit::window::Fullscreen::Exclusive(video_mode))
+                }
+            });
+            window_renderer.resize();
+        }
+    }
+
+    /// Grabs the cursor, confining it to the window or locking it in place, or releases it with
+    /// [`CursorGrabMode::None`]. Returns an error if the platform doesn't support `mode` (e.g.
+    /// `Locked` on Windows, `Confined` on macOS), in which case the grab is unchanged. Does nothing
+    /// if the window doesn't exist.
+    ///
+    /// The window's [`Window::cursor`] isn't updated, query the state with
+    /// [`BevyVulkanoWindows::cursor_state`].
+    pub fn set_cursor_grab(
+        &mut self,
+        entity: Entity,
+        mode: CursorGrabMode,
+    ) -> Result<(), ExternalError> {
+        let Some(window) = self.get_winit_window(entity) else {
+            return Ok(());
+        };
+        window.set_cursor_grab(converters::convert_cursor_grab_mode(mode))?;
+        if let Some(state) = self.cursor_states.get_mut(&entity) {
+            state.grab_mode = mode;
+        }
+        Ok(())
+    }
+
+    /// Shows or hides the cursor while it's over the window. Does nothing if the window doesn't
+    /// exist.
+    pub fn set_cursor_visible(&mut self, entity: Entity, visible: bool) {
+        let Some(window) = self.get_winit_window(entity) else {
+            return;
+        };
+        window.set_cursor_visible(visible);
+        if let Some(state) = self.cursor_states.get_mut(&entity) {
+            state.visible = visible;
+        }
+    }
+
+    /// Cursor grab & visibility of the window, as applied when it was created or by
+    /// [`BevyVulkanoWindows::set_cursor_grab`] & [`BevyVulkanoWindows::set_cursor_visible`].
+    /// winit releases grabs on some platforms when the window loses focus, which isn't reflected
+    /// here. `None` if the window doesn't exist.
+    pub fn cursor_state(&self, entity: Entity) -> Option<CursorState> {
+        self.cursor_states.get(&entity).copied()
+    }
+
     pub fn get_window_entity(&self, window_id: WindowId) -> Option<Entity> {
         self.winit_to_entity.get(&window_id).cloned()
     }
 
+    /// Current frame targets of all windows, for recording each window's commands in parallel.
+    /// The renderers themselves aren't `Send`, so the pattern is:
+    /// 1. [`VulkanoWindowRenderer::acquire`] each window on the main thread.
+    /// 2. Record each window's command buffers on worker threads (e.g. bevy's `ComputeTaskPool`)
+    ///    using these targets. The allocators shared through [`VulkanoContext`] are safe to use
+    ///    concurrently, they keep separate pools per thread.
+    /// 3. Execute the command buffers after each window's acquire future and
+    ///    [`VulkanoWindowRenderer::present`] on the main thread. Each window waits on its own frame
+    ///    fences, see [`VulkanoWindowRenderer::wait_for_frames`].
+    pub fn frame_targets(&self) -> Vec<(Entity, WindowFrameTarget)> {
+        self.winit_to_entity
+            .iter()
+            .filter_map(|(id, entity)| {
+                #[cfg(not(feature = "gui"))]
+                let window_renderer = self.windows.get(id)?;
+                #[cfg(feature = "gui")]
+                let (window_renderer, _) = self.windows.get(id)?;
+                Some((*entity, window_renderer.frame_target()))
+            })
+            .collect()
+    }
+
     #[cfg(not(feature = "gui"))]
     pub fn iter(&self) -> Iter<winit::window::WindowId, VulkanoWindowRenderer> {
         self.windows.iter()